@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use errormake::errormake;
+use log::info;
+
+use super::{find_sources, run_setup_command, FieldInfo};
+
+/// Default timeout for assembly programs, in seconds, per test case
+const DEFAULT_TIMEOUT: u64 = 5;
+
+/// The name of the binary which is linked in each student's directory
+const BINARY_NAME: &str = "main";
+
+/// This struct represents a configuration for running a program written
+/// in assembly (for NASM).
+///
+/// See `AsmConfig::from_toml` for docs on how to create one.
+pub struct AsmConfig {
+    name: String,
+    test_data_dirs: Option<Vec<String>>,
+    timeout: Option<Duration>,
+    args: Vec<String>,
+    nasm_flags: Vec<String>,
+    linker: String,
+    ld_flags: Vec<String>,
+    target_dir: String,
+}
+
+impl AsmConfig {
+    /// The fields which can be given in an assembly configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "nasm_flags",
+            "An array of flags to pass to nasm for each file (default: [\"-f\", \"elf64\"])",
+        ),
+        FieldInfo::optional(
+            "linker",
+            "The program to link with, such as \"ld\" or \"gcc\" (default: \"ld\")",
+        ),
+        FieldInfo::optional(
+            "ld_flags",
+            "An array of flags to pass to the linker (default: empty)",
+        ),
+    ];
+
+    /// Required fields in the toml:
+    ///  - "name": A name for this test
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "target_dir": The directory containing all student
+    ///    submissions (each submission as its own directory).
+    ///
+    /// Each `*.asm` file in a student's directory is assembled with
+    /// `nasm` into an object file next to it, and then they are all
+    /// linked into a binary named "main" in the same directory, which
+    /// is run for each case.
+    ///
+    /// Optional fields in the toml:
+    ///  - "timeout": Should be the number of seconds to allow before
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
+    ///  - "args": Should be an array of arguments to pass to the
+    ///    program being tested. Default: empty array
+    ///  - "nasm_flags": An array of flags to pass to nasm when
+    ///    assembling each file. Default: `["-f", "elf64"]`
+    ///  - "linker": The program which links the object files, usually
+    ///    "ld", or "gcc" for programs which use the C library. Default:
+    ///    "ld"
+    ///  - "ld_flags": An array of flags to pass to the linker (such as
+    ///    `["-no-pie"]` for gcc). Default: empty array
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<AsmConfig, AsmConfigError<std::convert::Infallible>> {
+        let name = match conf.get("name") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(AsmConfigError::with_description(
+                "Missing \"name\" field".to_string(),
+            )),
+            _ => Err(AsmConfigError::with_description(
+                "\"name\" field should be a string".to_string(),
+            )),
+        }?;
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(AsmConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(AsmConfigError::with_description(
+                "Missing \"tests_dir\" field".to_string(),
+            )),
+            _ => Err(AsmConfigError::with_description(
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
+            )),
+        }?;
+        let timeout = match conf.get("timeout") {
+            Some(toml::Value::Integer(seconds)) => Ok(Some(Duration::new(*seconds as u64, 0))),
+            Some(toml::Value::Float(seconds)) => Ok(Some(Duration::new(
+                *seconds as u64,
+                ((seconds % 1.0) * 1e9) as u32,
+            ))),
+            None | Some(toml::Value::Boolean(true)) => Ok(Some(Duration::new(DEFAULT_TIMEOUT, 0))),
+            Some(toml::Value::Boolean(false)) => Ok(None),
+            _ => Err(AsmConfigError::with_description(
+                "\"timeout\", if specified, should be a number or boolean".to_string(),
+            )),
+        }?;
+        let args: Vec<String> = match conf.get("args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    toml::Value::Array(_) | toml::Value::Table(_) => {
+                        Err(AsmConfigError::with_description(
+                            "Args may not contain nested structures".to_string(),
+                        ))
+                    }
+                    toml::Value::Integer(i) => Ok(format!("{}", i)),
+                    toml::Value::Float(f) => Ok(format!("{}", f)),
+                    toml::Value::Boolean(b) => Ok(format!("{}", b)),
+                    toml::Value::Datetime(d) => Ok(format!("{}", d)),
+                })
+                .collect(),
+            _ => Err(AsmConfigError::with_description(
+                "\"args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let nasm_flags: Vec<String> = match conf.get("nasm_flags") {
+            None => Ok(vec![String::from("-f"), String::from("elf64")]),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(AsmConfigError::with_description(
+                        "\"nasm_flags\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(AsmConfigError::with_description(
+                "\"nasm_flags\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let linker = match conf.get("linker") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Ok(String::from("ld")),
+            _ => Err(AsmConfigError::with_description(
+                "\"linker\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let ld_flags: Vec<String> = match conf.get("ld_flags") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(AsmConfigError::with_description(
+                        "\"ld_flags\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(AsmConfigError::with_description(
+                "\"ld_flags\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let target_dir = match conf.get("target_dir") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(AsmConfigError::with_description(
+                "Missing \"target_dir\" field".to_string(),
+            )),
+            _ => Err(AsmConfigError::with_description(
+                "\"target_dir\" field must be a string".to_string(),
+            )),
+        }?;
+        Ok(AsmConfig {
+            name,
+            test_data_dirs,
+            timeout,
+            args,
+            nasm_flags,
+            linker,
+            ld_flags,
+            target_dir,
+        })
+    }
+}
+
+impl super::Config for AsmConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
+    }
+
+    fn case_timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    fn command(&self, student_dir: &str) -> String {
+        format!("{}/{}", student_dir, BINARY_NAME)
+    }
+
+    fn args(&self, _student_dir: &str) -> Vec<String> {
+        self.args.clone()
+    }
+
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        let source_files = find_sources(student_dir, "*.asm")?;
+        if source_files.is_empty() {
+            info!("{}: no .asm files to assemble", student_dir);
+            return Err(String::from("No .asm files to assemble"));
+        }
+        // Each object file is written next to its source, so everything
+        // stays inside the student's directory.
+        let object_files: Vec<std::path::PathBuf> = source_files
+            .iter()
+            .map(|source| source.with_extension("o"))
+            .collect();
+        for (source, object) in source_files.iter().zip(&object_files) {
+            run_setup_command(
+                Command::new("nasm")
+                    .args(&self.nasm_flags)
+                    .arg("-o")
+                    .arg(object)
+                    .arg(source),
+                student_dir,
+            )?;
+        }
+        run_setup_command(
+            Command::new(&self.linker)
+                .args(&self.ld_flags)
+                .arg("-o")
+                .arg(Path::new(student_dir).join(BINARY_NAME))
+                .args(&object_files),
+            student_dir,
+        )
+    }
+
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        vec![String::from("nasm"), self.linker.clone()]
+    }
+
+    fn target_dir(&self) -> &str {
+        &self.target_dir
+    }
+
+    fn env_vars(&self, _student_dir: &str) -> HashMap<String, String> {
+        // No work needs to be done
+        HashMap::new()
+    }
+}
+
+errormake!(#[doc="An error while interpreting Assembly configuration"] pub AsmConfigError);
+
+#[cfg(test)]
+mod tests {
+    use super::super::Config;
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let asm_config = AsmConfig::from_toml(
+            &"name = \"Asm\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nargs = [\"x\", 3]\nnasm_flags = [\"-f\", \"macho64\"]\nlinker = \"gcc\"\nld_flags = [\"-no-pie\"]\ntimeout = false\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["x", "3"], asm_config.args("home"));
+        assert_eq!(&None, asm_config.case_timeout());
+        assert_eq!(vec!["-f", "macho64"], asm_config.nasm_flags);
+        assert_eq!("gcc", asm_config.linker);
+        assert_eq!(vec!["-no-pie"], asm_config.ld_flags);
+        for bad in [
+            "name = \"Asm\"\ntests_dir = \"t\"\n",
+            "name = \"Asm\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nnasm_flags = \"-f elf64\"\n",
+            "name = \"Asm\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nld_flags = [1]\n",
+            "name = \"Asm\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nlinker = [\"gcc\"]\n",
+        ]
+        .iter()
+        {
+            assert!(AsmConfig::from_toml(&bad.parse().unwrap()).is_err());
+        }
+    }
+}
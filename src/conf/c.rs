@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use errormake::errormake;
+use log::info;
+
+use super::{find_sources, run_setup_command, FieldInfo};
+
+/// Default timeout for C and C++ programs, in seconds, per test case
+const DEFAULT_TIMEOUT: u64 = 5;
+
+/// The name of the binary which is compiled in each student's
+/// directory, if unspecified
+const DEFAULT_BINARY: &str = "main";
+
+/// Which language the students' code is written in
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Language {
+    C,
+    Cpp,
+}
+impl Language {
+    /// The compiler to use, if unspecified
+    fn default_compiler(self) -> &'static str {
+        match self {
+            Language::C => "gcc",
+            Language::Cpp => "g++",
+        }
+    }
+
+    /// The extensions of the source files to compile
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Language::C => &["c"],
+            Language::Cpp => &["cpp", "cc", "cxx"],
+        }
+    }
+}
+
+/// This struct represents a configuration for running a program written
+/// in C or C++.
+///
+/// See `CConfig::from_toml` for docs on how to create one.
+pub struct CConfig {
+    name: String,
+    test_data_dirs: Option<Vec<String>>,
+    timeout: Option<Duration>,
+    args: Vec<String>,
+    language: Language,
+    compiler: String,
+    flags: Vec<String>,
+    warnings_as_errors: bool,
+    output_binary: String,
+    target_dir: String,
+}
+
+impl CConfig {
+    /// The fields which can be given in a C or C++ configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "compiler",
+            "The compiler to use, such as \"clang\" (default: \"gcc\" for C, \"g++\" for C++)",
+        ),
+        FieldInfo::optional(
+            "flags",
+            "An array of flags to pass to the compiler, such as \"-O2\" or \"-lm\" (default: empty)",
+        ),
+        FieldInfo::optional(
+            "warnings_as_errors",
+            "Whether to compile with -Werror, so warnings are compile errors (default: false)",
+        ),
+        FieldInfo::optional(
+            "output_binary",
+            "The name of the compiled program in each student's directory (default: \"main\")",
+        ),
+    ];
+
+    /// Loads a configuration for C code (the "c" section).
+    ///
+    /// Required fields in the toml:
+    ///  - "name": A name for this test
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "target_dir": The directory containing all student
+    ///    submissions (each submission as its own directory).
+    ///
+    /// All of the source files in a student's directory (`*.c` for C,
+    /// and `*.cpp`, `*.cc`, or `*.cxx` for C++) are compiled together
+    /// into a binary in the same directory, which is run for each case.
+    /// If they don't compile, every case is a compile error.
+    ///
+    /// Optional fields in the toml:
+    ///  - "timeout": Should be the number of seconds to allow before
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
+    ///  - "args": Should be an array of arguments to pass to the
+    ///    program being tested. Default: empty array
+    ///  - "compiler": The compiler to run. Default: "gcc" for C, and
+    ///    "g++" for C++
+    ///  - "flags": An array of flags to pass to the compiler. They come
+    ///    after the source files, so libraries (such as "-lm") can be
+    ///    given here too. Default: empty array
+    ///  - "warnings_as_errors": Whether to compile with `-Werror`, so
+    ///    that code which the compiler warns about is a compile error.
+    ///    Only the warnings which are turned on (such as with "-Wall" in
+    ///    "flags") count. Default: false
+    ///  - "output_binary": The name of the compiled program, which is
+    ///    put in the student's directory. Default: "main"
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<CConfig, CConfigError<std::convert::Infallible>> {
+        Self::from_toml_for(conf, Language::C)
+    }
+
+    /// Loads a configuration for C++ code (the "cpp" section).
+    ///
+    /// This takes the same fields as `CConfig::from_toml`.
+    pub fn cpp_from_toml(
+        conf: &toml::Value,
+    ) -> Result<CConfig, CConfigError<std::convert::Infallible>> {
+        Self::from_toml_for(conf, Language::Cpp)
+    }
+
+    fn from_toml_for(
+        conf: &toml::Value,
+        language: Language,
+    ) -> Result<CConfig, CConfigError<std::convert::Infallible>> {
+        let name = match conf.get("name") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(CConfigError::with_description(
+                "Missing \"name\" field".to_string(),
+            )),
+            _ => Err(CConfigError::with_description(
+                "\"name\" field should be a string".to_string(),
+            )),
+        }?;
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(CConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(CConfigError::with_description(
+                "Missing \"tests_dir\" field".to_string(),
+            )),
+            _ => Err(CConfigError::with_description(
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
+            )),
+        }?;
+        let timeout = match conf.get("timeout") {
+            Some(toml::Value::Integer(seconds)) => Ok(Some(Duration::new(*seconds as u64, 0))),
+            Some(toml::Value::Float(seconds)) => Ok(Some(Duration::new(
+                *seconds as u64,
+                ((seconds % 1.0) * 1e9) as u32,
+            ))),
+            None | Some(toml::Value::Boolean(true)) => Ok(Some(Duration::new(DEFAULT_TIMEOUT, 0))),
+            Some(toml::Value::Boolean(false)) => Ok(None),
+            _ => Err(CConfigError::with_description(
+                "\"timeout\", if specified, should be a number or boolean".to_string(),
+            )),
+        }?;
+        let args: Vec<String> = match conf.get("args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    toml::Value::Array(_) | toml::Value::Table(_) => {
+                        Err(CConfigError::with_description(
+                            "Args may not contain nested structures".to_string(),
+                        ))
+                    }
+                    toml::Value::Integer(i) => Ok(format!("{}", i)),
+                    toml::Value::Float(f) => Ok(format!("{}", f)),
+                    toml::Value::Boolean(b) => Ok(format!("{}", b)),
+                    toml::Value::Datetime(d) => Ok(format!("{}", d)),
+                })
+                .collect(),
+            _ => Err(CConfigError::with_description(
+                "\"args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let compiler = match conf.get("compiler") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Ok(String::from(language.default_compiler())),
+            _ => Err(CConfigError::with_description(
+                "\"compiler\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let flags: Vec<String> = match conf.get("flags") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(CConfigError::with_description(
+                        "\"flags\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(CConfigError::with_description(
+                "\"flags\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let warnings_as_errors = match conf.get("warnings_as_errors") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(CConfigError::with_description(
+                "\"warnings_as_errors\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let output_binary = match conf.get("output_binary") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Ok(String::from(DEFAULT_BINARY)),
+            _ => Err(CConfigError::with_description(
+                "\"output_binary\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let target_dir = match conf.get("target_dir") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(CConfigError::with_description(
+                "Missing \"target_dir\" field".to_string(),
+            )),
+            _ => Err(CConfigError::with_description(
+                "\"target_dir\" field must be a string".to_string(),
+            )),
+        }?;
+        Ok(CConfig {
+            name,
+            test_data_dirs,
+            timeout,
+            args,
+            language,
+            compiler,
+            flags,
+            warnings_as_errors,
+            output_binary,
+            target_dir,
+        })
+    }
+}
+
+impl super::Config for CConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
+    }
+
+    fn case_timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    fn command(&self, student_dir: &str) -> String {
+        format!("{}/{}", student_dir, self.output_binary)
+    }
+
+    fn args(&self, _student_dir: &str) -> Vec<String> {
+        self.args.clone()
+    }
+
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        let mut source_files: Vec<PathBuf> = Vec::new();
+        for extension in self.language.extensions() {
+            source_files.extend(find_sources(student_dir, &format!("*.{}", extension))?);
+        }
+        if source_files.is_empty() {
+            info!("{}: no source files to compile", student_dir);
+            return Err(String::from("No source files to compile"));
+        }
+        let mut command = Command::new(&self.compiler);
+        command
+            .arg("-o")
+            .arg(Path::new(student_dir).join(&self.output_binary))
+            .args(&source_files)
+            .args(&self.flags);
+        if self.warnings_as_errors {
+            command.arg("-Werror");
+        }
+        run_setup_command(&mut command, student_dir)
+    }
+
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        vec![self.compiler.clone()]
+    }
+
+    fn target_dir(&self) -> &str {
+        &self.target_dir
+    }
+
+    fn env_vars(&self, _student_dir: &str) -> HashMap<String, String> {
+        // No work needs to be done
+        HashMap::new()
+    }
+}
+
+errormake!(#[doc="An error while interpreting C or C++ configuration"] pub CConfigError);
+
+#[cfg(test)]
+mod tests {
+    use super::super::Config;
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let c_config = CConfig::from_toml(
+            &"name = \"C\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("gcc", c_config.compiler);
+        assert!(c_config.flags.is_empty());
+        assert!(!c_config.warnings_as_errors);
+        assert_eq!("home/main", c_config.command("home"));
+        let cpp_config = CConfig::cpp_from_toml(
+            &"name = \"C++\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nargs = [\"x\", 3]\ncompiler = \"clang++\"\nflags = [\"-O2\", \"-std=c++17\"]\noutput_binary = \"prog\"\ntimeout = false\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["x", "3"], cpp_config.args("home"));
+        assert_eq!(&None, cpp_config.case_timeout());
+        assert_eq!("clang++", cpp_config.compiler);
+        assert_eq!(vec!["-O2", "-std=c++17"], cpp_config.flags);
+        assert_eq!("home/prog", cpp_config.command("home"));
+        for bad in [
+            "name = \"C\"\ntests_dir = \"t\"\n",
+            "name = \"C\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nflags = \"-O2\"\n",
+            "name = \"C\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\ncompiler = [\"gcc\"]\n",
+            "name = \"C\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\noutput_binary = 1\n",
+        ]
+        .iter()
+        {
+            assert!(CConfig::from_toml(&bad.parse().unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        let student = tempfile::tempdir().unwrap();
+        let student_dir = student.path().to_str().unwrap();
+        let config = CConfig::cpp_from_toml(
+            &"name = \"C++\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        // There's nothing to compile yet
+        assert_eq!(
+            Err(String::from("No source files to compile")),
+            config.do_setup(student_dir)
+        );
+        std::fs::write(
+            student.path().join("main.cpp"),
+            "#include <iostream>\nint twice(int);\nint main() { std::cout << twice(21) << std::endl; }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            student.path().join("twice.cc"),
+            "int twice(int x) { return 2 * x; }\n",
+        )
+        .unwrap();
+        assert!(config.do_setup(student_dir).is_ok());
+        let output = Command::new(config.command(student_dir)).output().unwrap();
+        assert_eq!("42\n", String::from_utf8_lossy(&output.stdout));
+        std::fs::write(student.path().join("twice.cc"), "int twice(int x) {\n").unwrap();
+        assert!(config
+            .do_setup(student_dir)
+            .unwrap_err()
+            .contains("twice.cc"));
+    }
+}
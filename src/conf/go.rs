@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::process::Command;
+use std::time::Duration;
+
+use errormake::errormake;
+use log::info;
+
+use super::{find_sources, run_setup_command, FieldInfo};
+
+/// Default timeout for Go programs, in seconds, per test case
+const DEFAULT_TIMEOUT: u64 = 5;
+
+/// The name of the binary which is built in each student's directory,
+/// if unspecified
+const DEFAULT_BINARY: &str = "main";
+
+/// What is passed to `go build` to say what to build
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum BuildTarget {
+    /// All of the `.go` files in the student's directory (except tests)
+    Sources,
+    /// A package, such as "." or "./cmd/main", which needs a `go.mod`
+    Package(String),
+    /// A single file, relative to the student's directory
+    MainFile(String),
+}
+
+/// This struct represents a configuration for building and running a
+/// program written in Go.
+///
+/// See `GoConfig::from_toml` for docs on how to create one.
+pub struct GoConfig {
+    name: String,
+    test_data_dirs: Option<Vec<String>>,
+    timeout: Option<Duration>,
+    args: Vec<String>,
+    build_target: BuildTarget,
+    build_flags: Vec<String>,
+    output_binary: String,
+    target_dir: String,
+}
+
+impl GoConfig {
+    /// The fields which can be given in a Go configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "package",
+            "The package to build, such as \".\" or \"./cmd/main\" (default: the .go files in the student's directory)",
+        ),
+        FieldInfo::optional(
+            "main_file",
+            "The one file to build, instead of a package (default: the .go files in the student's directory)",
+        ),
+        FieldInfo::optional(
+            "build_flags",
+            "An array of flags to pass to `go build`, such as \"-race\" (default: empty)",
+        ),
+        FieldInfo::optional(
+            "output_binary",
+            "The name of the built program in each student's directory (default: \"main\")",
+        ),
+    ];
+
+    /// Required fields in the toml:
+    ///  - "name": A name for this test
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "target_dir": The directory containing all student
+    ///    submissions (each submission as its own directory).
+    ///
+    /// Each student's program is built with `go build`, run in their
+    /// directory, into a binary in the same directory, which is run for
+    /// each case. If it doesn't build, every case is a compile error.
+    ///
+    /// Optional fields in the toml:
+    ///  - "timeout": Should be the number of seconds to allow before
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
+    ///  - "args": Should be an array of arguments to pass to the
+    ///    program being tested. Default: empty array
+    ///  - "package": The package to build, relative to the student's
+    ///    directory (such as "." or "./cmd/main"). This needs the
+    ///    student to have a `go.mod`. Default: the `.go` files in the
+    ///    student's directory (other than `_test.go` files) are built
+    ///    together, which works without a `go.mod`
+    ///  - "main_file": A single file to build, relative to the student's
+    ///    directory (such as "main.go"), instead of a package. This
+    ///    can't be given along with "package". Default: as for "package"
+    ///  - "build_flags": An array of flags to pass to `go build`, before
+    ///    what is being built. Default: empty array
+    ///  - "output_binary": The name of the built program, which is put in
+    ///    the student's directory. Default: "main"
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<GoConfig, GoConfigError<std::convert::Infallible>> {
+        let name = match conf.get("name") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(GoConfigError::with_description(
+                "Missing \"name\" field".to_string(),
+            )),
+            _ => Err(GoConfigError::with_description(
+                "\"name\" field should be a string".to_string(),
+            )),
+        }?;
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(GoConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(GoConfigError::with_description(
+                "Missing \"tests_dir\" field".to_string(),
+            )),
+            _ => Err(GoConfigError::with_description(
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
+            )),
+        }?;
+        let timeout = match conf.get("timeout") {
+            Some(toml::Value::Integer(seconds)) => Ok(Some(Duration::new(*seconds as u64, 0))),
+            Some(toml::Value::Float(seconds)) => Ok(Some(Duration::new(
+                *seconds as u64,
+                ((seconds % 1.0) * 1e9) as u32,
+            ))),
+            None | Some(toml::Value::Boolean(true)) => Ok(Some(Duration::new(DEFAULT_TIMEOUT, 0))),
+            Some(toml::Value::Boolean(false)) => Ok(None),
+            _ => Err(GoConfigError::with_description(
+                "\"timeout\", if specified, should be a number or boolean".to_string(),
+            )),
+        }?;
+        let args: Vec<String> = match conf.get("args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    toml::Value::Array(_) | toml::Value::Table(_) => {
+                        Err(GoConfigError::with_description(
+                            "Args may not contain nested structures".to_string(),
+                        ))
+                    }
+                    toml::Value::Integer(i) => Ok(format!("{}", i)),
+                    toml::Value::Float(f) => Ok(format!("{}", f)),
+                    toml::Value::Boolean(b) => Ok(format!("{}", b)),
+                    toml::Value::Datetime(d) => Ok(format!("{}", d)),
+                })
+                .collect(),
+            _ => Err(GoConfigError::with_description(
+                "\"args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let build_target = match (conf.get("package"), conf.get("main_file")) {
+            (None, None) => Ok(BuildTarget::Sources),
+            (Some(toml::Value::String(s)), None) => Ok(BuildTarget::Package(s.clone())),
+            (None, Some(toml::Value::String(s))) => Ok(BuildTarget::MainFile(s.clone())),
+            (Some(_), Some(_)) => Err(GoConfigError::with_description(
+                "Only one of \"package\" and \"main_file\" may be given".to_string(),
+            )),
+            _ => Err(GoConfigError::with_description(
+                "\"package\" and \"main_file\", if specified, must be strings".to_string(),
+            )),
+        }?;
+        let build_flags: Vec<String> = match conf.get("build_flags") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(GoConfigError::with_description(
+                        "\"build_flags\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(GoConfigError::with_description(
+                "\"build_flags\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let output_binary = match conf.get("output_binary") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Ok(String::from(DEFAULT_BINARY)),
+            _ => Err(GoConfigError::with_description(
+                "\"output_binary\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let target_dir = match conf.get("target_dir") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(GoConfigError::with_description(
+                "Missing \"target_dir\" field".to_string(),
+            )),
+            _ => Err(GoConfigError::with_description(
+                "\"target_dir\" field must be a string".to_string(),
+            )),
+        }?;
+        Ok(GoConfig {
+            name,
+            test_data_dirs,
+            timeout,
+            args,
+            build_target,
+            build_flags,
+            output_binary,
+            target_dir,
+        })
+    }
+
+    /// What to pass to `go build` for the given student, relative to
+    /// their directory
+    fn build_args(&self, student_dir: &str) -> Result<Vec<OsString>, String> {
+        match &self.build_target {
+            BuildTarget::Sources => {
+                let sources: Vec<OsString> = find_sources(student_dir, "*.go")?
+                    .into_iter()
+                    .filter(|path| !path.to_string_lossy().ends_with("_test.go"))
+                    .filter_map(|path| path.file_name().map(|name| name.to_os_string()))
+                    .collect();
+                if sources.is_empty() {
+                    info!("{}: no source files to build", student_dir);
+                    return Err(String::from("No source files to compile"));
+                }
+                Ok(sources)
+            }
+            BuildTarget::Package(package) => Ok(vec![package.into()]),
+            BuildTarget::MainFile(file) => Ok(vec![file.into()]),
+        }
+    }
+}
+
+impl super::Config for GoConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
+    }
+
+    fn case_timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    fn command(&self, student_dir: &str) -> String {
+        format!("{}/{}", student_dir, self.output_binary)
+    }
+
+    fn args(&self, _student_dir: &str) -> Vec<String> {
+        self.args.clone()
+    }
+
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        let build_args = self.build_args(student_dir)?;
+        // It's built from the student's directory, so that their go.mod
+        // (if they have one) is used
+        run_setup_command(
+            Command::new("go")
+                .current_dir(student_dir)
+                .arg("build")
+                .arg("-o")
+                .arg(&self.output_binary)
+                .args(&self.build_flags)
+                .args(build_args),
+            student_dir,
+        )
+    }
+
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        vec![String::from("go")]
+    }
+
+    fn required_files(&self) -> Vec<String> {
+        match &self.build_target {
+            BuildTarget::MainFile(file) => vec![file.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn target_dir(&self) -> &str {
+        &self.target_dir
+    }
+
+    fn env_vars(&self, _student_dir: &str) -> HashMap<String, String> {
+        // No work needs to be done
+        HashMap::new()
+    }
+}
+
+errormake!(#[doc="An error while interpreting Go configuration"] pub GoConfigError);
+
+#[cfg(test)]
+mod tests {
+    use super::super::Config;
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = GoConfig::from_toml(
+            &"name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(BuildTarget::Sources, config.build_target);
+        assert!(config.build_flags.is_empty());
+        assert_eq!("home/main", config.command("home"));
+        assert!(config.required_files().is_empty());
+        let config = GoConfig::from_toml(
+            &"name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nargs = [\"x\", 3]\nmain_file = \"hello.go\"\nbuild_flags = [\"-race\"]\noutput_binary = \"hello\"\ntimeout = false\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["x", "3"], config.args("home"));
+        assert_eq!(&None, config.case_timeout());
+        assert_eq!(
+            BuildTarget::MainFile(String::from("hello.go")),
+            config.build_target
+        );
+        assert_eq!(vec!["-race"], config.build_flags);
+        assert_eq!("home/hello", config.command("home"));
+        assert_eq!(vec!["hello.go"], config.required_files());
+        for bad in [
+            "name = \"Go\"\ntests_dir = \"t\"\n",
+            "name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\npackage = \".\"\nmain_file = \"main.go\"\n",
+            "name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\npackage = [\".\"]\n",
+            "name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\nbuild_flags = \"-race\"\n",
+        ]
+        .iter()
+        {
+            assert!(GoConfig::from_toml(&bad.parse().unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_build_args() {
+        let student = tempfile::tempdir().unwrap();
+        let student_dir = student.path().to_str().unwrap();
+        let config = GoConfig::from_toml(
+            &"name = \"Go\"\ntests_dir = \"t\"\ntarget_dir = \"s\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        // There's nothing to build yet
+        assert_eq!(
+            Err(String::from("No source files to compile")),
+            config.do_setup(student_dir)
+        );
+        for file in ["main.go", "util.go", "main_test.go", "notes.txt"].iter() {
+            std::fs::write(student.path().join(file), "package main\n").unwrap();
+        }
+        let mut build_args = config.build_args(student_dir).unwrap();
+        build_args.sort();
+        assert_eq!(vec!["main.go", "util.go"], build_args);
+    }
+}
@@ -0,0 +1,71 @@
+use super::InterpretConfigError;
+
+/// A scale mapping percentages to letter grades.
+///
+/// Each grade has a threshold, and a percentage earns the grade with the
+/// highest threshold that it is at least.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeScale {
+    // Sorted from the highest threshold to the lowest
+    grades: Vec<(f64, String)>,
+}
+impl GradeScale {
+    /// Makes a scale from pairs of a threshold (as a percentage) and the
+    /// letter given at or above it
+    pub fn new(mut grades: Vec<(f64, String)>) -> Self {
+        grades.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        GradeScale { grades }
+    }
+
+    /// Reads the scale from the "grade_scale" table in the config file,
+    /// which maps each threshold to its letter, for example:
+    ///
+    /// ```toml
+    /// [grade_scale]
+    /// 90 = "A"
+    /// 80 = "B"
+    /// 0 = "F"
+    /// ```
+    ///
+    /// Thresholds which aren't whole numbers need to be quoted (as in
+    /// `"92.5" = "A"`).
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<GradeScale, InterpretConfigError<std::convert::Infallible>> {
+        let table = conf.as_table().ok_or_else(|| {
+            InterpretConfigError::with_description(String::from("\"grade_scale\" must be a table"))
+        })?;
+        if table.is_empty() {
+            return Err(InterpretConfigError::with_description(String::from(
+                "\"grade_scale\" must have at least one grade",
+            )));
+        }
+        let grades = table
+            .iter()
+            .map(|(threshold, letter)| {
+                let threshold = threshold.parse::<f64>().map_err(|_| {
+                    InterpretConfigError::with_description(format!(
+                        "Grade thresholds must be numbers, not {:?}",
+                        threshold
+                    ))
+                })?;
+                match letter {
+                    toml::Value::String(letter) => Ok((threshold, letter.clone())),
+                    _ => Err(InterpretConfigError::with_description(String::from(
+                        "Each grade in \"grade_scale\" must be a string",
+                    ))),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(GradeScale::new(grades))
+    }
+
+    /// Returns the letter for the given percentage, or None if it's
+    /// below every threshold
+    pub fn letter_for(&self, percent: f64) -> Option<&str> {
+        self.grades
+            .iter()
+            .find(|(threshold, _)| percent >= *threshold)
+            .map(|(_, letter)| letter.as_str())
+    }
+}
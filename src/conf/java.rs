@@ -1,10 +1,10 @@
 use std::collections::HashMap;
-use std::process::{Command, Stdio};
+use std::process::Command;
 use std::time::Duration;
 
 use errormake::errormake;
 
-use glob::glob;
+use super::{find_sources, run_setup_command, FieldInfo};
 
 /// Default timeout for java programs, in seconds, per test case
 const DEFAULT_TIMEOUT: u64 = 5;
@@ -14,30 +14,76 @@ const DEFAULT_TIMEOUT: u64 = 5;
 /// See `JavaConfig::from_toml` for docs on how to create one.
 pub struct JavaConfig {
     name: String,
-    test_data_dir: String,
+    test_data_dirs: Option<Vec<String>>,
     timeout: Option<Duration>,
     main_class: String,
     args: Vec<String>,
+    jvm_args: Vec<String>,
+    warnings_as_errors: bool,
     target_dir: String,
 }
 
 impl JavaConfig {
+    /// The fields which can be given in a java configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "main_class",
+            "The class containing a public static void main(String[] args) method to be run",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the java program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "jvm_args",
+            "An array of flags to pass to the JVM, such as \"-Xss8m\" (default: empty)",
+        ),
+        FieldInfo::optional(
+            "warnings_as_errors",
+            "Whether code which compiles with warnings counts as a compile error (default: false)",
+        ),
+    ];
+
     /// Required fields in the toml:
     ///  - "name": A name for this test
-    ///  - "tests_dir": The directory to contain input and output data
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
     ///  - "main_class": The class containing a public static void
+    ///    main(String[] args) method to be run.
     ///  - "target_dir": The directory containing all student
     ///    submissions (each submission as its own directory).
-    /// main(String[] args) method to be run.
     ///
     /// Optional fields in the toml:
     ///  - "timeout": Should be the number of seconds to allow before
-    /// timing out, `true` (use default timeout value), or `false`
-    /// (allow tested code to run however long it takes - not
-    /// recommended). Default: 5 seconds
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
     ///  - "args": Should be an array of arguments to pass to the java
-    /// program being tested. It will be passed directly to the String[]
-    /// args in the java program. Default: empty array.
+    ///    program being tested. It will be passed directly to the
+    ///    String[] args in the java program. Default: empty array.
+    ///  - "jvm_args": Should be an array of flags to pass to the JVM
+    ///    (for example, "-Xss8m" for a bigger stack). They are put
+    ///    before the main class, so they don't reach the java program.
+    ///    Default: empty array.
+    ///  - "warnings_as_errors": Whether to compile with `-Werror`, so
+    ///    that code which javac warns about (such as using APIs which
+    ///    are marked for removal) is a compile error. Default: false
     pub fn from_toml(
         conf: &toml::Value,
     ) -> Result<JavaConfig, JavaConfigError<std::convert::Infallible>> {
@@ -50,13 +96,24 @@ impl JavaConfig {
                 "\"name\" field should be a string".to_string(),
             )),
         }?;
-        let test_data_dir = match conf.get("tests_dir") {
-            Some(toml::Value::String(s)) => Ok(s.clone()),
-            None => Err(JavaConfigError::with_description(
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(JavaConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(JavaConfigError::with_description(
                 "Missing \"tests_dir\" field".to_string(),
             )),
             _ => Err(JavaConfigError::with_description(
-                "\"tests_dir\" field should be a string".to_string(),
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
             )),
         }?;
         let main_class = match conf.get("main_class") {
@@ -101,6 +158,28 @@ impl JavaConfig {
                 "\"args\", if specified, must be an array".to_string(),
             )),
         }?;
+        let jvm_args: Vec<String> = match conf.get("jvm_args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(JavaConfigError::with_description(
+                        "\"jvm_args\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(JavaConfigError::with_description(
+                "\"jvm_args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let warnings_as_errors = match conf.get("warnings_as_errors") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(JavaConfigError::with_description(
+                "\"warnings_as_errors\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
         let target_dir = match conf.get("target_dir") {
             Some(toml::Value::String(s)) => Ok(s.clone()),
             None => Err(JavaConfigError::with_description(
@@ -112,10 +191,12 @@ impl JavaConfig {
         }?;
         Ok(JavaConfig {
             name,
-            test_data_dir,
+            test_data_dirs,
             timeout,
             main_class,
             args,
+            jvm_args,
+            warnings_as_errors,
             target_dir,
         })
     }
@@ -126,8 +207,11 @@ impl super::Config for JavaConfig {
         &self.name
     }
 
-    fn test_type(&self) -> super::TestType {
-        super::TestType::Directory(&self.test_data_dir)
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
     }
 
     fn case_timeout(&self) -> &Option<Duration> {
@@ -139,30 +223,33 @@ impl super::Config for JavaConfig {
     }
 
     fn args(&self, _student_dir: &str) -> Vec<String> {
-        let mut args = self.args.clone();
-        args.insert(0, self.main_class.clone());
+        let mut args = self.jvm_args.clone();
+        args.push(self.main_class.clone());
+        args.extend(self.args.iter().cloned());
         args
     }
 
-    fn do_setup(&self, student_dir: &str) -> bool {
-        let source_glob = format!("{}/*.java", student_dir);
-        let source_files: Vec<std::path::PathBuf> = match match glob(&source_glob) {
-            Ok(files) => files,
-            Err(_) => return false,
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        let source_files = find_sources(student_dir, "*.java")?;
+        // Classes are looked up in, and written to, only the student's
+        // own directory, so that students never see each other's classes.
+        let mut javac = Command::new("javac");
+        if self.warnings_as_errors {
+            javac.arg("-Werror");
         }
-        .collect()
-        {
-            Ok(files) => files,
-            Err(_) => return false,
-        };
-        Command::new("javac")
-            .args(source_files)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .spawn()
-            .map_or(false, |mut child| {
-                child.wait().map_or(false, |signal| signal.success())
-            })
+        run_setup_command(
+            javac
+                .arg("-cp")
+                .arg(student_dir)
+                .arg("-d")
+                .arg(student_dir)
+                .args(source_files),
+            student_dir,
+        )
+    }
+
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        vec![String::from("javac")]
     }
 
     fn target_dir(&self) -> &str {
@@ -1,19 +1,43 @@
 //! Handles loading of configurations for tests
 
+mod asm;
+mod c;
+mod go;
+mod grade_scale;
 mod java;
+mod node;
+mod options;
+mod output;
 mod python;
+mod ruby;
+mod shell;
 
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::Read;
 use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
 use errormake::errormake;
+use glob::glob;
+use log::info;
 
+use crate::test::ComparisonOptions;
+
+pub use asm::AsmConfig;
+pub use c::CConfig;
+pub use go::GoConfig;
+pub use grade_scale::GradeScale;
 pub use java::JavaConfig;
+pub use node::NodeConfig;
+pub use options::{CompareStream, InlineCase, InputVia, RunOptions};
+pub use output::OutputPreferences;
 pub use python::PythonConfig;
+pub use ruby::RubyConfig;
+pub use shell::ShellConfig;
 
 /// This struct represents all of the configuration for a test run.
 ///
@@ -21,6 +45,10 @@ pub use python::PythonConfig;
 /// with some extra convenience methods about using it.
 pub struct TestConfig {
     config: Box<dyn Config>,
+    options: RunOptions,
+    grade_scale: Option<GradeScale>,
+    output: Option<OutputPreferences>,
+    weights: HashMap<String, f64>,
 }
 impl TestConfig {
     /// Returns a reference to the config contained in here
@@ -33,6 +61,36 @@ impl TestConfig {
         self.config.as_mut()
     }
 
+    /// Returns the language-independent options for this test run
+    pub fn options(&self) -> &RunOptions {
+        &self.options
+    }
+
+    /// Returns a mutable reference to the language-independent options
+    /// for this test run
+    pub fn options_mut(&mut self) -> &mut RunOptions {
+        &mut self.options
+    }
+
+    /// Returns the scale for turning scores into letter grades, if the
+    /// config has one
+    pub fn grade_scale(&self) -> Option<&GradeScale> {
+        self.grade_scale.as_ref()
+    }
+
+    /// Returns the output method to use when none is given on the
+    /// command line, if the config has one
+    pub fn output_preferences(&self) -> Option<&OutputPreferences> {
+        self.output.as_ref()
+    }
+
+    /// Returns the number of points each case in the config's "weights"
+    /// section is worth (cases which aren't in it are worth the default
+    /// number of points, unless the weights file gives them some)
+    pub fn weights(&self) -> &HashMap<String, f64> {
+        &self.weights
+    }
+
     /// Loads a given filename into a configuration
     ///
     /// See `TestConfig::from_toml_values` for information about what it
@@ -49,33 +107,107 @@ impl TestConfig {
     /// case-sensitive).
     ///
     /// The file should have one section header, whose name is the kind
-    /// of test being run. The available options currently are "java"
-    /// and "python".
+    /// of test being run. The available options currently are "java",
+    /// "python", "node" (or "javascript"), "ruby", "asm", "c", "cpp",
+    /// "go", and "command" (for any other language, run by a command
+    /// given in the config).
     ///
     /// Configuration options for java are at `JavaConfig::from_toml`.
     ///
     /// Configuration options for python are at `PythonConfig::from_toml`.
+    ///
+    /// Configuration options for JavaScript are at `NodeConfig::from_toml`.
+    ///
+    /// Configuration options for Ruby are at `RubyConfig::from_toml`.
+    ///
+    /// Configuration options for assembly are at `AsmConfig::from_toml`.
+    ///
+    /// Configuration options for C and C++ are at `CConfig::from_toml`.
+    ///
+    /// Configuration options for Go are at `GoConfig::from_toml`.
+    ///
+    /// Configuration options for commands are at `ShellConfig::from_toml`.
+    ///
+    /// Options which apply regardless of language are also read from
+    /// that section, and are documented at `RunOptions::from_toml`.
+    ///
+    /// There may also be a "comparison" section, containing the
+    /// "compare", "field_separator", "collapse_spaces", "ignore_case",
+    /// "float_tolerance", and "relative_tolerance" options. These
+    /// override the defaults for the language (see
+    /// `Config::default_comparison`), and are in turn overridden by those
+    /// options in the language section.
+    ///
+    /// There may also be a "grade_scale" section, mapping percentages to
+    /// letter grades (see `GradeScale::from_toml`).
+    ///
+    /// There may also be an "output" section, giving the output method
+    /// (and file) to use when they aren't given on the command line (see
+    /// `OutputPreferences::from_toml`).
+    ///
+    /// There may also be a "weights" section, mapping case names to the
+    /// number of points each is worth, such as `"case_1" = 2.5`. These
+    /// are used along with the "weights_file" option, and override it for
+    /// the cases given in both.
     pub fn from_toml_values(values: toml::Value) -> Result<TestConfig, Box<dyn Error + 'static>> {
         match values {
             toml::Value::Table(table) => {
-                if table.len() == 1 {
-                    let key = table.keys().find(|_| true).unwrap();
+                let comparison_table = table.get("comparison");
+                let grade_scale_table = table.get("grade_scale");
+                let output_table = table.get("output");
+                let weights_table = table.get("weights");
+                let expected_len = 1
+                    + comparison_table.iter().count()
+                    + grade_scale_table.iter().count()
+                    + output_table.iter().count()
+                    + weights_table.iter().count();
+                if table.len() == expected_len {
+                    let key = table
+                        .keys()
+                        .find(|key| {
+                            !["comparison", "grade_scale", "output", "weights"]
+                                .contains(&key.as_str())
+                        })
+                        .unwrap();
                     let value = table.get(key).unwrap();
+                    let config: Box<dyn Config> = match key.as_str() {
+                        "java" => Box::new(java::JavaConfig::from_toml(value)?),
+                        "python" => Box::new(python::PythonConfig::from_toml(value)?),
+                        "node" | "javascript" => Box::new(node::NodeConfig::from_toml(value)?),
+                        "ruby" => Box::new(ruby::RubyConfig::from_toml(value)?),
+                        "asm" => Box::new(asm::AsmConfig::from_toml(value)?),
+                        "c" => Box::new(c::CConfig::from_toml(value)?),
+                        "cpp" => Box::new(c::CConfig::cpp_from_toml(value)?),
+                        "go" => Box::new(go::GoConfig::from_toml(value)?),
+                        "command" => Box::new(shell::ShellConfig::from_toml(value)?),
+                        key => {
+                            return Err(Box::new(InterpretConfigError::with_description(format!(
+                                "Unrecognized config type: {}",
+                                key
+                            )))
+                            .into())
+                        }
+                    };
+                    let comparison = match comparison_table {
+                        Some(comparison_table) => options::comparison_from_toml(
+                            comparison_table,
+                            config.default_comparison(),
+                        )?,
+                        None => config.default_comparison(),
+                    };
                     Ok(TestConfig {
-                        config: match key.as_str() {
-                            "java" => Box::new(java::JavaConfig::from_toml(value)?),
-                            "python" => Box::new(python::PythonConfig::from_toml(value)?),
-                            key => {
-                                return Err(Box::new(InterpretConfigError::with_description(
-                                    format!("Unrecognized config type: {}", key),
-                                ))
-                                .into())
-                            }
-                        },
+                        config,
+                        options: RunOptions::from_toml(value, comparison)?,
+                        grade_scale: grade_scale_table.map(GradeScale::from_toml).transpose()?,
+                        output: output_table.map(OutputPreferences::from_toml).transpose()?,
+                        weights: weights_table
+                            .map(|weights| weights_from_toml(weights, "\"weights\""))
+                            .transpose()?
+                            .unwrap_or_default(),
                     })
                 } else {
                     Err(Box::new(InterpretConfigError::with_description(
-                        String::from("The config file should have exactly one section"),
+                        String::from("The config file should have exactly one language section"),
                     )))
                 }
             }
@@ -100,12 +232,15 @@ impl DerefMut for TestConfig {
 }
 
 /// The trait implemented by all supported configurations.
-pub trait Config {
+///
+/// Configurations may be shared between threads (for example, to set
+/// up several students at once), so they must be `Send` and `Sync`.
+pub trait Config: Send + Sync {
     /// A name for this set of tests
     fn name(&self) -> &str;
 
     /// The kind of test to run (see `TestType` for options)
-    fn test_type(&self) -> TestType;
+    fn test_type(&self) -> TestType<'_>;
 
     /// The amount of time to let code run before timing out
     fn case_timeout(&self) -> &Option<Duration>;
@@ -117,10 +252,32 @@ pub trait Config {
     fn args(&self, student_dir: &str) -> Vec<String>;
 
     /// Execute all necessary setup for the student in that folder
-    /// Returns true if the setup worked, and false if there was
-    /// an error which would prevent the code from running (i.e.
-    /// a compile error).
-    fn do_setup(&self, student_dir: &str) -> bool;
+    /// Returns `Ok(())` if the setup worked, and otherwise an error
+    /// which would prevent the code from running (i.e. a compile
+    /// error), with a message saying what went wrong (such as what the
+    /// compiler printed), which can be given to the student.
+    ///
+    /// Anything produced by setup (such as compiled classes) must be
+    /// written only inside `student_dir`, and setup may only read the
+    /// student's own files, so that students can be set up at the same
+    /// time without interfering with each other.
+    fn do_setup(&self, student_dir: &str) -> Result<(), String>;
+
+    /// The programs which `do_setup` runs (such as a compiler), so that
+    /// they can be checked for before anyone is set up. By default,
+    /// there are none.
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The files (relative to the student's directory) which every
+    /// submission must have, such as the file which is run. If any are
+    /// missing, the student's code isn't set up or run, and each case
+    /// is marked as `TestAnswer::NoSubmission`. By default, no files
+    /// are checked for.
+    fn required_files(&self) -> Vec<String> {
+        Vec::new()
+    }
 
     /// The directory containing all student submissions. Each student
     /// should have their own folder within this directory.
@@ -129,6 +286,87 @@ pub trait Config {
     /// Returns a HashMap containing all environment variables which
     /// should be set and their corresponding values
     fn env_vars(&self, student_dir: &str) -> HashMap<String, String>;
+
+    /// How output is compared for this language, unless the config file
+    /// says otherwise. By default, output must match exactly.
+    fn default_comparison(&self) -> ComparisonOptions {
+        ComparisonOptions::default()
+    }
+}
+
+/// A description of a single field which can be given in a config file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FieldInfo {
+    name: &'static str,
+    required: bool,
+    description: &'static str,
+}
+impl FieldInfo {
+    /// Describes a field which must be given
+    pub const fn required(name: &'static str, description: &'static str) -> Self {
+        FieldInfo {
+            name,
+            required: true,
+            description,
+        }
+    }
+
+    /// Describes a field which may be left out
+    pub const fn optional(name: &'static str, description: &'static str) -> Self {
+        FieldInfo {
+            name,
+            required: false,
+            description,
+        }
+    }
+
+    /// The name of the field
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether the field must be given
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// A short description of what the field does
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+}
+
+/// Returns all of the fields accepted in the section for the given kind
+/// of config (including the options shared between all kinds), or
+/// `None` if that kind of config doesn't exist.
+pub fn config_fields(kind: &str) -> Option<Vec<FieldInfo>> {
+    let fields = match kind {
+        "java" => JavaConfig::FIELDS,
+        "python" => PythonConfig::FIELDS,
+        "node" | "javascript" => NodeConfig::FIELDS,
+        "ruby" => RubyConfig::FIELDS,
+        "asm" => AsmConfig::FIELDS,
+        "c" | "cpp" => CConfig::FIELDS,
+        "go" => GoConfig::FIELDS,
+        "command" => ShellConfig::FIELDS,
+        _ => return None,
+    };
+    Some(fields.iter().chain(RunOptions::FIELDS).copied().collect())
+}
+
+/// Returns a human-readable listing of the required and optional fields
+/// for the given kind of config, or `None` if it doesn't exist.
+pub fn describe_schema(kind: &str) -> Option<String> {
+    let fields = config_fields(kind)?;
+    let mut description = format!("[{}]\n", kind);
+    for (heading, required) in [("Required fields:", true), ("Optional fields:", false)].iter() {
+        description.push_str(heading);
+        description.push('\n');
+        for field in fields.iter().filter(|f| f.is_required() == *required) {
+            description.push_str(&format!("  {}: {}\n", field.name(), field.description()));
+        }
+    }
+    Some(description)
 }
 
 errormake!(#[doc="An error in interpreting a config file"] pub InterpretConfigError);
@@ -136,12 +374,109 @@ errormake!(#[doc="An error in interpreting a config file"] pub InterpretConfigEr
 /// The different kinds of tests that can be done.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TestType<'a> {
-    /// Load in testing data from a directory.
+    /// Load in testing data from one or more directories.
     ///
     /// For each test case, there should be a file <test_case_name>.in
     /// and another file <test_case_name>.out, which contain,
-    /// respectively, the input and output for that test case.
-    Directory(&'a str),
+    /// respectively, the input and output for that test case. If the
+    /// expected output differs between platforms, it can be given in
+    /// <test_case_name>.out.windows or <test_case_name>.out.unix, which
+    /// is used instead of the .out file on that platform. An
+    /// empty .out file expects no output, and one containing just
+    /// `#nonempty` accepts any output that isn't empty. One whose first
+    /// line is `#regex-lines` has a regex for each line of the output on
+    /// the lines after it, and one whose first line is `#regex` has a
+    /// regex which the whole output must match on the lines after it
+    /// (which can also be given in a <test_case_name>.regex file instead
+    /// of the .out file). If there is also
+    /// a file <test_case_name>.skip, then the case is reported as
+    /// skipped instead of being run.
+    ///
+    /// Instead of a .out file, a case can have a <test_case_name>.url
+    /// file containing an http(s) URL, from which the expected output
+    /// is fetched at the start of the run. This needs stipulate to be
+    /// built with the "remote-tests" feature.
+    ///
+    /// The directory can also be a `.tar.gz` (or `.tgz`) or `.zip`
+    /// archive of the files, which is extracted when the cases are
+    /// loaded. This needs stipulate to be built with the "archive-tests"
+    /// feature.
+    ///
+    /// A case can also have a <test_case_name>.args file, with arguments
+    /// which are passed to the program after the ones in the config, for
+    /// just that case (one per line, or as a JSON array of strings). A
+    /// case with a .args file doesn't need a .in file, and is given no
+    /// input if it doesn't have one.
+    ///
+    /// When there are several directories, the cases in all of them are
+    /// run. If two have a case with the same name, the one in the later
+    /// directory is used, unless `RunOptions::error_on_duplicate_cases`
+    /// makes that an error.
+    Directory(&'a [String]),
+    /// There's no tests directory, and only the cases given in the
+    /// config file itself are run (see `RunOptions::inline_cases`).
+    Inline,
+}
+
+/// Reads a table of case weights, which maps case names to the
+/// (nonnegative) number of points each is worth. This is used for both
+/// the "weights" table in the config file and a `weights_file`, and
+/// `source` names which one it is for error messages.
+pub(crate) fn weights_from_toml(
+    conf: &toml::Value,
+    source: &str,
+) -> Result<HashMap<String, f64>, InterpretConfigError<std::convert::Infallible>> {
+    let weights = conf.as_table().ok_or_else(|| {
+        InterpretConfigError::with_description(format!("{} must be a table", source))
+    })?;
+    weights
+        .iter()
+        .map(|(case, points)| match points {
+            toml::Value::Integer(points) if *points >= 0 => Ok((case.clone(), *points as f64)),
+            toml::Value::Float(points) if *points >= 0.0 => Ok((case.clone(), *points)),
+            _ => Err(InterpretConfigError::with_description(format!(
+                "The weight for case \"{}\" in {} must be a nonnegative number",
+                case, source
+            ))),
+        })
+        .collect()
+}
+
+/// Runs a command used in setup (such as a compiler). If it exits with
+/// an error, the error is what it printed to stderr, and if it couldn't
+/// be run at all, the error says why. Either way, it's also logged at
+/// the info level.
+fn run_setup_command(command: &mut Command, student_dir: &str) -> Result<(), String> {
+    match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+    {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            info!(
+                "{}: setup failed with {}:\n{}",
+                student_dir, output.status, stderr
+            );
+            Err(stderr)
+        }
+        Err(e) => {
+            info!("{}: couldn't run setup: {}", student_dir, e);
+            Err(format!("Couldn't run setup: {}", e))
+        }
+    }
+}
+
+/// Returns the files in the student's directory matching the glob
+/// pattern (relative to the directory), or an error saying why they
+/// couldn't be listed.
+fn find_sources(student_dir: &str, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    glob(&format!("{}/{}", student_dir, pattern))
+        .map_err(|e| format!("Couldn't look for {} files: {}", pattern, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Couldn't look for {} files: {}", pattern, e))
 }
 
 /// Reads from an input stream until the input stream ends, and returns
@@ -164,7 +499,10 @@ mod tests {
                 .unwrap();
         let java_config = TestConfig::from_toml_values(java_toml).unwrap();
         assert_eq!("Test A", java_config.name());
-        assert_eq!(TestType::Directory("path/to/test"), java_config.test_type());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            java_config.test_type()
+        );
         assert_eq!("java", java_config.command("directory"));
         assert_eq!(vec!["Main"], java_config.args("directory"));
         assert_eq!(&Some(Duration::new(5, 0)), java_config.case_timeout());
@@ -172,7 +510,10 @@ mod tests {
         let java_toml: toml::Value = "[java]\nname = \"Test B\"\ntests_dir = \"path/to/test\"\nmain_class = \"MainB\"\ntimeout = 1\ntarget_dir = \"testb/sub\"\n".parse().unwrap();
         let java_config = TestConfig::from_toml_values(java_toml).unwrap();
         assert_eq!("Test B", java_config.name());
-        assert_eq!(TestType::Directory("path/to/test"), java_config.test_type());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            java_config.test_type()
+        );
         assert_eq!("java", java_config.command("home"));
         assert_eq!(vec!["MainB"], java_config.args("home"));
         assert_eq!(&Some(Duration::new(1, 0)), java_config.case_timeout());
@@ -180,7 +521,10 @@ mod tests {
         let java_toml: toml::Value = "[java]\nname = \"Test C\"\ntests_dir = \"path/to/test\"\nmain_class = \"OtherClass\"\ntimeout = false\ntarget_dir = \"testc/sub\"\n".parse().unwrap();
         let java_config = TestConfig::from_toml_values(java_toml).unwrap();
         assert_eq!("Test C", java_config.name());
-        assert_eq!(TestType::Directory("path/to/test"), java_config.test_type());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            java_config.test_type()
+        );
         assert_eq!("java", java_config.command("home"));
         assert_eq!(vec!["OtherClass"], java_config.args("home"));
         assert_eq!(&None, java_config.case_timeout());
@@ -189,7 +533,7 @@ mod tests {
         let python_config = TestConfig::from_toml_values(python_toml).unwrap();
         assert_eq!("Test A", python_config.name());
         assert_eq!(
-            TestType::Directory("path/to/test"),
+            TestType::Directory(&[String::from("path/to/test")]),
             python_config.test_type()
         );
         assert_eq!("python3", python_config.command("home"));
@@ -198,6 +542,606 @@ mod tests {
         assert_eq!("testa/pysub", python_config.target_dir());
     }
 
+    #[test]
+    fn test_node_from_toml() {
+        let node_toml: toml::Value = "[node]\nname = \"Test N\"\ntests_dir = \"path/to/test\"\nfile = \"main.js\"\ntarget_dir = \"testn/sub\"\n".parse().unwrap();
+        let node_config = TestConfig::from_toml_values(node_toml).unwrap();
+        assert_eq!("Test N", node_config.name());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            node_config.test_type()
+        );
+        assert_eq!("node", node_config.command("home"));
+        assert_eq!(vec!["home/main.js"], node_config.args("home"));
+        assert_eq!(&Some(Duration::new(5, 0)), node_config.case_timeout());
+        assert_eq!("testn/sub", node_config.target_dir());
+        assert_eq!(vec!["main.js"], node_config.required_files());
+        assert!(node_config.do_setup("home").is_ok());
+        let node_config = TestConfig::from_toml_values(
+            "[javascript]\nname = \"Test N\"\ntests_dir = \"t\"\nfile = \"main.js\"\ninterpreter = \"node18\"\nargs = [\"-v\", 3]\ntimeout = false\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("node18", node_config.command("dir"));
+        assert_eq!(vec!["dir/main.js", "-v", "3"], node_config.args("dir"));
+        assert_eq!(&None, node_config.case_timeout());
+        // The file to run is required
+        assert!(TestConfig::from_toml_values(
+            "[node]\nname = \"Test N\"\ntests_dir = \"t\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap()
+        )
+        .is_err());
+        assert!(config_fields("javascript").is_some());
+    }
+
+    #[test]
+    fn test_ruby_from_toml() {
+        let ruby_toml: toml::Value = "[ruby]\nname = \"Test R\"\ntests_dir = \"path/to/test\"\nfile = \"main.rb\"\ntarget_dir = \"testr/sub\"\n".parse().unwrap();
+        let ruby_config = TestConfig::from_toml_values(ruby_toml).unwrap();
+        assert_eq!("Test R", ruby_config.name());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            ruby_config.test_type()
+        );
+        assert_eq!("ruby", ruby_config.command("home"));
+        assert_eq!(vec!["home/main.rb"], ruby_config.args("home"));
+        assert_eq!(&Some(Duration::new(5, 0)), ruby_config.case_timeout());
+        assert_eq!("testr/sub", ruby_config.target_dir());
+        assert_eq!(vec!["main.rb"], ruby_config.required_files());
+        assert!(ruby_config.do_setup("home").is_ok());
+        assert!(ruby_config.env_vars("home").is_empty());
+        let ruby_config = TestConfig::from_toml_values(
+            "[ruby]\nname = \"Test R\"\ntests_dir = \"t\"\nfile = \"main.rb\"\ninterpreter = \"ruby3.2\"\nargs = [\"-v\", 3]\ntimeout = 2.5\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("ruby3.2", ruby_config.command("dir"));
+        assert_eq!(vec!["dir/main.rb", "-v", "3"], ruby_config.args("dir"));
+        assert_eq!(
+            &Some(Duration::from_millis(2500)),
+            ruby_config.case_timeout()
+        );
+        // The file to run is required
+        assert!(TestConfig::from_toml_values(
+            "[ruby]\nname = \"Test R\"\ntests_dir = \"t\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap()
+        )
+        .is_err());
+        assert!(describe_schema("ruby").unwrap().contains("interpreter"));
+    }
+
+    #[test]
+    fn test_command_from_toml() {
+        let command_config = TestConfig::from_toml_values(
+            "[command]\nname = \"Ruby\"\ntests_dir = \"path/to/test\"\ncommand = \"ruby\"\nargs = [\"{student_dir}/main.rb\", \"--size\", 3]\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("Ruby", command_config.name());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            command_config.test_type()
+        );
+        assert_eq!("ruby", command_config.command("d/alice"));
+        assert_eq!(
+            vec!["d/alice/main.rb", "--size", "3"],
+            command_config.args("d/alice")
+        );
+        assert_eq!(&Some(Duration::new(5, 0)), command_config.case_timeout());
+        assert!(command_config.do_setup("d/alice").is_ok());
+        let command_config = TestConfig::from_toml_values(
+            "[command]\nname = \"Rust\"\ntests_dir = \"t\"\ncommand = \"{student_dir}/target/release/main\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("d/bob/target/release/main", command_config.command("d/bob"));
+        assert!(command_config.args("d/bob").is_empty());
+        for bad in [
+            "command = \"\"\n",
+            "command = \"cat\"\nsetup = []\n",
+            "command = \"cat\"\nsetup = \"make\"\n",
+            "args = [\"x\"]\n",
+        ]
+        .iter()
+        {
+            assert!(TestConfig::from_toml_values(
+                format!(
+                    "[command]\nname = \"Bad\"\ntests_dir = \"t\"\ntarget_dir = \"d\"\n{}",
+                    bad
+                )
+                .parse()
+                .unwrap()
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_asm_from_toml() {
+        let asm_config = TestConfig::from_toml_values(
+            "[asm]\nname = \"Asm\"\ntests_dir = \"path/to/test\"\ntarget_dir = \"asm/sub\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("Asm", asm_config.name());
+        assert_eq!(
+            TestType::Directory(&[String::from("path/to/test")]),
+            asm_config.test_type()
+        );
+        assert_eq!("home/main", asm_config.command("home"));
+        assert!(asm_config.args("home").is_empty());
+        assert_eq!(&Some(Duration::new(5, 0)), asm_config.case_timeout());
+        assert_eq!("asm/sub", asm_config.target_dir());
+    }
+
+    #[test]
+    fn test_c_from_toml() {
+        for (kind, compiler) in [("c", "gcc"), ("cpp", "g++")].iter() {
+            let config = TestConfig::from_toml_values(
+                format!(
+                    "[{}]\nname = \"C\"\ntests_dir = \"path/to/test\"\ntarget_dir = \"c/sub\"\n",
+                    kind
+                )
+                .parse()
+                .unwrap(),
+            )
+            .unwrap();
+            assert_eq!("C", config.name());
+            assert_eq!(
+                TestType::Directory(&[String::from("path/to/test")]),
+                config.test_type()
+            );
+            assert_eq!("home/main", config.command("home"));
+            assert!(config.args("home").is_empty());
+            assert_eq!(&Some(Duration::new(5, 0)), config.case_timeout());
+            assert_eq!("c/sub", config.target_dir());
+            assert!(describe_schema(kind)
+                .unwrap()
+                .contains(&format!("\"{}\"", compiler)));
+        }
+    }
+
+    #[test]
+    fn test_multiple_tests_dirs() {
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = [\"t1\", \"t2\"]\nfile = \"a.py\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            TestType::Directory(&[String::from("t1"), String::from("t2")]),
+            config.test_type()
+        );
+        assert!(!config.options().error_on_duplicate_cases());
+        for bad in ["[]", "[\"t1\", 2]", "3"].iter() {
+            assert!(TestConfig::from_toml_values(
+                format!(
+                    "[python]\nname = \"Test A\"\ntests_dir = {}\nfile = \"a.py\"\ntarget_dir = \"d\"\n",
+                    bad
+                )
+                .parse()
+                .unwrap(),
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_output_preferences() {
+        let python_toml =
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\n";
+        let config = TestConfig::from_toml_values(python_toml.parse().unwrap()).unwrap();
+        assert_eq!(None, config.output_preferences());
+        let config = TestConfig::from_toml_values(
+            format!(
+                "{}[output]\nmode = \"csv\"\nfile = \"results.csv\"\n",
+                python_toml
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let output = config.output_preferences().unwrap();
+        assert_eq!("csv", output.mode());
+        assert_eq!(Some("results.csv"), output.file());
+        let config = TestConfig::from_toml_values(
+            format!("{}[output]\nmode = \"json\"\n", python_toml)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("json", config.output_preferences().unwrap().mode());
+        assert_eq!(None, config.output_preferences().unwrap().file());
+        assert!(TestConfig::from_toml_values(
+            format!("{}[output]\nfile = \"results.csv\"\n", python_toml)
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_python_multiple_files() {
+        let python_config = TestConfig::from_toml_values(
+            "[python]\nname = \"Multi\"\ntests_dir = \"t\"\nfiles = [\"main.py\", \"helpers.py\"]\ntarget_dir = \"test-data/python-multi-file\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["dir/main.py"], python_config.args("dir"));
+        assert!(python_config
+            .do_setup("test-data/python-multi-file/complete")
+            .is_ok());
+        assert!(python_config
+            .do_setup("test-data/python-multi-file/missing_helper")
+            .is_err());
+        assert!(python_config
+            .do_setup("test-data/python-multi-file/broken_helper")
+            .unwrap_err()
+            .contains("SyntaxError"));
+        let python_config = TestConfig::from_toml_values(
+            "[python]\nname = \"Multi\"\ntests_dir = \"t\"\nfile = \"main.py\"\nfiles = [\"helpers.py\", \"main.py\"]\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vec!["dir/main.py"], python_config.args("dir"));
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Multi\"\ntests_dir = \"t\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run_options() {
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!config.options().capture_output());
+        assert_eq!(None, config.options().max_message_len());
+        assert!(config.options().masks().is_empty());
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncapture_output = true\nmax_message_len = 80\nmask = [[\"/tmp/\\\\S+\", \"<DIR>\"]]\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(config.options().capture_output());
+        assert_eq!(Some(80), config.options().max_message_len());
+        assert_eq!(1, config.options().masks().len());
+        assert_eq!("<DIR>", config.options().masks()[0].apply("/tmp/abc"));
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncompare = \"sorted_lines\"\ncollapse_spaces = true\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            crate::test::TestAnswer::Success,
+            config
+                .options()
+                .comparator()
+                .compare("", "b  2\na 1\n", "a\t1\nb 2\n")
+        );
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncompare = \"trim_lines\"\nignore_case = true\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            crate::test::TestAnswer::Success,
+            config
+                .options()
+                .comparator()
+                .compare("", "YES\n", "yes  \n\n")
+        );
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\nself_check_line = \"OK\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            crate::test::TestAnswer::Success,
+            config.options().comparator().compare("", "7\n", "6\nOK\n")
+        );
+        assert_eq!(
+            super::CompareStream::Stdout,
+            config.options().compare_stream()
+        );
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncompare_stream = \"both\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            super::CompareStream::Both,
+            config.options().compare_stream()
+        );
+        assert!(config.options().ignore_folders());
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\nignore_folders = false\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!config.options().ignore_folders());
+        assert_eq!(config.options().timeout_retries(), 0);
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ntimeout_retries = 2\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.options().timeout_retries(), 2);
+        assert_eq!(super::InputVia::Stdin, config.options().input_via());
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ninput_via = \"file\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(super::InputVia::File, config.options().input_via());
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ninput_via = \"args\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ntimeout_retries = -1\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncompare_stream = \"stdin\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\nmask = [\"/tmp\"]\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_combined_comparison_flags() {
+        // Each output only passes with the options in its list
+        let outputs: &[(&str, &[&str])] = &[
+            ("A B\n", &[]),
+            ("a b\n", &["ignore_case"]),
+            ("A   B\n", &["collapse_whitespace"]),
+            ("A B \n\n", &["trim_lines"]),
+            (
+                "a\t b \n\n",
+                &["ignore_case", "collapse_whitespace", "trim_lines"],
+            ),
+        ];
+        let flags = ["ignore_case", "collapse_whitespace", "trim_lines"];
+        for enabled in 0..(1 << flags.len()) {
+            let is_enabled = |flag: &str| {
+                let index = flags.iter().position(|f| *f == flag).unwrap();
+                enabled & (1 << index) != 0
+            };
+            let config = TestConfig::from_toml_values(
+                format!(
+                    "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncompare = {:?}\nignore_case = {}\ncollapse_whitespace = {}\n",
+                    match is_enabled("trim_lines") {
+                        true => "trim_lines",
+                        false => "exact",
+                    },
+                    is_enabled("ignore_case"),
+                    is_enabled("collapse_whitespace")
+                )
+                .parse()
+                .unwrap(),
+            )
+            .unwrap();
+            for (output, needed) in outputs {
+                let result = config.options().comparator().compare("", "A B\n", output);
+                assert_eq!(
+                    result == crate::test::TestAnswer::Success,
+                    needed.iter().all(|flag| is_enabled(flag)),
+                    "{:?} with {:?}: {:?}",
+                    output,
+                    flags
+                        .iter()
+                        .filter(|flag| is_enabled(flag))
+                        .collect::<Vec<_>>(),
+                    result
+                );
+            }
+        }
+        // collapse_spaces is the same option, so only one may be given
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\ncollapse_spaces = true\ncollapse_whitespace = false\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_concurrent_java_setup() {
+        let java_config = TestConfig::from_toml_values(
+            "[java]\nname = \"Test A\"\ntests_dir = \"t\"\nmain_class = \"Main\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let students: Vec<String> = ["alice", "bob"]
+            .iter()
+            .map(|name| {
+                let dir = target.path().join(name);
+                std::fs::create_dir(&dir).unwrap();
+                std::fs::write(
+                    dir.join("Main.java"),
+                    format!(
+                        "public class Main {{ public static void main(String[] args) {{ System.out.println(\"{}\"); }} }}\n",
+                        name
+                    ),
+                )
+                .unwrap();
+                String::from(dir.to_str().unwrap())
+            })
+            .collect();
+        std::thread::scope(|scope| {
+            for student in &students {
+                let java_config = &java_config;
+                scope.spawn(move || assert!(java_config.do_setup(student).is_ok()));
+            }
+        });
+        for (name, student) in ["alice", "bob"].iter().zip(&students) {
+            let output = std::process::Command::new(java_config.command(student))
+                .args(java_config.args(student))
+                .envs(java_config.env_vars(student))
+                .output()
+                .unwrap();
+            assert_eq!(
+                format!("{}\n", name),
+                String::from_utf8_lossy(&output.stdout)
+            );
+        }
+    }
+
+    #[test]
+    fn test_java_warnings_as_errors() {
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(
+            target.path().join("Main.java"),
+            "public class Main { public static void main(String[] args) { System.out.println(new Integer(5)); } }\n",
+        )
+        .unwrap();
+        let student = target.path().to_str().unwrap();
+        let config_toml = "[java]\nname = \"Warnings\"\ntests_dir = \"t\"\nmain_class = \"Main\"\ntarget_dir = \"d\"\n";
+        let java_config = TestConfig::from_toml_values(config_toml.parse().unwrap()).unwrap();
+        // The deprecated constructor only gets a warning by default...
+        assert!(java_config.do_setup(student).is_ok());
+        let java_config = TestConfig::from_toml_values(
+            format!("{}warnings_as_errors = true\n", config_toml)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        // ...but it's a compile error with the option, which says why
+        let error = java_config.do_setup(student).unwrap_err();
+        assert!(error.contains("Main.java:1"), "{}", error);
+        assert!(error.contains("-Werror"), "{}", error);
+    }
+
+    #[test]
+    fn test_c_warnings_as_errors() {
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(
+            target.path().join("main.c"),
+            "int main(void) { int unused; return 0; }\n",
+        )
+        .unwrap();
+        let student = target.path().to_str().unwrap();
+        let config_toml =
+            "[c]\nname = \"Warnings\"\ntests_dir = \"t\"\ntarget_dir = \"d\"\nflags = [\"-Wall\"]\n";
+        let c_config = TestConfig::from_toml_values(config_toml.parse().unwrap()).unwrap();
+        // The unused variable only gets a warning by default...
+        assert!(c_config.do_setup(student).is_ok());
+        let c_config = TestConfig::from_toml_values(
+            format!("{}warnings_as_errors = true\n", config_toml)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        // ...but it's a compile error with the option, which says why
+        let error = c_config.do_setup(student).unwrap_err();
+        assert!(error.contains("unused"), "{}", error);
+    }
+
+    #[test]
+    fn test_default_comparison() {
+        let java_config = TestConfig::from_toml_values(
+            "[java]\nname = \"Test A\"\ntests_dir = \"t\"\nmain_class = \"Main\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        let python_toml =
+            "[python]\nname = \"Test A\"\ntests_dir = \"t\"\nfile = \"a.py\"\ntarget_dir = \"d\"\n";
+        let python_config = TestConfig::from_toml_values(python_toml.parse().unwrap()).unwrap();
+        assert_ne!(
+            java_config.default_comparison(),
+            python_config.default_comparison()
+        );
+        let compare = |config: &TestConfig| config.options().comparator().compare("", "X\n", "X");
+        let missing_newline = crate::test::TestAnswer::FailWithMessage(String::from(
+            "Expected a newline at the end of the output",
+        ));
+        assert_eq!(missing_newline, compare(&java_config));
+        assert_eq!(crate::test::TestAnswer::Success, compare(&python_config));
+        // The [comparison] table overrides the language's default...
+        let python_config = TestConfig::from_toml_values(
+            format!("{}[comparison]\ncompare = \"exact\"\n", python_toml)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(missing_newline, compare(&python_config));
+        // ...and leaves the language's default for anything it doesn't set
+        let python_config = TestConfig::from_toml_values(
+            format!("{}[comparison]\ncollapse_spaces = true\n", python_toml)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(crate::test::TestAnswer::Success, compare(&python_config));
+        assert!(TestConfig::from_toml_values(
+            format!("{}[comparison]\ncompare = 1\n", python_toml)
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+        assert!(TestConfig::from_toml_values(
+            "[comparison]\ncompare = \"exact\"\n".parse().unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let java_fields = config_fields("java").unwrap();
+        let main_class = java_fields
+            .iter()
+            .find(|f| f.name() == "main_class")
+            .unwrap();
+        assert!(main_class.is_required());
+        assert!(java_fields
+            .iter()
+            .any(|f| f.name() == "timeout" && !f.is_required()));
+        let schema = describe_schema("java").unwrap();
+        let required = schema.find("Required fields:").unwrap();
+        let optional = schema.find("Optional fields:").unwrap();
+        let main_class = schema.find("  main_class: ").unwrap();
+        assert!(required < main_class && main_class < optional);
+        assert!(config_fields("fortran").is_none());
+    }
+
     #[test]
     fn test_from_toml_with_args() {
         let java_config = TestConfig::from_toml_values(
@@ -216,6 +1160,21 @@ mod tests {
             vec!["Main", "Hello,", "world!"],
             java_config.args("test/dir")
         );
+        let java_config = TestConfig::from_toml_values(
+            "[java]\nname = \"Test A\"\ntests_dir = \"path/to/test\"\nmain_class = \"Main\"\nargs = [\"-v\", \"in.txt\"]\njvm_args = [\"-Xss8m\", \"-Xmx1g\"]\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap()
+        ).unwrap();
+        assert_eq!(
+            vec!["-Xss8m", "-Xmx1g", "Main", "-v", "in.txt"],
+            java_config.args("test/dir")
+        );
+        assert!(TestConfig::from_toml_values(
+            "[java]\nname = \"Test A\"\ntests_dir = \"t\"\nmain_class = \"Main\"\njvm_args = \"-Xss8m\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap()
+        )
+        .is_err());
         let python_config = TestConfig::from_toml_values(
             "[python]\nname = \"Test A\"\ntests_dir = \"path/to/test\"\nfile = \"source.py\"\ntarget_dir = \"d\"\n"
                 .parse()
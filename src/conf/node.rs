@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use errormake::errormake;
+
+use super::FieldInfo;
+use crate::test::{Comparison, ComparisonOptions};
+
+/// Default timeout for JavaScript programs, in seconds, per test case
+const DEFAULT_TIMEOUT: u64 = 5;
+
+/// The default JavaScript interpreter to use, if unspecified
+const DEFAULT_NODE: &str = "node";
+
+/// This struct represents a configuration for running a JavaScript
+/// program with node.
+///
+/// See `NodeConfig::from_toml` for docs on how to create one.
+pub struct NodeConfig {
+    name: String,
+    test_data_dirs: Option<Vec<String>>,
+    interpreter: String,
+    timeout: Option<Duration>,
+    filename: String,
+    args: Vec<String>,
+    target_dir: String,
+}
+
+impl NodeConfig {
+    /// The fields which can be given in a node configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required("file", "The file to be run"),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the JavaScript program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "interpreter",
+            "The command which runs JavaScript files (default: \"node\")",
+        ),
+    ];
+
+    /// Required fields in the toml:
+    ///  - "name": A name for this test
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "file": The file to be run
+    ///  - "target_dir": The directory containing all student
+    ///    submissions (each submission as its own directory).
+    ///
+    /// Optional fields in the toml:
+    ///  - "timeout": Should be the number of seconds to allow before
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
+    ///  - "args": Should be an array of arguments to pass to the
+    ///    JavaScript program being tested. They come after the file in
+    ///    `process.argv`. Default: empty array
+    ///  - "interpreter": The command which runs JavaScript files, such as
+    ///    a particular version of node. Default: "node"
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<NodeConfig, NodeConfigError<std::convert::Infallible>> {
+        let name = match conf.get("name") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(NodeConfigError::with_description(
+                "Missing \"name\" field".to_string(),
+            )),
+            _ => Err(NodeConfigError::with_description(
+                "\"name\" field should be a string".to_string(),
+            )),
+        }?;
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(NodeConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(NodeConfigError::with_description(
+                "Missing \"tests_dir\" field".to_string(),
+            )),
+            _ => Err(NodeConfigError::with_description(
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
+            )),
+        }?;
+        let interpreter = match conf.get("interpreter") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Ok(String::from(DEFAULT_NODE)),
+            _ => Err(NodeConfigError::with_description(
+                "\"interpreter\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let timeout = match conf.get("timeout") {
+            Some(toml::Value::Integer(seconds)) => Ok(Some(Duration::new(*seconds as u64, 0))),
+            Some(toml::Value::Float(seconds)) => Ok(Some(Duration::new(
+                *seconds as u64,
+                ((seconds % 1.0) * 1e9) as u32,
+            ))),
+            None | Some(toml::Value::Boolean(true)) => Ok(Some(Duration::new(DEFAULT_TIMEOUT, 0))),
+            Some(toml::Value::Boolean(false)) => Ok(None),
+            _ => Err(NodeConfigError::with_description(
+                "\"timeout\", if specified, should be a number or false".to_string(),
+            )),
+        }?;
+        let filename = match conf.get("file") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(NodeConfigError::with_description(
+                "Missing \"file\" field".to_string(),
+            )),
+            _ => Err(NodeConfigError::with_description(
+                "\"file\" field should be a string".to_string(),
+            )),
+        }?;
+        let args: Vec<String> = match conf.get("args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    toml::Value::Array(_) | toml::Value::Table(_) => {
+                        Err(NodeConfigError::with_description(
+                            "Args may not contain nested structures".to_string(),
+                        ))
+                    }
+                    toml::Value::Integer(i) => Ok(format!("{}", i)),
+                    toml::Value::Float(f) => Ok(format!("{}", f)),
+                    toml::Value::Boolean(b) => Ok(format!("{}", b)),
+                    toml::Value::Datetime(d) => Ok(format!("{}", d)),
+                })
+                .collect(),
+            _ => Err(NodeConfigError::with_description(
+                "\"args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let target_dir = match conf.get("target_dir") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(NodeConfigError::with_description(
+                "Missing \"target_dir\" field".to_string(),
+            )),
+            _ => Err(NodeConfigError::with_description(
+                "\"target_dir\" field must be a string".to_string(),
+            )),
+        }?;
+        Ok(NodeConfig {
+            name,
+            test_data_dirs,
+            interpreter,
+            timeout,
+            filename,
+            args,
+            target_dir,
+        })
+    }
+}
+
+impl super::Config for NodeConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
+    }
+
+    fn case_timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    fn command(&self, _student_dir: &str) -> String {
+        String::from(&self.interpreter)
+    }
+
+    fn args(&self, student_dir: &str) -> Vec<String> {
+        let mut args = vec![format!("{}/{}", student_dir, self.filename)];
+        args.extend(self.args.iter().cloned());
+        args
+    }
+
+    fn do_setup(&self, _student_dir: &str) -> Result<(), String> {
+        // JavaScript isn't compiled, so no setup needs to be done
+        Ok(())
+    }
+
+    fn required_files(&self) -> Vec<String> {
+        vec![self.filename.clone()]
+    }
+
+    fn target_dir(&self) -> &str {
+        &self.target_dir
+    }
+
+    fn env_vars(&self, _student_dir: &str) -> HashMap<String, String> {
+        // No work needs to be done
+        HashMap::new()
+    }
+
+    fn default_comparison(&self) -> ComparisonOptions {
+        // Like python, `console.log` adds a newline but
+        // `process.stdout.write` doesn't, so a missing or extra one at the
+        // end isn't held against students
+        ComparisonOptions::new(Comparison::ExactIgnoreFinalNewline)
+    }
+}
+
+errormake!(#[doc="An error while interpreting Node configuration"] pub NodeConfigError);
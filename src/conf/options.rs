@@ -0,0 +1,1269 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
+
+use glob::Pattern;
+use regex::Regex;
+
+use super::{FieldInfo, InterpretConfigError};
+use crate::test::{Comparator, Comparison, ComparisonOptions, Mask, SelfCheckComparator};
+
+/// A test case given in the config file itself, instead of in the tests
+/// directory (see the "cases" option in `RunOptions::from_toml`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineCase {
+    name: String,
+    input: String,
+    input_files: Vec<String>,
+    output: String,
+    hidden: bool,
+    description: Option<String>,
+}
+impl InlineCase {
+    /// Makes a case with the given name, input, and expected output
+    pub fn new(name: String, input: String, output: String) -> Self {
+        InlineCase {
+            name,
+            input,
+            input_files: Vec::new(),
+            output,
+            hidden: false,
+            description: None,
+        }
+    }
+
+    /// Sets whether the case's details are hidden from students
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Sets the description of the case for students
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Sets the files whose contents are concatenated (in order) to make
+    /// up the input, instead of the input given to `new`
+    pub fn with_input_files(mut self, input_files: Vec<String>) -> Self {
+        self.input_files = input_files;
+        self
+    }
+
+    /// The name of the case
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The input given to the student's code, exactly as written
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The files whose contents make up the input, if it's given as an
+    /// array of files instead of as text
+    pub fn input_files(&self) -> &[String] {
+        &self.input_files
+    }
+
+    /// The expected output, exactly as written
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Whether the case's details are hidden from students
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// The description of the case for students, if it has one
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Which of the output streams of student code are compared against the
+/// expected output (see the "compare_stream" option in
+/// `RunOptions::from_toml`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareStream {
+    /// Only standard output is compared, against `<case>.out`
+    Stdout,
+    /// Only standard error is compared, against `<case>.out`
+    Stderr,
+    /// Standard output is compared against `<case>.out`, and standard
+    /// error against `<case>.err`
+    Both,
+}
+impl CompareStream {
+    /// Finds the stream with the given name in the config ("stdout",
+    /// "stderr", or "both")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stdout" => Some(CompareStream::Stdout),
+            "stderr" => Some(CompareStream::Stderr),
+            "both" => Some(CompareStream::Both),
+            _ => None,
+        }
+    }
+}
+
+/// How the input of each case is given to student code (see the
+/// "input_via" option in `RunOptions::from_toml`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputVia {
+    /// The input is written to the code's standard input
+    Stdin,
+    /// The input is written to a temporary file, whose path is passed
+    /// as an argument
+    File,
+}
+impl InputVia {
+    /// Finds the way of giving input with the given name in the config
+    /// ("stdin" or "file")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stdin" => Some(InputVia::Stdin),
+            "file" => Some(InputVia::File),
+            _ => None,
+        }
+    }
+}
+
+/// Options which control how a test run is carried out, independent of
+/// the language being tested.
+///
+/// See `RunOptions::from_toml` for docs on how to create one.
+#[derive(Clone)]
+pub struct RunOptions {
+    capture_output: bool,
+    max_message_len: Option<usize>,
+    comparator: Arc<dyn Comparator>,
+    masks: Vec<Mask>,
+    weights_file: Option<String>,
+    follow_symlinks: bool,
+    run_wrapper: Vec<String>,
+    warmup: bool,
+    detect_nondeterminism: bool,
+    student_name_regex: Option<Regex>,
+    input_filter: Vec<String>,
+    max_students: Option<usize>,
+    abort_if_all_fail: Option<usize>,
+    nice: Option<i32>,
+    clean_env: bool,
+    kill_grace_period: Option<Duration>,
+    inline_cases: Vec<InlineCase>,
+    run_in_student_dir: bool,
+    include_input_in_message: bool,
+    expected_exit_code: Option<i32>,
+    error_on_duplicate_cases: bool,
+    student_filter: Vec<Pattern>,
+    case_filter: Vec<Pattern>,
+    compare_stream: CompareStream,
+    ignore_folders: bool,
+    timeout_retries: usize,
+    input_via: InputVia,
+}
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            capture_output: false,
+            max_message_len: None,
+            comparator: Arc::new(ComparisonOptions::default()),
+            masks: Vec::new(),
+            weights_file: None,
+            follow_symlinks: true,
+            run_wrapper: Vec::new(),
+            warmup: false,
+            detect_nondeterminism: false,
+            student_name_regex: None,
+            input_filter: Vec::new(),
+            max_students: None,
+            abort_if_all_fail: None,
+            nice: None,
+            clean_env: false,
+            kill_grace_period: None,
+            inline_cases: Vec::new(),
+            run_in_student_dir: true,
+            include_input_in_message: false,
+            expected_exit_code: Some(0),
+            error_on_duplicate_cases: false,
+            student_filter: Vec::new(),
+            case_filter: Vec::new(),
+            compare_stream: CompareStream::Stdout,
+            ignore_folders: true,
+            timeout_retries: 0,
+            input_via: InputVia::Stdin,
+        }
+    }
+}
+
+/// Reads the "compare", "field_separator", "collapse_spaces" (or
+/// "collapse_whitespace"), "ignore_case", "float_tolerance", and
+/// "relative_tolerance" keys from the given toml, using the values in
+/// `defaults` for the ones which aren't given.
+pub(super) fn comparison_from_toml(
+    conf: &toml::Value,
+    defaults: ComparisonOptions,
+) -> Result<ComparisonOptions, InterpretConfigError<std::convert::Infallible>> {
+    let comparison = match conf.get("compare") {
+        Some(toml::Value::String(name)) => Comparison::from_name(name).ok_or_else(|| {
+            InterpretConfigError::with_description(format!("Unrecognized comparison: {}", name))
+        }),
+        None => Ok(defaults.comparison()),
+        _ => Err(InterpretConfigError::with_description(
+            "\"compare\", if specified, must be a string".to_string(),
+        )),
+    }?;
+    let comparison = match (comparison, conf.get("field_separator")) {
+        (comparison, None) => Ok(comparison),
+        (Comparison::RecordSet { sort, .. }, Some(toml::Value::String(sep)))
+            if sep.chars().count() == 1 =>
+        {
+            Ok(Comparison::RecordSet {
+                field_sep: sep.chars().next().unwrap(),
+                sort,
+            })
+        }
+        (Comparison::RecordSet { .. }, Some(_)) => Err(InterpretConfigError::with_description(
+            "\"field_separator\", if specified, must be a single character".to_string(),
+        )),
+        (_, Some(_)) => Err(InterpretConfigError::with_description(
+            "\"field_separator\" only applies to the \"records\" and \"sorted_records\" comparisons"
+                .to_string(),
+        )),
+    }?;
+    let collapse_spaces = match (conf.get("collapse_spaces"), conf.get("collapse_whitespace")) {
+        (Some(toml::Value::Boolean(b)), None) | (None, Some(toml::Value::Boolean(b))) => Ok(*b),
+        (None, None) => Ok(defaults.collapse_spaces()),
+        (Some(_), Some(_)) => Err(InterpretConfigError::with_description(
+            "Only one of \"collapse_spaces\" and \"collapse_whitespace\" may be given".to_string(),
+        )),
+        _ => Err(InterpretConfigError::with_description(
+            "\"collapse_spaces\", if specified, must be a boolean".to_string(),
+        )),
+    }?;
+    let ignore_case = match conf.get("ignore_case") {
+        Some(toml::Value::Boolean(b)) => Ok(*b),
+        None => Ok(defaults.ignore_case()),
+        _ => Err(InterpretConfigError::with_description(
+            "\"ignore_case\", if specified, must be a boolean".to_string(),
+        )),
+    }?;
+    let float_tolerance = match conf.get("float_tolerance") {
+        Some(toml::Value::Float(tolerance)) if *tolerance >= 0.0 => Ok(Some(*tolerance)),
+        Some(toml::Value::Integer(tolerance)) if *tolerance >= 0 => Ok(Some(*tolerance as f64)),
+        None => Ok(defaults.float_tolerance()),
+        _ => Err(InterpretConfigError::with_description(
+            "\"float_tolerance\", if specified, must be a nonnegative number".to_string(),
+        )),
+    }?;
+    let relative_tolerance = match conf.get("relative_tolerance") {
+        Some(toml::Value::Float(tolerance)) if *tolerance >= 0.0 => Ok(Some(*tolerance)),
+        Some(toml::Value::Integer(tolerance)) if *tolerance >= 0 => Ok(Some(*tolerance as f64)),
+        None => Ok(defaults.relative_tolerance()),
+        _ => Err(InterpretConfigError::with_description(
+            "\"relative_tolerance\", if specified, must be a nonnegative number".to_string(),
+        )),
+    }?;
+    Ok(ComparisonOptions::new(comparison)
+        .with_collapse_spaces(collapse_spaces)
+        .with_ignore_case(ignore_case)
+        .with_float_tolerance(float_tolerance)
+        .with_relative_tolerance(relative_tolerance))
+}
+
+impl RunOptions {
+    /// The fields which can be given for any language
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::optional(
+            "capture_output",
+            "Whether to keep the output of each student's code (default: false)",
+        ),
+        FieldInfo::optional(
+            "compare",
+            "How to compare output: \"exact\", \"ignore_final_newline\", \"trim\", \"trim_lines\", \"sorted_lines\", \"sorted_numeric\", \"csv\", \"csv_sorted_rows\", \"records\", \"sorted_records\", or \"line_counts\" (default: depends on the language)",
+        ),
+        FieldInfo::optional(
+            "field_separator",
+            "The character which separates fields for the \"records\" comparisons (default: a tab)",
+        ),
+        FieldInfo::optional(
+            "collapse_spaces",
+            "Whether runs of spaces and tabs count as a single space when comparing (default: false)",
+        ),
+        FieldInfo::optional(
+            "collapse_whitespace",
+            "Another name for \"collapse_spaces\"",
+        ),
+        FieldInfo::optional(
+            "ignore_case",
+            "Whether capitalization is ignored when comparing (default: false)",
+        ),
+        FieldInfo::optional(
+            "float_tolerance",
+            "If given, compare output token by token, letting numbers be off by up to this much (default: none)",
+        ),
+        FieldInfo::optional(
+            "relative_tolerance",
+            "If given, compare output token by token, letting numbers be off by up to this fraction of the expected number (default: none)",
+        ),
+        FieldInfo::optional(
+            "self_check_line",
+            "If given, a case passes when the last line printed is this, ignoring the expected output (default: none)",
+        ),
+        FieldInfo::optional(
+            "mask",
+            "An array of [regex, replacement] pairs applied to both outputs before comparing (default: empty)",
+        ),
+        FieldInfo::optional(
+            "weights_file",
+            "A toml file mapping case names to the points they're worth (default: 1 point each)",
+        ),
+        FieldInfo::optional(
+            "follow_symlinks",
+            "Whether symlinks to directories in target_dir count as students (default: true)",
+        ),
+        FieldInfo::optional(
+            "run_wrapper",
+            "A command and arguments to launch student code through, such as [\"firejail\", \"--\"] (default: none)",
+        ),
+        FieldInfo::optional(
+            "warmup",
+            "Whether to run each student's code once, with empty input, before the cases (default: false)",
+        ),
+        FieldInfo::optional(
+            "detect_nondeterminism",
+            "Whether to run each case twice, and flag cases whose output changes (default: false)",
+        ),
+        FieldInfo::optional(
+            "student_name_regex",
+            "A regex whose first capture group, in each student's folder name, is used as their name (default: the whole folder name)",
+        ),
+        FieldInfo::optional(
+            "input_filter",
+            "A command and arguments which each case's input is passed through before it's given to students (default: none)",
+        ),
+        FieldInfo::optional(
+            "max_students",
+            "Only test this many students, taking the first ones in sorted order (default: no limit)",
+        ),
+        FieldInfo::optional(
+            "students",
+            "An array of folder names or globs, where only the students whose folders match one are tested (default: everyone)",
+        ),
+        FieldInfo::optional(
+            "only_cases",
+            "An array of case names or globs, where only the cases which match one are run (default: every case)",
+        ),
+        FieldInfo::optional(
+            "abort_if_all_fail",
+            "Stop the run if the first this many students whose code ran all fail the same case (default: never stop)",
+        ),
+        FieldInfo::optional(
+            "nice",
+            "The niceness (from -20 to 19) to run student code at, on unix (default: unchanged)",
+        ),
+        FieldInfo::optional(
+            "clean_env",
+            "Whether to run student code with only the configured environment variables and a minimal PATH (default: false)",
+        ),
+        FieldInfo::optional(
+            "kill_grace_period",
+            "Seconds between asking code which timed out to stop (SIGTERM) and killing it, on unix (default: kill right away)",
+        ),
+        FieldInfo::optional(
+            "run_in_student_dir",
+            "Whether student code runs with its own directory as the working directory (default: true)",
+        ),
+        FieldInfo::optional(
+            "cases",
+            "An array of tables with the \"input\", \"output\" (where \"input\" may be an array of files to concatenate), and optionally \"name\", \"hidden\", and \"description\" of extra cases (default: empty)",
+        ),
+        FieldInfo::optional(
+            "max_message_len",
+            "The maximum number of characters to keep from failure messages (default: no limit)",
+        ),
+        FieldInfo::optional(
+            "include_input_in_message",
+            "Whether failure messages start with the case's input, unless the case is hidden (default: false)",
+        ),
+        FieldInfo::optional(
+            "expected_exit_code",
+            "The exit code student code must exit with to pass, or \"any\" to not check it (default: 0)",
+        ),
+        FieldInfo::optional(
+            "error_on_duplicate_cases",
+            "Whether two tests directories having a case with the same name is an error, instead of the later one being used (default: false)",
+        ),
+        FieldInfo::optional(
+            "compare_stream",
+            "Which output to compare: \"stdout\", \"stderr\", or \"both\" (against <case>.out and <case>.err) (default: \"stdout\")",
+        ),
+        FieldInfo::optional(
+            "ignore_folders",
+            "Whether dotfolders, and folders matching a pattern in target_dir's .stipulateignore file, aren't tested as students (default: true)",
+        ),
+        FieldInfo::optional(
+            "timeout_retries",
+            "How many more times to run a case which timed out before it counts as a timeout (default: 0)",
+        ),
+        FieldInfo::optional(
+            "input_via",
+            "How each case's input is given: \"stdin\", or \"file\" for a temporary file whose path replaces {input_file} in the args or is appended to them (default: \"stdin\")",
+        ),
+    ];
+
+    /// These options are read from the same section as the language
+    /// configuration, and all of them are optional:
+    ///  - "capture_output": Whether to keep the output produced by each
+    ///    student's code alongside the result. Default: false
+    ///  - "max_message_len": The maximum number of characters to keep
+    ///    from any failure or error message. Longer messages are cut
+    ///    off and marked as truncated. Default: no limit
+    ///  - "include_input_in_message": Whether to put the input of a
+    ///    case which a student failed at the start of the failure
+    ///    message, so they can see what their code was given. The input
+    ///    is cut off at "max_message_len" characters, like the rest of
+    ///    the message. Cases marked as hidden (with a `<case>.hidden`
+    ///    file) never have their input shown. Default: false
+    ///  - "expected_exit_code": The exit code which student code has to
+    ///    exit with to pass a case, even if its output is right, so that
+    ///    code which crashes after printing the right output fails. It
+    ///    can also be "any", so the exit code isn't checked. Default: 0
+    ///  - "compare_stream": Which of the streams student code prints to
+    ///    are compared. With "stdout", only standard output is compared
+    ///    against `<case>.out` (and standard error is shown in the
+    ///    terminal, as usual). With "stderr", only standard error is
+    ///    compared against `<case>.out`. With "both", standard output is
+    ///    compared against `<case>.out`, and standard error against
+    ///    `<case>.err` (or it must be empty, if a case has no `.err`
+    ///    file). When output is captured, it's whatever was compared,
+    ///    with standard output first. Default: "stdout"
+    ///  - "input_via": How the input of each case is given to student
+    ///    code. With "stdin", it's written to standard input. With
+    ///    "file", it's written to a temporary file instead (which is
+    ///    deleted once the code exits), for programs which take the name
+    ///    of their input file as an argument. Each `{input_file}` in the
+    ///    arguments (including a case's own arguments) is replaced with
+    ///    the file's path, or if there aren't any, the path is passed as
+    ///    the last argument. Standard input is left empty. Default:
+    ///    "stdin"
+    ///  - "compare": How to compare the output of student code with
+    ///    the expected output. One of "exact", "ignore_final_newline"
+    ///    (exact, except for a missing or extra newline at the end),
+    ///    "trim" (exact, except for any whitespace at the end),
+    ///    "trim_lines" (exact, except for whitespace at the end of each
+    ///    line and blank lines at the end),
+    ///    "sorted_lines" (the same lines in any order),
+    ///    "sorted_numeric" (like "sorted_lines", but numbers are ordered
+    ///    by value), "csv" (the same CSV cells, with a header row), or
+    ///    "csv_sorted_rows" (like "csv", but the rows after the header
+    ///    may be in any order), "records" (each line is a record of
+    ///    fields, which must all match), "sorted_records" (like
+    ///    "records", but the records may be in any order), or
+    ///    "line_counts" (each line appears the same number of times, in
+    ///    any order, and failures say which line's count is off).
+    ///    Default: depends on the language (see
+    ///    `Config::default_comparison`)
+    ///  - "field_separator": The character which separates the fields
+    ///    of each record, for the "records" and "sorted_records"
+    ///    comparisons. Default: a tab
+    ///  - "collapse_spaces": Whether to replace each run of spaces and
+    ///    tabs with a single space in both outputs before comparing them
+    ///    (which still keeps lines separate). This applies along with
+    ///    any choice of "compare". It can also be given as
+    ///    "collapse_whitespace" (but not both). Default: depends on the
+    ///    language
+    ///  - "ignore_case": Whether to make both outputs lowercase before
+    ///    comparing them. This applies along with any choice of
+    ///    "compare", and with "collapse_spaces". Both happen before the
+    ///    comparison, so "trim" and "trim_lines" trim the collapsed,
+    ///    lowercased outputs. Default: false
+    ///  - "float_tolerance": If given, the outputs are compared token by
+    ///    token (splitting on whitespace) instead of with "compare", and
+    ///    numbers may differ by up to this amount. Failures report the
+    ///    number which was off by the most. Default: none
+    ///  - "relative_tolerance": Like "float_tolerance", but numbers may
+    ///    differ by up to this fraction of the expected number (so 0.01
+    ///    allows them to be off by 1%). If both are given, a number
+    ///    passes if it's within either one. Default: none
+    ///  - "self_check_line": For programs which check their own answers.
+    ///    If given, a case passes exactly when the last line the program
+    ///    prints is this line, and the other comparison options (and the
+    ///    expected output) are ignored. Default: none
+    ///  - "mask": An array of `[regex, replacement]` pairs. Before the
+    ///    outputs are compared, every match of each regex (in order) is
+    ///    replaced in both the expected and actual output, which lets
+    ///    you hide details such as absolute paths. Default: empty array
+    ///  - "weights_file": A toml file containing a table which maps
+    ///    case names to the number of points each case is worth. Cases
+    ///    which aren't listed are worth 1 point, and a "weights" section
+    ///    in the config (see `TestConfig::from_toml_values`) overrides
+    ///    this for the cases in it. Default: none
+    ///  - "follow_symlinks": Whether symlinks to directories in the
+    ///    target directory are treated as students. Default: true
+    ///  - "ignore_folders": Whether some folders in the target directory
+    ///    are skipped, instead of being treated as students: those whose
+    ///    names start with a dot (such as `.git`), and those which match
+    ///    one of the patterns in the target directory's
+    ///    `.stipulateignore` file, if it has one. That file has a glob on
+    ///    each line, like a `.gitignore` file (such as `__MACOSX` or
+    ///    `solutions/`), where blank lines and lines starting with `#`
+    ///    are skipped, and a line starting with `!` un-ignores the
+    ///    folders it matches (such as `!.keep`), with later lines taking
+    ///    precedence. Default: true
+    ///  - "timeout_retries": How many more times to run a case which
+    ///    timed out, in case it was only slow because the machine was
+    ///    busy. The first result which isn't a timeout is used, and the
+    ///    case only counts as a timeout if every run of it timed out.
+    ///    Default: 0 (no retries)
+    ///  - "run_wrapper": An array containing a command and its
+    ///    arguments, which student code is launched through (for
+    ///    example, `["firejail", "--quiet", "--"]`). The command to run
+    ///    and its arguments are appended to the wrapper, so include
+    ///    "--" at the end if the wrapper needs it. Default: none
+    ///  - "warmup": Whether to run each student's code once (with empty
+    ///    input, and ignoring the result) before running the cases, so
+    ///    that slow startup the first time (such as loading the JVM from
+    ///    disk) doesn't count against the timeouts. Default: false
+    ///  - "detect_nondeterminism": Whether to run each case twice, and
+    ///    mark cases where the two outputs differ as nondeterministic
+    ///    instead of passing or failing them. This catches bugs such as
+    ///    reading uninitialized memory. Default: false
+    ///  - "student_name_regex": A regex with a capture group, which is
+    ///    matched against each student's folder name, and the first
+    ///    group is used as the student's name in the results. Folders
+    ///    which don't match keep their whole name. It's an error for two
+    ///    students to end up with the same name. Default: none
+    ///  - "input_filter": An array containing a command and its
+    ///    arguments. The input for each case is given to this command on
+    ///    standard input when the cases are loaded, and what it prints is
+    ///    used as the input instead (for example, `["sed", "/^#/d"]` to
+    ///    remove comment lines). Default: none
+    ///  - "max_students": The maximum number of students to test. Only
+    ///    the first ones (sorted by folder name) are tested, which is
+    ///    useful for trying out a config quickly. Default: no limit
+    ///  - "students": An array of folder names, or globs such as
+    ///    "smith*", where only the students whose folders match one of
+    ///    them are tested (before "max_students" is applied). This is
+    ///    useful for rerunning a few students. Default: everyone is
+    ///    tested
+    ///  - "only_cases": An array of case names, or globs such as
+    ///    "edge_*", where only the cases which match one of them are
+    ///    loaded and run (including those given in "cases"), so the
+    ///    results only have those cases. Default: every case is run
+    ///  - "abort_if_all_fail": A number of students, K. If each of the
+    ///    first K students whose code ran fails the same case, the run
+    ///    stops with an error, since the tests are probably misconfigured.
+    ///    Students who didn't submit, whose code didn't compile, or who
+    ///    couldn't be tested don't count, and when a run is resumed, only
+    ///    the students tested after resuming count. Default: the run is
+    ///    never stopped
+    ///  - "nice": The niceness (from -20 to 19, where higher is lower
+    ///    priority) to run student code at, so that it doesn't starve
+    ///    other processes on a shared machine. Only unix supports this,
+    ///    and it's ignored elsewhere. Lowering the niceness usually needs
+    ///    extra permissions. Default: the same as stipulate's own
+    ///  - "clean_env": Whether to run student code without inheriting
+    ///    stipulate's environment variables (such as HOME and USER), so
+    ///    that results don't depend on the grading machine. Only the
+    ///    variables the language sets (such as CLASSPATH for java) and
+    ///    a minimal PATH are given, so the language's tools need to be
+    ///    installed in a standard location. Default: false
+    ///  - "kill_grace_period": The number of seconds to give student
+    ///    code which timed out to clean up (such as removing temporary
+    ///    files). It's sent SIGTERM, and then killed if it's still
+    ///    running after this long. Only unix supports this, and
+    ///    elsewhere the code is killed right away. Default: the code is
+    ///    killed right away
+    ///  - "run_in_student_dir": Whether student code is run with the
+    ///    student's directory as its working directory, so that it can
+    ///    open files next to it by relative paths. Otherwise, it's run
+    ///    in the directory stipulate was started in. Default: true
+    ///  - "cases": An array of tables, each of which is a case with a
+    ///    "name", an "input", and an expected "output", which are run
+    ///    along with the cases in the tests directory (so a name can't
+    ///    be used by both), if there is one. The name may be left out, in
+    ///    which case it's "inline_" followed by the case's position in
+    ///    the array (starting from 1). The input and output are used exactly as
+    ///    written, so they are the same as a `.in` and `.out` file with
+    ///    the same contents. In particular, with a multiline string
+    ///    (`"""` or `'''`), the newline right after the opening quotes
+    ///    isn't part of the string (as TOML specifies), but every other
+    ///    newline is, so putting the closing quotes on their own line
+    ///    ends the text with a newline, and putting them at the end of
+    ///    the last line doesn't. The input can also be an array of files
+    ///    whose contents are concatenated in order, such as
+    ///    `["shared/common.in", "shared/3.in"]`, like a `<case>.inputs`
+    ///    file. They're relative to the tests directory (the first one,
+    ///    if there are several), or, if there isn't one or it's an
+    ///    archive, to the current directory like the other paths in the
+    ///    config. The input filter is applied to the input like any
+    ///    other case's. A case can also set "hidden" to
+    ///    true, which is like a `<case>.hidden` file for the cases in
+    ///    the tests directory, and a "description" for students, like a
+    ///    `<case>.desc` file. Default: empty array
+    ///  - "error_on_duplicate_cases": When "tests_dir" is an array of
+    ///    several directories, whether it's an error for two of them to
+    ///    have a case with the same name. If it isn't, the case from the
+    ///    directory which comes later in the array is used. Default: false
+    ///
+    /// The comparison options which aren't given fall back to the ones in
+    /// `comparison`.
+    pub fn from_toml(
+        conf: &toml::Value,
+        comparison: ComparisonOptions,
+    ) -> Result<RunOptions, InterpretConfigError<std::convert::Infallible>> {
+        let capture_output = match conf.get("capture_output") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"capture_output\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let max_message_len = match conf.get("max_message_len") {
+            Some(toml::Value::Integer(len)) if *len >= 0 => Ok(Some(*len as usize)),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"max_message_len\", if specified, must be a nonnegative integer".to_string(),
+            )),
+        }?;
+        let include_input_in_message = match conf.get("include_input_in_message") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"include_input_in_message\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let expected_exit_code = match conf.get("expected_exit_code") {
+            Some(toml::Value::Integer(code)) if i32::try_from(*code).is_ok() => {
+                Ok(Some(*code as i32))
+            }
+            Some(toml::Value::String(s)) if s == "any" => Ok(None),
+            None => Ok(Some(0)),
+            _ => Err(InterpretConfigError::with_description(
+                "\"expected_exit_code\", if specified, must be an integer or \"any\"".to_string(),
+            )),
+        }?;
+        let comparison = comparison_from_toml(conf, comparison)?;
+        let masks: Vec<Mask> = match conf.get("mask") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                    Some([toml::Value::String(pattern), toml::Value::String(replacement)]) => {
+                        Regex::new(pattern)
+                            .map(|pattern| Mask::new(pattern, replacement.clone()))
+                            .map_err(|e| {
+                                InterpretConfigError::with_description(format!(
+                                    "Invalid regex in \"mask\": {}",
+                                    e
+                                ))
+                            })
+                    }
+                    _ => Err(InterpretConfigError::with_description(
+                        "Each \"mask\" entry must be a [regex, replacement] pair of strings"
+                            .to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"mask\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let weights_file = match conf.get("weights_file") {
+            Some(toml::Value::String(s)) => Ok(Some(s.clone())),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"weights_file\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let follow_symlinks = match conf.get("follow_symlinks") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(true),
+            _ => Err(InterpretConfigError::with_description(
+                "\"follow_symlinks\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let ignore_folders = match conf.get("ignore_folders") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(true),
+            _ => Err(InterpretConfigError::with_description(
+                "\"ignore_folders\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let timeout_retries = match conf.get("timeout_retries") {
+            Some(toml::Value::Integer(retries)) if *retries >= 0 => Ok(*retries as usize),
+            None => Ok(0),
+            _ => Err(InterpretConfigError::with_description(
+                "\"timeout_retries\", if specified, must be a nonnegative integer".to_string(),
+            )),
+        }?;
+        let run_wrapper: Vec<String> = match conf.get("run_wrapper") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(InterpretConfigError::with_description(
+                        "\"run_wrapper\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"run_wrapper\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let warmup = match conf.get("warmup") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"warmup\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let detect_nondeterminism = match conf.get("detect_nondeterminism") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"detect_nondeterminism\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let student_name_regex = match conf.get("student_name_regex") {
+            Some(toml::Value::String(pattern)) => match Regex::new(pattern) {
+                Ok(regex) if regex.captures_len() > 1 => Ok(Some(regex)),
+                Ok(_) => Err(InterpretConfigError::with_description(
+                    "\"student_name_regex\" must have a capture group".to_string(),
+                )),
+                Err(e) => Err(InterpretConfigError::with_description(format!(
+                    "Invalid regex in \"student_name_regex\": {}",
+                    e
+                ))),
+            },
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"student_name_regex\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let input_filter: Vec<String> = match conf.get("input_filter") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(InterpretConfigError::with_description(
+                        "\"input_filter\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"input_filter\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let max_students = match conf.get("max_students") {
+            Some(toml::Value::Integer(max)) if *max >= 0 => Ok(Some(*max as usize)),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"max_students\", if specified, must be a nonnegative integer".to_string(),
+            )),
+        }?;
+        let student_filter: Vec<Pattern> = match conf.get("students") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Pattern::new(s).map_err(|e| {
+                        InterpretConfigError::with_description(format!(
+                            "Invalid pattern in \"students\": {}",
+                            e
+                        ))
+                    }),
+                    _ => Err(InterpretConfigError::with_description(
+                        "\"students\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"students\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let case_filter: Vec<Pattern> = match conf.get("only_cases") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Pattern::new(s).map_err(|e| {
+                        InterpretConfigError::with_description(format!(
+                            "Invalid pattern in \"only_cases\": {}",
+                            e
+                        ))
+                    }),
+                    _ => Err(InterpretConfigError::with_description(
+                        "\"only_cases\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"only_cases\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let abort_if_all_fail = match conf.get("abort_if_all_fail") {
+            Some(toml::Value::Integer(count)) if *count > 0 => Ok(Some(*count as usize)),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"abort_if_all_fail\", if specified, must be a positive integer".to_string(),
+            )),
+        }?;
+        let nice = match conf.get("nice") {
+            Some(toml::Value::Integer(nice)) if (-20..=19).contains(nice) => Ok(Some(*nice as i32)),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"nice\", if specified, must be an integer from -20 to 19".to_string(),
+            )),
+        }?;
+        let clean_env = match conf.get("clean_env") {
+            Some(toml::Value::Boolean(clean_env)) => Ok(*clean_env),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"clean_env\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let kill_grace_period = match conf.get("kill_grace_period") {
+            Some(toml::Value::Integer(seconds)) if *seconds >= 0 => {
+                Ok(Some(Duration::from_secs(*seconds as u64)))
+            }
+            Some(toml::Value::Float(seconds)) if *seconds >= 0.0 => {
+                Ok(Some(Duration::from_secs_f64(*seconds)))
+            }
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"kill_grace_period\", if specified, must be a nonnegative number".to_string(),
+            )),
+        }?;
+        let run_in_student_dir = match conf.get("run_in_student_dir") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(true),
+            _ => Err(InterpretConfigError::with_description(
+                "\"run_in_student_dir\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let error_on_duplicate_cases = match conf.get("error_on_duplicate_cases") {
+            Some(toml::Value::Boolean(b)) => Ok(*b),
+            None => Ok(false),
+            _ => Err(InterpretConfigError::with_description(
+                "\"error_on_duplicate_cases\", if specified, must be a boolean".to_string(),
+            )),
+        }?;
+        let compare_stream = match conf.get("compare_stream") {
+            Some(toml::Value::String(name)) => CompareStream::from_name(name).ok_or_else(|| {
+                InterpretConfigError::with_description(format!(
+                    "\"compare_stream\" must be \"stdout\", \"stderr\", or \"both\", not \"{}\"",
+                    name
+                ))
+            }),
+            None => Ok(CompareStream::Stdout),
+            _ => Err(InterpretConfigError::with_description(
+                "\"compare_stream\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let input_via = match conf.get("input_via") {
+            Some(toml::Value::String(name)) => InputVia::from_name(name).ok_or_else(|| {
+                InterpretConfigError::with_description(format!(
+                    "\"input_via\" must be \"stdin\" or \"file\", not \"{}\"",
+                    name
+                ))
+            }),
+            None => Ok(InputVia::Stdin),
+            _ => Err(InterpretConfigError::with_description(
+                "\"input_via\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        let inline_cases: Vec<InlineCase> = match conf.get("cases") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .enumerate()
+                .map(|(index, case)| {
+                    let field = |field: &str| match case.get(field) {
+                        Some(toml::Value::String(s)) => Ok(s.clone()),
+                        _ => Err(InterpretConfigError::with_description(format!(
+                            "Each of \"cases\" must have a string \"{}\"",
+                            field
+                        ))),
+                    };
+                    let name = match case.get("name") {
+                        None => Ok(format!("inline_{}", index + 1)),
+                        _ => field("name"),
+                    }?;
+                    let hidden = match case.get("hidden") {
+                        Some(toml::Value::Boolean(b)) => Ok(*b),
+                        None => Ok(false),
+                        _ => Err(InterpretConfigError::with_description(
+                            "\"hidden\" in \"cases\", if specified, must be a boolean".to_string(),
+                        )),
+                    }?;
+                    let input_files: Vec<String> = match case.get("input") {
+                        Some(toml::Value::Array(files)) if !files.is_empty() => files
+                            .iter()
+                            .map(|file| match file {
+                                toml::Value::String(file) => Ok(file.clone()),
+                                _ => Err(InterpretConfigError::with_description(
+                                    "\"input\" in \"cases\" may only contain strings".to_string(),
+                                )),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        _ => Vec::new(),
+                    };
+                    let input = match input_files.is_empty() {
+                        true => field("input")?,
+                        false => String::new(),
+                    };
+                    let inline_case = InlineCase::new(name, input, field("output")?)
+                        .with_hidden(hidden)
+                        .with_input_files(input_files);
+                    match case.get("description") {
+                        Some(_) => Ok(inline_case.with_description(field("description")?)),
+                        None => Ok(inline_case),
+                    }
+                })
+                .collect(),
+            _ => Err(InterpretConfigError::with_description(
+                "\"cases\", if specified, must be an array of tables".to_string(),
+            )),
+        }?;
+        let comparator: Arc<dyn Comparator> = match conf.get("self_check_line") {
+            Some(toml::Value::String(line)) => Arc::new(SelfCheckComparator::new(line.clone())),
+            None => Arc::new(comparison),
+            _ => {
+                return Err(InterpretConfigError::with_description(
+                    "\"self_check_line\", if specified, must be a string".to_string(),
+                ))
+            }
+        };
+        Ok(RunOptions {
+            capture_output,
+            max_message_len,
+            comparator,
+            masks,
+            weights_file,
+            follow_symlinks,
+            run_wrapper,
+            warmup,
+            detect_nondeterminism,
+            student_name_regex,
+            input_filter,
+            max_students,
+            abort_if_all_fail,
+            nice,
+            clean_env,
+            kill_grace_period,
+            inline_cases,
+            run_in_student_dir,
+            include_input_in_message,
+            expected_exit_code,
+            error_on_duplicate_cases,
+            student_filter,
+            case_filter,
+            compare_stream,
+            ignore_folders,
+            timeout_retries,
+            input_via,
+        })
+    }
+
+    /// Whether the output of each student's code should be kept
+    pub fn capture_output(&self) -> bool {
+        self.capture_output
+    }
+
+    /// The maximum length of failure and error messages, if any
+    pub fn max_message_len(&self) -> Option<usize> {
+        self.max_message_len
+    }
+
+    /// The comparator used to judge the output of student code
+    pub fn comparator(&self) -> &dyn Comparator {
+        self.comparator.as_ref()
+    }
+
+    /// Sets whether the output of each student's code should be kept
+    pub fn set_capture_output(&mut self, capture_output: bool) {
+        self.capture_output = capture_output;
+    }
+
+    /// Sets the maximum length of failure and error messages
+    pub fn set_max_message_len(&mut self, max_message_len: Option<usize>) {
+        self.max_message_len = max_message_len;
+    }
+
+    /// Sets the comparator used to judge the output of student code.
+    ///
+    /// By default, output has to match the expected output exactly.
+    pub fn set_comparator<C: Comparator + 'static>(&mut self, comparator: C) {
+        self.comparator = Arc::new(comparator);
+    }
+
+    /// The masks applied to outputs before they are compared
+    pub fn masks(&self) -> &[Mask] {
+        &self.masks
+    }
+
+    /// Sets the masks applied to outputs before they are compared
+    pub fn set_masks(&mut self, masks: Vec<Mask>) {
+        self.masks = masks;
+    }
+
+    /// The file containing the weight of each case, if any
+    pub fn weights_file(&self) -> Option<&str> {
+        self.weights_file.as_deref()
+    }
+
+    /// Sets the file containing the weight of each case
+    pub fn set_weights_file(&mut self, weights_file: Option<String>) {
+        self.weights_file = weights_file;
+    }
+
+    /// Whether symlinks to directories count as students
+    pub fn follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// Sets whether symlinks to directories count as students
+    pub fn set_follow_symlinks(&mut self, follow_symlinks: bool) {
+        self.follow_symlinks = follow_symlinks;
+    }
+
+    /// The command (and arguments) which student code is launched
+    /// through, or empty to launch it directly
+    pub fn run_wrapper(&self) -> &[String] {
+        &self.run_wrapper
+    }
+
+    /// Sets the command (and arguments) which student code is launched
+    /// through
+    pub fn set_run_wrapper(&mut self, run_wrapper: Vec<String>) {
+        self.run_wrapper = run_wrapper;
+    }
+
+    /// Whether student code is run once before the cases
+    pub fn warmup(&self) -> bool {
+        self.warmup
+    }
+
+    /// Sets whether student code is run once before the cases
+    pub fn set_warmup(&mut self, warmup: bool) {
+        self.warmup = warmup;
+    }
+
+    /// Whether each case is run twice to check that its output doesn't
+    /// change
+    pub fn detect_nondeterminism(&self) -> bool {
+        self.detect_nondeterminism
+    }
+
+    /// Sets whether each case is run twice to check that its output
+    /// doesn't change
+    pub fn set_detect_nondeterminism(&mut self, detect_nondeterminism: bool) {
+        self.detect_nondeterminism = detect_nondeterminism;
+    }
+
+    /// The regex used to find each student's name in their folder name,
+    /// if any
+    pub fn student_name_regex(&self) -> Option<&Regex> {
+        self.student_name_regex.as_ref()
+    }
+
+    /// Sets the regex used to find each student's name in their folder
+    /// name. Its first capture group is used as the name.
+    pub fn set_student_name_regex(&mut self, student_name_regex: Option<Regex>) {
+        self.student_name_regex = student_name_regex;
+    }
+
+    /// The command (and arguments) which the input of each case is
+    /// passed through, or empty to use the input as it is
+    pub fn input_filter(&self) -> &[String] {
+        &self.input_filter
+    }
+
+    /// Sets the command (and arguments) which the input of each case is
+    /// passed through
+    pub fn set_input_filter(&mut self, input_filter: Vec<String>) {
+        self.input_filter = input_filter;
+    }
+
+    /// The maximum number of students to test, if any
+    pub fn max_students(&self) -> Option<usize> {
+        self.max_students
+    }
+
+    /// Sets the maximum number of students to test
+    pub fn set_max_students(&mut self, max_students: Option<usize>) {
+        self.max_students = max_students;
+    }
+
+    /// The number of students who all have to fail the same case for
+    /// the run to stop, if the run can stop early
+    pub fn abort_if_all_fail(&self) -> Option<usize> {
+        self.abort_if_all_fail
+    }
+
+    /// Sets the number of students who all have to fail the same case
+    /// for the run to stop
+    pub fn set_abort_if_all_fail(&mut self, abort_if_all_fail: Option<usize>) {
+        self.abort_if_all_fail = abort_if_all_fail;
+    }
+
+    /// The niceness to run student code at, if it should be changed
+    pub fn nice(&self) -> Option<i32> {
+        self.nice
+    }
+
+    /// Sets the niceness to run student code at
+    pub fn set_nice(&mut self, nice: Option<i32>) {
+        self.nice = nice;
+    }
+
+    /// Whether student code is run without inheriting the environment
+    pub fn clean_env(&self) -> bool {
+        self.clean_env
+    }
+
+    /// Sets whether student code is run without inheriting the
+    /// environment
+    pub fn set_clean_env(&mut self, clean_env: bool) {
+        self.clean_env = clean_env;
+    }
+
+    /// How long student code which timed out gets to stop on its own
+    /// before it's killed, if it gets any time
+    pub fn kill_grace_period(&self) -> Option<Duration> {
+        self.kill_grace_period
+    }
+
+    /// Sets how long student code which timed out gets to stop on its
+    /// own before it's killed
+    pub fn set_kill_grace_period(&mut self, kill_grace_period: Option<Duration>) {
+        self.kill_grace_period = kill_grace_period;
+    }
+
+    /// The cases given in the config file itself
+    pub fn inline_cases(&self) -> &[InlineCase] {
+        &self.inline_cases
+    }
+
+    /// Sets the cases given in the config file itself
+    pub fn set_inline_cases(&mut self, inline_cases: Vec<InlineCase>) {
+        self.inline_cases = inline_cases;
+    }
+
+    /// Whether student code is run in the student's own directory
+    pub fn run_in_student_dir(&self) -> bool {
+        self.run_in_student_dir
+    }
+
+    /// Sets whether student code is run in the student's own directory
+    pub fn set_run_in_student_dir(&mut self, run_in_student_dir: bool) {
+        self.run_in_student_dir = run_in_student_dir;
+    }
+
+    /// Whether failure messages start with the input of the case, for
+    /// cases which aren't hidden
+    pub fn include_input_in_message(&self) -> bool {
+        self.include_input_in_message
+    }
+
+    /// Sets whether failure messages start with the input of the case
+    pub fn set_include_input_in_message(&mut self, include_input_in_message: bool) {
+        self.include_input_in_message = include_input_in_message;
+    }
+
+    /// The exit code which student code has to exit with to pass, or
+    /// None if it isn't checked
+    pub fn expected_exit_code(&self) -> Option<i32> {
+        self.expected_exit_code
+    }
+
+    /// Sets the exit code which student code has to exit with to pass
+    pub fn set_expected_exit_code(&mut self, expected_exit_code: Option<i32>) {
+        self.expected_exit_code = expected_exit_code;
+    }
+
+    /// Whether it's an error for two tests directories to have a case
+    /// with the same name, instead of the later one being used
+    pub fn error_on_duplicate_cases(&self) -> bool {
+        self.error_on_duplicate_cases
+    }
+
+    /// Sets whether it's an error for two tests directories to have a
+    /// case with the same name
+    pub fn set_error_on_duplicate_cases(&mut self, error_on_duplicate_cases: bool) {
+        self.error_on_duplicate_cases = error_on_duplicate_cases;
+    }
+
+    /// The folder names (or globs) of the students to test, where an
+    /// empty filter tests everyone
+    pub fn student_filter(&self) -> &[Pattern] {
+        &self.student_filter
+    }
+
+    /// Sets the folder names (or globs) of the students to test
+    pub fn set_student_filter(&mut self, student_filter: Vec<Pattern>) {
+        self.student_filter = student_filter;
+    }
+
+    /// The names (or globs) of the cases to run, where an empty filter
+    /// runs every case
+    pub fn case_filter(&self) -> &[Pattern] {
+        &self.case_filter
+    }
+
+    /// Sets the names (or globs) of the cases to run
+    pub fn set_case_filter(&mut self, case_filter: Vec<Pattern>) {
+        self.case_filter = case_filter;
+    }
+
+    /// Which of the output streams of student code are compared
+    pub fn compare_stream(&self) -> CompareStream {
+        self.compare_stream
+    }
+
+    /// Sets which of the output streams of student code are compared
+    pub fn set_compare_stream(&mut self, compare_stream: CompareStream) {
+        self.compare_stream = compare_stream;
+    }
+
+    /// Whether dotfolders and the folders matching the target
+    /// directory's `.stipulateignore` file are skipped when finding the
+    /// students
+    pub fn ignore_folders(&self) -> bool {
+        self.ignore_folders
+    }
+
+    /// Sets whether dotfolders and the folders matching the target
+    /// directory's `.stipulateignore` file are skipped
+    pub fn set_ignore_folders(&mut self, ignore_folders: bool) {
+        self.ignore_folders = ignore_folders;
+    }
+
+    /// How many more times a case which timed out is run before it
+    /// counts as a timeout
+    pub fn timeout_retries(&self) -> usize {
+        self.timeout_retries
+    }
+
+    /// Sets how many more times a case which timed out is run
+    pub fn set_timeout_retries(&mut self, timeout_retries: usize) {
+        self.timeout_retries = timeout_retries;
+    }
+
+    /// How the input of each case is given to student code
+    pub fn input_via(&self) -> InputVia {
+        self.input_via
+    }
+
+    /// Sets how the input of each case is given to student code
+    pub fn set_input_via(&mut self, input_via: InputVia) {
+        self.input_via = input_via;
+    }
+}
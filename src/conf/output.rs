@@ -0,0 +1,60 @@
+use super::InterpretConfigError;
+
+/// The output method (and file) to use when they aren't given on the
+/// command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputPreferences {
+    mode: String,
+    file: Option<String>,
+}
+impl OutputPreferences {
+    /// Makes preferences for the given output method, writing to the
+    /// given file (or standard output, if there isn't one)
+    pub fn new(mode: String, file: Option<String>) -> Self {
+        OutputPreferences { mode, file }
+    }
+
+    /// Reads the preferences from the "output" table in the config file,
+    /// for example:
+    ///
+    /// ```toml
+    /// [output]
+    /// mode = "csv"
+    /// file = "results.csv"
+    /// ```
+    ///
+    /// "mode" is required, and is the name of an output method, as would
+    /// be given on the command line. "file" is optional, and is where to
+    /// write the output (or, for gradescope, the directory).
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<OutputPreferences, InterpretConfigError<std::convert::Infallible>> {
+        let mode = match conf.get("mode") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(InterpretConfigError::with_description(
+                "Missing \"mode\" field in \"output\"".to_string(),
+            )),
+            _ => Err(InterpretConfigError::with_description(
+                "\"mode\" in \"output\" must be a string".to_string(),
+            )),
+        }?;
+        let file = match conf.get("file") {
+            Some(toml::Value::String(s)) => Ok(Some(s.clone())),
+            None => Ok(None),
+            _ => Err(InterpretConfigError::with_description(
+                "\"file\" in \"output\", if specified, must be a string".to_string(),
+            )),
+        }?;
+        Ok(OutputPreferences { mode, file })
+    }
+
+    /// The name of the output method
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    /// The file to write output to, if not standard output
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+}
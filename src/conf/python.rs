@@ -1,15 +1,19 @@
 use std::collections::HashMap;
+use std::process::Command;
 use std::time::Duration;
 
 use errormake::errormake;
 
+use super::{run_setup_command, FieldInfo};
+use crate::test::{Comparison, ComparisonOptions};
+
 /// Default timeout for python programs, in seconds, per test case
 const DEFAULT_TIMEOUT: u64 = 5;
 
 /// The default python interpreter to use, if unspecified
 #[cfg(target_family = "windows")]
 const DEFAULT_PYTHON: &str = "python";
-#[cfg(any(target_family = "unix"))]
+#[cfg(target_family = "unix")]
 const DEFAULT_PYTHON: &str = "python3";
 
 /// This struct represents a configuration for running a python program.
@@ -17,31 +21,74 @@ const DEFAULT_PYTHON: &str = "python3";
 /// See `PythonConfig::from_toml` for docs on how to create one.
 pub struct PythonConfig {
     name: String,
-    test_data_dir: String,
+    test_data_dirs: Option<Vec<String>>,
     python_version: String,
     timeout: Option<Duration>,
     filename: String,
+    other_files: Vec<String>,
     args: Vec<String>,
     target_dir: String,
 }
 
 impl PythonConfig {
+    /// The fields which can be given in a python configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "file",
+            "The file to be run (may be left out if \"files\" is given)",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the python program (default: empty)",
+        ),
+        FieldInfo::optional(
+            "files",
+            "An array of all files in the submission, the first being run if \"file\" isn't given (default: just \"file\")",
+        ),
+        FieldInfo::optional(
+            "version",
+            "The python interpreter to use (default: \"python\" on Windows, \"python3\" elsewhere)",
+        ),
+    ];
+
     /// Required fields in the toml:
     ///  - "name": A name for this test
-    ///  - "tests_dir": The directory to contain input and output data
-    ///  - "file": The file to be run
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "file": The file to be run. This may be left out if "files" is
+    ///    given, in which case the first of those files is run.
     ///
     /// Optional fields in the toml:
     ///  - "timeout": Should be the number of seconds to allow before
-    /// timing out, `true` (use default timeout value), or `false`
-    /// (allow tested code to run however long it takes - not
-    /// recommended). Default: 5 seconds
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
     ///  - "args": Should be an array of arguments to pass to the python
-    /// program being tested. It will be passed to the sys.argv value
-    /// in the python program. Default: empty array
+    ///    program being tested. It will be passed to the sys.argv value
+    ///    in the python program. Default: empty array
     ///  - "version": Enables you to specify a version of python to use.
-    /// Default: OS dependent: "python" for Windows, "python3" for
-    /// Linux/MacOS.
+    ///    Default: OS dependent: "python" for Windows, "python3" for
+    ///    Linux/MacOS.
+    ///  - "files": An array of all of the files which make up a
+    ///    submission. Each one must be present in the student's
+    ///    directory, and the ones other than the file being run are
+    ///    checked for syntax errors during setup. Default: empty array
     pub fn from_toml(
         conf: &toml::Value,
     ) -> Result<PythonConfig, PythonConfigError<std::convert::Infallible>> {
@@ -54,13 +101,24 @@ impl PythonConfig {
                 "\"name\" field should be a string".to_string(),
             )),
         }?;
-        let test_data_dir = match conf.get("tests_dir") {
-            Some(toml::Value::String(s)) => Ok(s.clone()),
-            None => Err(PythonConfigError::with_description(
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(PythonConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(PythonConfigError::with_description(
                 "Missing \"tests_dir\" field".to_string(),
             )),
             _ => Err(PythonConfigError::with_description(
-                "\"tests_dir\" field should be a string".to_string(),
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
             )),
         }?;
         let python_version = match conf.get("version") {
@@ -82,15 +140,32 @@ impl PythonConfig {
                 "\"timeout\", if specified, should be a number or false".to_string(),
             )),
         }?;
-        let filename = match conf.get("file") {
-            Some(toml::Value::String(s)) => Ok(s.clone()),
-            None => Err(PythonConfigError::with_description(
+        let files: Vec<String> = match conf.get("files") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(PythonConfigError::with_description(
+                        "\"files\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(PythonConfigError::with_description(
+                "\"files\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let filename = match (conf.get("file"), files.first()) {
+            (Some(toml::Value::String(s)), _) => Ok(s.clone()),
+            (None, Some(first)) => Ok(first.clone()),
+            (None, None) => Err(PythonConfigError::with_description(
                 "Missing \"file\" field".to_string(),
             )),
             _ => Err(PythonConfigError::with_description(
                 "\"file\" field should be a string".to_string(),
             )),
         }?;
+        let other_files = files.into_iter().filter(|f| *f != filename).collect();
         let args: Vec<String> = match conf.get("args") {
             None => Ok(Vec::new()),
             Some(toml::Value::Array(arr)) => arr
@@ -123,10 +198,11 @@ impl PythonConfig {
         }?;
         Ok(PythonConfig {
             name,
-            test_data_dir,
+            test_data_dirs,
             python_version,
             timeout,
             filename,
+            other_files,
             args,
             target_dir,
         })
@@ -138,8 +214,11 @@ impl super::Config for PythonConfig {
         &self.name
     }
 
-    fn test_type(&self) -> super::TestType {
-        super::TestType::Directory(&self.test_data_dir)
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
     }
 
     fn case_timeout(&self) -> &Option<Duration> {
@@ -158,9 +237,42 @@ impl super::Config for PythonConfig {
         args
     }
 
-    fn do_setup(&self, _student_dir: &str) -> bool {
-        // No setup needs to be done
-        true
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        if self.other_files.is_empty() {
+            // No setup needs to be done
+            return Ok(());
+        }
+        // Compile (without running or writing out) each of the other
+        // files, which fails if any are missing or have syntax errors.
+        run_setup_command(
+            Command::new(&self.python_version)
+                .arg("-c")
+                .arg("import sys\nfor f in sys.argv[1:]:\n    compile(open(f).read(), f, 'exec')")
+                .args(
+                    self.other_files
+                        .iter()
+                        .map(|file| format!("{}/{}", student_dir, file)),
+                ),
+            student_dir,
+        )
+    }
+
+    fn setup_programs(&self, _student_dir: &str) -> Vec<String> {
+        match self.other_files.is_empty() {
+            true => Vec::new(),
+            false => vec![self.python_version.clone()],
+        }
+    }
+
+    fn required_files(&self) -> Vec<String> {
+        let mut files = vec![self.filename.clone()];
+        files.extend(
+            self.other_files
+                .iter()
+                .filter(|file| **file != self.filename)
+                .cloned(),
+        );
+        files
     }
 
     fn target_dir(&self) -> &str {
@@ -171,6 +283,13 @@ impl super::Config for PythonConfig {
         // No work needs to be done
         HashMap::new()
     }
+
+    fn default_comparison(&self) -> ComparisonOptions {
+        // Python programs tend to be inconsistent about whether the
+        // last line ends with a newline (`print` adds one, but
+        // `sys.stdout.write` doesn't), so that isn't held against them
+        ComparisonOptions::new(Comparison::ExactIgnoreFinalNewline)
+    }
 }
 
 errormake!(#[doc="An error while interpreting Python configuration"] pub PythonConfigError);
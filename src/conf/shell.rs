@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::Duration;
+
+use errormake::errormake;
+
+use super::{run_setup_command, FieldInfo};
+
+/// Default timeout for programs run by a command, in seconds, per test
+/// case
+const DEFAULT_TIMEOUT: u64 = 5;
+
+/// The placeholder which is replaced with the student's directory in
+/// the command, arguments, and setup command
+const STUDENT_DIR_PLACEHOLDER: &str = "{student_dir}";
+
+/// This struct represents a configuration for running student code
+/// with a command given in the config, for languages which don't have
+/// their own kind of config.
+///
+/// See `ShellConfig::from_toml` for docs on how to create one.
+pub struct ShellConfig {
+    name: String,
+    test_data_dirs: Option<Vec<String>>,
+    timeout: Option<Duration>,
+    command: String,
+    args: Vec<String>,
+    setup: Vec<String>,
+    target_dir: String,
+}
+
+impl ShellConfig {
+    /// The fields which can be given in a command configuration
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::required("name", "A name for this test"),
+        FieldInfo::required(
+            "tests_dir",
+            "The directory to contain input and output data, or an array of several (may be left out if \"cases\" is given)",
+        ),
+        FieldInfo::required(
+            "command",
+            "The command which runs a student's code, where {student_dir} is replaced with their directory",
+        ),
+        FieldInfo::required(
+            "target_dir",
+            "The directory containing all student submissions",
+        ),
+        FieldInfo::optional(
+            "timeout",
+            "Seconds to allow before timing out, or true/false to use the default/no timeout (default: 5)",
+        ),
+        FieldInfo::optional(
+            "args",
+            "An array of arguments to pass to the command, where {student_dir} is replaced with their directory (default: empty)",
+        ),
+        FieldInfo::optional(
+            "setup",
+            "An array of a command and its arguments, run for each student before the cases, which fails to compile if it exits with an error (default: none)",
+        ),
+    ];
+
+    /// Required fields in the toml:
+    ///  - "name": A name for this test
+    ///  - "tests_dir": The directory to contain input and output data,
+    ///    or an array of several directories, whose cases are all run
+    ///    (see `TestType::Directory`). This may be left out if "cases" is
+    ///    given (see `RunOptions::from_toml`), in which case only those
+    ///    cases are run.
+    ///  - "command": The command which runs a student's code (such as
+    ///    "ruby" or "{student_dir}/target/release/main").
+    ///  - "target_dir": The directory containing all student
+    ///    submissions (each submission as its own directory).
+    ///
+    /// Optional fields in the toml:
+    ///  - "timeout": Should be the number of seconds to allow before
+    ///    timing out, `true` (use default timeout value), or `false`
+    ///    (allow tested code to run however long it takes - not
+    ///    recommended). Default: 5 seconds
+    ///  - "args": Should be an array of arguments to pass to the
+    ///    command (such as `["{student_dir}/main.rb"]`). Default: empty
+    ///    array
+    ///  - "setup": An array containing a command and its arguments,
+    ///    which is run for each student before their code is tested
+    ///    (such as `["cargo", "build", "--release", "--manifest-path",
+    ///    "{student_dir}/Cargo.toml"]`). If it exits with an error, the
+    ///    student's cases are marked as compile errors, with what it
+    ///    printed to stderr. Default: none
+    ///
+    /// In "command", "args", and "setup", each `{student_dir}` is
+    /// replaced with the path to the student's directory. The command is
+    /// run directly, rather than through a shell, so use something like
+    /// `["sh", "-c", "cd {student_dir} && go build"]` for setup which
+    /// needs one.
+    pub fn from_toml(
+        conf: &toml::Value,
+    ) -> Result<ShellConfig, ShellConfigError<std::convert::Infallible>> {
+        let name = match conf.get("name") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(ShellConfigError::with_description(
+                "Missing \"name\" field".to_string(),
+            )),
+            _ => Err(ShellConfigError::with_description(
+                "\"name\" field should be a string".to_string(),
+            )),
+        }?;
+        let test_data_dirs = match (conf.get("tests_dir"), conf.get("cases")) {
+            (Some(toml::Value::String(s)), _) => Ok(Some(vec![s.clone()])),
+            (Some(toml::Value::Array(arr)), _) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(ShellConfigError::with_description(
+                        "\"tests_dir\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            (None, Some(_)) => Ok(None),
+            (None, None) => Err(ShellConfigError::with_description(
+                "Missing \"tests_dir\" field".to_string(),
+            )),
+            _ => Err(ShellConfigError::with_description(
+                "\"tests_dir\" field should be a string or a nonempty array of strings".to_string(),
+            )),
+        }?;
+        let timeout = match conf.get("timeout") {
+            Some(toml::Value::Integer(seconds)) => Ok(Some(Duration::new(*seconds as u64, 0))),
+            Some(toml::Value::Float(seconds)) => Ok(Some(Duration::new(
+                *seconds as u64,
+                ((seconds % 1.0) * 1e9) as u32,
+            ))),
+            None | Some(toml::Value::Boolean(true)) => Ok(Some(Duration::new(DEFAULT_TIMEOUT, 0))),
+            Some(toml::Value::Boolean(false)) => Ok(None),
+            _ => Err(ShellConfigError::with_description(
+                "\"timeout\", if specified, should be a number or false".to_string(),
+            )),
+        }?;
+        let command = match conf.get("command") {
+            Some(toml::Value::String(s)) if !s.is_empty() => Ok(s.clone()),
+            None => Err(ShellConfigError::with_description(
+                "Missing \"command\" field".to_string(),
+            )),
+            _ => Err(ShellConfigError::with_description(
+                "\"command\" field should be a nonempty string".to_string(),
+            )),
+        }?;
+        let args: Vec<String> = match conf.get("args") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    toml::Value::Array(_) | toml::Value::Table(_) => {
+                        Err(ShellConfigError::with_description(
+                            "Args may not contain nested structures".to_string(),
+                        ))
+                    }
+                    toml::Value::Integer(i) => Ok(format!("{}", i)),
+                    toml::Value::Float(f) => Ok(format!("{}", f)),
+                    toml::Value::Boolean(b) => Ok(format!("{}", b)),
+                    toml::Value::Datetime(d) => Ok(format!("{}", d)),
+                })
+                .collect(),
+            _ => Err(ShellConfigError::with_description(
+                "\"args\", if specified, must be an array".to_string(),
+            )),
+        }?;
+        let setup: Vec<String> = match conf.get("setup") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::Array(arr)) if !arr.is_empty() => arr
+                .iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s.clone()),
+                    _ => Err(ShellConfigError::with_description(
+                        "\"setup\" may only contain strings".to_string(),
+                    )),
+                })
+                .collect(),
+            _ => Err(ShellConfigError::with_description(
+                "\"setup\", if specified, must be a nonempty array".to_string(),
+            )),
+        }?;
+        let target_dir = match conf.get("target_dir") {
+            Some(toml::Value::String(s)) => Ok(s.clone()),
+            None => Err(ShellConfigError::with_description(
+                "Missing \"target_dir\" field".to_string(),
+            )),
+            _ => Err(ShellConfigError::with_description(
+                "\"target_dir\" field must be a string".to_string(),
+            )),
+        }?;
+        Ok(ShellConfig {
+            name,
+            test_data_dirs,
+            timeout,
+            command,
+            args,
+            setup,
+            target_dir,
+        })
+    }
+}
+
+/// Replaces each `{student_dir}` in `template` with the student's
+/// directory
+fn fill_in(template: &str, student_dir: &str) -> String {
+    template.replace(STUDENT_DIR_PLACEHOLDER, student_dir)
+}
+
+impl super::Config for ShellConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn test_type(&self) -> super::TestType<'_> {
+        match &self.test_data_dirs {
+            Some(dirs) => super::TestType::Directory(dirs),
+            None => super::TestType::Inline,
+        }
+    }
+
+    fn case_timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    fn command(&self, student_dir: &str) -> String {
+        fill_in(&self.command, student_dir)
+    }
+
+    fn args(&self, student_dir: &str) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| fill_in(arg, student_dir))
+            .collect()
+    }
+
+    fn do_setup(&self, student_dir: &str) -> Result<(), String> {
+        let (program, args) = match self.setup.split_first() {
+            Some(setup) => setup,
+            // No setup needs to be done
+            None => return Ok(()),
+        };
+        run_setup_command(
+            Command::new(fill_in(program, student_dir))
+                .args(args.iter().map(|arg| fill_in(arg, student_dir))),
+            student_dir,
+        )
+    }
+
+    fn setup_programs(&self, student_dir: &str) -> Vec<String> {
+        self.setup
+            .first()
+            .map(|program| fill_in(program, student_dir))
+            .into_iter()
+            .collect()
+    }
+
+    fn target_dir(&self) -> &str {
+        &self.target_dir
+    }
+
+    fn env_vars(&self, _student_dir: &str) -> HashMap<String, String> {
+        // No work needs to be done
+        HashMap::new()
+    }
+}
+
+errormake!(#[doc="An error while interpreting command configuration"] pub ShellConfigError);
@@ -3,4 +3,8 @@ pub mod output;
 pub mod test;
 
 pub use conf::TestConfig;
-pub use test::{test_from_configuration, ClassResults, TestAnswer};
+pub use test::{
+    resume_from_configuration, test_from_cases, test_from_configuration,
+    test_from_configuration_with_progress, test_student, CaseResult, ClassResults, Comparator,
+    TestAnswer, TestCase,
+};
@@ -1,13 +1,46 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
+use glob::Pattern;
 
-use stipulate::output::{get_output_mode, get_output_mode_for_file};
-use stipulate::{test_from_configuration, TestConfig};
+use std::fmt::Display;
+use std::fs::File;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use stipulate::conf::describe_schema;
+
+use stipulate::output::{
+    case_listing, case_progress_line, diff_results, error_summary, get_output_mode,
+    get_output_mode_for_file, is_registered_output_mode, outlier_case_warnings, results_from_json,
+    run_manifest, Checkpoint, GradeOutput, GradescopeOutput, OutputMode, Tee,
+};
+use stipulate::test::load_test_cases;
+use stipulate::{resume_from_configuration, ClassResults, TestConfig};
+
+/// The file that finished results are saved to if the run is
+/// interrupted, unless another is given
+const DEFAULT_CHECKPOINT: &str = "stipulate-checkpoint.json";
+
+/// Prints the error (and what was being done when it happened) to
+/// stderr, and exits with a nonzero status
+fn fail<E: Display>(action: &str, error: E) -> ! {
+    eprintln!("Error {}: {}", action, error);
+    std::process::exit(1);
+}
+
+/// Fails because there's no output method with the given name
+fn unknown_output_method(name: &str) -> ! {
+    fail(
+        "choosing the output method",
+        format!("there's no output method named {:?}", name),
+    )
+}
 
 fn main() {
     let args = App::new("stipulate.rs")
         .version("0.0.3")
         .author("Jarred Allen <jarredallen73@gmail.com>")
         .about("Automate testing of student code")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("config_file")
                 .help("The file which stores the test configuration")
@@ -15,25 +48,345 @@ fn main() {
         )
         .arg(
             Arg::with_name("output_method")
-                .help("The method to use to output data")
-                .required(true),
+                .help("The method to use to output data (defaults to the mode in the config's [output] section)"),
         )
         .arg(
             Arg::with_name("output_file")
-                .help("The file to write output to (defaults to standard output")
+                .help("The file (or, for gradescope, the directory) to write output to (defaults to standard output)")
                 .short("o")
                 .long("output-file")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("verbose")
+                .help("Print each case's result as it finishes (repeat to log more details about the run instead, and again for even more)")
+                .short("v")
+                .long("verbose")
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .help("A file to write a manifest of the run's environment to")
+                .long("manifest")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_students")
+                .help("Only test the first N students (overrides the config)")
+                .long("max-students")
+                .value_name("N")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("student")
+                .help("Only test the students whose folders have this name, or match this glob (repeat for several; overrides the config)")
+                .long("student")
+                .value_name("name")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("case")
+                .help("Only run the cases with this name, or which match this glob (repeat for several; overrides the config)")
+                .long("case")
+                .value_name("name")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("abort_if_all_fail")
+                .help("Stop if the first K students whose code ran all fail the same case (overrides the config)")
+                .long("abort-if-all-fail")
+                .value_name("K")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("diff")
+                .help("Results saved by the json output method, to print the changes from")
+                .long("diff")
+                .value_name("old.json")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .help("Where to save finished students' results if the run is interrupted (default: stipulate-checkpoint.json)")
+                .long("checkpoint")
+                .value_name("file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .help("A checkpoint from an interrupted run, whose students aren't tested again")
+                .long("resume")
+                .value_name("checkpoint")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("low_pass_rate")
+                .help("Warn about cases which at most this percent of students passed")
+                .long("low-pass-rate")
+                .value_name("percent")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("high_pass_rate")
+                .help("Warn about cases which at least this percent of students passed")
+                .long("high-pass-rate")
+                .value_name("percent")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list_cases")
+                .help("Print the cases which were found (after --case), without testing anyone")
+                .long("list-cases"),
+        )
+        .arg(
+            Arg::with_name("tee")
+                .help("Also print the output to standard output when writing to a file (not for gradescope)")
+                .long("tee"),
+        )
+        .subcommand(
+            SubCommand::with_name("schema")
+                .about("Prints the fields available for a kind of config")
+                .arg(
+                    Arg::with_name("language")
+                        .help("The kind of config to describe (e.g. \"java\")")
+                        .required(true),
+                ),
+        )
         .get_matches();
+    env_logger::Builder::new()
+        .filter_level(match args.occurrences_of("verbose") {
+            0 | 1 => log::LevelFilter::Warn,
+            2 => log::LevelFilter::Info,
+            _ => log::LevelFilter::Debug,
+        })
+        .init();
+    if let Some(schema_args) = args.subcommand_matches("schema") {
+        let language = schema_args.value_of("language").unwrap();
+        match describe_schema(language) {
+            Some(schema) => print!("{}", schema),
+            None => {
+                eprintln!("Unrecognized config type: {}", language);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     let config_file = args.value_of("config_file").unwrap();
-    let config = TestConfig::from_file(config_file).unwrap();
-    let results = test_from_configuration(&config).unwrap();
-    let output_method = args.value_of("output_method").unwrap();
-    let mut output_writer = if let Some(output_file) = args.value_of("output_file") {
-        get_output_mode_for_file(output_method, output_file).expect("Unknown output method")
+    let mut config = TestConfig::from_file(config_file)
+        .unwrap_or_else(|e| fail(&format!("loading the config from {}", config_file), e));
+    if let Some(max_students) = args.value_of("max_students") {
+        let max_students = max_students
+            .parse()
+            .unwrap_or_else(|_| fail("in --max-students", "it must be a nonnegative integer"));
+        config.options_mut().set_max_students(Some(max_students));
+    }
+    if let Some(students) = args.values_of("student") {
+        let student_filter = students
+            .map(Pattern::new)
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| fail("in --student", e));
+        config.options_mut().set_student_filter(student_filter);
+    }
+    if let Some(cases) = args.values_of("case") {
+        let case_filter = cases
+            .map(Pattern::new)
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| fail("in --case", e));
+        config.options_mut().set_case_filter(case_filter);
+    }
+    if let Some(count) = args.value_of("abort_if_all_fail") {
+        let count = match count.parse() {
+            Ok(count) if count > 0 => count,
+            _ => fail("in --abort-if-all-fail", "it must be a positive integer"),
+        };
+        config.options_mut().set_abort_if_all_fail(Some(count));
+    }
+    if args.is_present("list_cases") {
+        let cases = load_test_cases(&config).unwrap_or_else(|e| fail("loading the cases", e));
+        print!("{}", case_listing(&cases));
+        return;
+    }
+    if let Some(manifest_file) = args.value_of("manifest") {
+        let action = format!("writing the manifest to {}", manifest_file);
+        let config_text = std::fs::read_to_string(config_file).unwrap_or_else(|e| fail(&action, e));
+        let manifest = run_manifest(&config, &config_text).unwrap_or_else(|e| fail(&action, e));
+        std::fs::write(manifest_file, manifest).unwrap_or_else(|e| fail(&action, e));
+    }
+    // The output method and file from the command line are used if
+    // they're given, and otherwise the ones in the config (the config's
+    // file is only used with its own output method). They're chosen
+    // before the run, so that mistakes in them are found right away.
+    let (output_method, output_file) = match args.value_of("output_method") {
+        Some(output_method) => (output_method, args.value_of("output_file")),
+        None => {
+            let preferences = config.output_preferences().unwrap_or_else(|| {
+                fail(
+                    "choosing the output method",
+                    "none was given, and the config has no [output] section",
+                )
+            });
+            (
+                preferences.mode(),
+                args.value_of("output_file").or_else(|| preferences.file()),
+            )
+        }
+    };
+    // Registered output modes are used instead of the built-in ones, even
+    // gradescope and grades
+    let registered = is_registered_output_mode(output_method);
+    let tee = args.is_present("tee");
+    if tee && output_file.is_none() {
+        fail("in --tee", "it needs an output file (-o) to echo");
+    }
+    if tee && output_method == "gradescope" && !registered {
+        fail(
+            "in --tee",
+            "gradescope writes a directory, which can't be echoed",
+        );
+    }
+    let pass_rate = |arg: &str| {
+        args.value_of(arg)
+            .map(|percent| match percent.parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => percent,
+                _ => fail(
+                    &format!("in --{}", arg.replace('_', "-")),
+                    "it must be a number from 0 to 100",
+                ),
+            })
+    };
+    let low_pass_rate = pass_rate("low_pass_rate");
+    let high_pass_rate = pass_rate("high_pass_rate");
+    // The old results are read now, so that a wrong file is found before
+    // anyone is tested, and they're compared once the run is done
+    let old_results = args.value_of("diff").map(|old_results_file| {
+        let action = format!("reading the old results {}", old_results_file);
+        let old_results = std::fs::read_to_string(old_results_file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| results_from_json(&text).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| fail(&action, e));
+        (old_results_file, old_results)
+    });
+    let previous_text = args.value_of("resume").map(|checkpoint_file| {
+        let action = format!("reading the checkpoint {}", checkpoint_file);
+        let text = std::fs::read_to_string(checkpoint_file).unwrap_or_else(|e| fail(&action, e));
+        // Check that it can be read now, rather than partway through
+        results_from_json(&text).unwrap_or_else(|e| fail(&action, e));
+        text
+    });
+    let previous = || match &previous_text {
+        Some(text) => results_from_json(text).unwrap(),
+        None => ClassResults::new(),
+    };
+    // On the first Ctrl-C, the students who have finished are saved once
+    // the current case ends (which is soon, since the student's code gets
+    // the interrupt too), and a second Ctrl-C exits right away
+    let interrupted = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register_conditional_shutdown(
+        signal_hook::consts::SIGINT,
+        130,
+        Arc::clone(&interrupted),
+    )
+    .unwrap();
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&interrupted)).unwrap();
+    let checkpoint_file = args.value_of("checkpoint").unwrap_or(DEFAULT_CHECKPOINT);
+    // Students from the run being resumed are kept, in case this run is
+    // interrupted too
+    let mut checkpoint = Checkpoint::with_finished(previous());
+    // With one --verbose, each result is printed to stderr as it
+    // finishes, separately from the output method. With more, the info
+    // log already has a line for each result, so they aren't printed
+    // twice.
+    let verbose = args.occurrences_of("verbose") == 1;
+    let results = resume_from_configuration(&config, previous(), &mut |student, case, result| {
+        if verbose {
+            eprintln!("{}", case_progress_line(student, case, result));
+        }
+        checkpoint.record(student, case, result);
+        if interrupted.load(Ordering::SeqCst) {
+            checkpoint.save(checkpoint_file).unwrap_or_else(|e| {
+                fail(&format!("saving the checkpoint to {}", checkpoint_file), e)
+            });
+            eprintln!(
+                "Interrupted: saved {} finished students to {} (continue with --resume {})",
+                checkpoint.finished().len(),
+                checkpoint_file,
+                checkpoint_file
+            );
+            std::process::exit(130);
+        }
+    })
+    .unwrap_or_else(|e| fail("running the tests", e));
+    let mut output_writer: Box<dyn OutputMode> = if output_method == "gradescope" && !registered {
+        // This writes a file for each student, so it needs a directory
+        let output_dir = output_file.unwrap_or_else(|| {
+            fail(
+                "choosing the output method",
+                "gradescope needs an output directory (-o)",
+            )
+        });
+        Box::new(GradescopeOutput::new(output_dir))
+    } else if output_method == "grades" && !registered {
+        // This needs the grade scale
+        let scale = config
+            .grade_scale()
+            .unwrap_or_else(|| {
+                fail(
+                    "choosing the output method",
+                    "grades needs a [grade_scale] section in the config",
+                )
+            })
+            .clone();
+        match output_file {
+            Some(output_file) => {
+                let file = File::create(output_file)
+                    .unwrap_or_else(|e| fail(&format!("creating {}", output_file), e));
+                match tee {
+                    true => Box::new(GradeOutput::with_output(
+                        Tee::new(file, io::stdout()),
+                        scale,
+                    )),
+                    false => Box::new(GradeOutput::with_output(file, scale)),
+                }
+            }
+            None => Box::new(GradeOutput::with_stdout(scale)),
+        }
+    } else if let Some(output_file) = output_file {
+        get_output_mode_for_file(output_method, output_file, tee)
+            .unwrap_or_else(|e| fail(&format!("creating {}", output_file), e))
+            .unwrap_or_else(|| unknown_output_method(output_method))
     } else {
-        get_output_mode(output_method).expect("Unknown output method")
+        get_output_mode(output_method).unwrap_or_else(|| unknown_output_method(output_method))
     };
-    output_writer.output_class_results(&results).unwrap();
+    output_writer
+        .output_class_results(&results)
+        .unwrap_or_else(|e| fail("writing the output", e));
+    if let Some((old_results_file, old_results)) = old_results {
+        let changes = diff_results(&old_results, &results);
+        eprintln!(
+            "{} results changed since {}",
+            changes.len(),
+            old_results_file
+        );
+        for change in changes {
+            eprintln!(
+                "  {}/{}: {} -> {}",
+                change.student, change.case, change.old_status, change.new_status
+            );
+        }
+    }
+    let warnings = outlier_case_warnings(&results, low_pass_rate, high_pass_rate);
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let errors = error_summary(&results);
+    if !errors.is_empty() {
+        eprintln!("Errors encountered while testing:");
+        for (message, count) in errors {
+            eprintln!("  {} x {}", count, message);
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::super::test::{TestCase, DEFAULT_POINTS};
+
+/// Returns a listing of the cases which were found for a run, one per
+/// line in order of name, followed by how many there are, so that the
+/// cases can be checked before anyone is tested.
+///
+/// Cases which are skipped or hidden say so, as do cases which are worth
+/// other than the default number of points.
+pub fn case_listing(cases: &HashMap<String, TestCase>) -> String {
+    let mut listing = String::new();
+    for (name, case) in cases.iter().sorted_by_key(|a| a.0) {
+        let mut notes = Vec::new();
+        if case.is_skipped() {
+            notes.push(String::from("skipped"));
+        }
+        if case.is_hidden() {
+            notes.push(String::from("hidden"));
+        }
+        if (case.get_points() - DEFAULT_POINTS).abs() > f64::EPSILON {
+            notes.push(format!("{} points", case.get_points()));
+        }
+        match notes.is_empty() {
+            true => listing.push_str(&format!("{}\n", name)),
+            false => listing.push_str(&format!("{} ({})\n", name, notes.join(", "))),
+        }
+    }
+    let skipped = cases.values().filter(|case| case.is_skipped()).count();
+    listing.push_str(&format!(
+        "{} {} ({} to run, {} skipped)\n",
+        cases.len(),
+        match cases.len() {
+            1 => "case",
+            _ => "cases",
+        },
+        cases.len() - skipped,
+        skipped
+    ));
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_listing() {
+        let mut cases = HashMap::new();
+        cases.insert(
+            String::from("b"),
+            TestCase::new(String::new(), String::new()).with_skipped(true),
+        );
+        cases.insert(
+            String::from("a"),
+            TestCase::new(String::new(), String::new()),
+        );
+        cases.insert(
+            String::from("c"),
+            TestCase::new(String::new(), String::new())
+                .with_hidden(true)
+                .with_points(2.5),
+        );
+        assert_eq!(
+            case_listing(&cases),
+            "a\nb (skipped)\nc (hidden, 2.5 points)\n3 cases (2 to run, 1 skipped)\n"
+        );
+        cases.remove("a");
+        cases.remove("b");
+        assert_eq!(
+            case_listing(&cases),
+            "c (hidden, 2.5 points)\n1 case (1 to run, 0 skipped)\n"
+        );
+        assert_eq!(
+            case_listing(&HashMap::new()),
+            "0 cases (0 to run, 0 skipped)\n"
+        );
+    }
+}
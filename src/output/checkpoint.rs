@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::fs;
+
+use super::super::test::StudentResults;
+use super::super::{CaseResult, ClassResults};
+use super::json::results_to_json;
+
+/// Keeps the results of the students who have finished so far in a run,
+/// so that they can be saved if the run is interrupted, and the run can
+/// be resumed later (see `resume_from_configuration`).
+///
+/// Give it each result as it finishes (from the callback of
+/// `test_from_configuration_with_progress`). Since students are tested
+/// one at a time, a student has finished once results for the next
+/// student start coming in, and the student who was being tested when
+/// the run stopped is left out.
+#[derive(Default)]
+pub struct Checkpoint {
+    finished: ClassResults,
+    current: Option<(String, StudentResults)>,
+}
+impl Checkpoint {
+    /// Makes an empty checkpoint
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a checkpoint which starts with the given students finished
+    /// (such as the ones from an earlier checkpoint)
+    pub fn with_finished(finished: ClassResults) -> Self {
+        Checkpoint {
+            finished,
+            current: None,
+        }
+    }
+
+    /// Records the result of one case for one student. Errors are kept
+    /// as just their messages.
+    pub fn record(
+        &mut self,
+        student_name: &str,
+        case_name: &str,
+        result: &Result<CaseResult, Box<dyn Error + 'static>>,
+    ) {
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|(current, _)| current != student_name)
+        {
+            if let Some((finished_name, results)) = self.current.take() {
+                self.finished.insert(finished_name, results);
+            }
+            self.current = Some((String::from(student_name), StudentResults::new()));
+        }
+        let result = match result {
+            Ok(result) => Ok(result.clone()),
+            Err(e) => Err(e.to_string().into()),
+        };
+        if let Some((_, results)) = self.current.as_mut() {
+            results.insert(String::from(case_name), result);
+        }
+    }
+
+    /// The results of the students who have finished
+    pub fn finished(&self) -> &ClassResults {
+        &self.finished
+    }
+
+    /// Writes the results of the students who have finished to the given
+    /// file, in the format of the "json" output method (so it can be read
+    /// back with `results_from_json`).
+    pub fn save(&self, filename: &str) -> Result<(), Box<dyn Error + 'static>> {
+        fs::write(
+            filename,
+            serde_json::to_string_pretty(&results_to_json(&self.finished))?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::super::super::{
+        resume_from_configuration, test_from_configuration_with_progress, TestConfig,
+    };
+    use super::super::json::results_from_json;
+    use super::super::result_status;
+    use super::*;
+
+    #[test]
+    fn test_resume_after_interrupt() {
+        let config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        // Pretend the run is interrupted partway through the second
+        // student (of three), by only recording the first three results
+        let mut checkpoint = Checkpoint::new();
+        let mut recorded = 0;
+        let full_results =
+            test_from_configuration_with_progress(&config, &mut |student, case, result| {
+                if recorded < 3 {
+                    checkpoint.record(student, case, result);
+                    recorded += 1;
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            checkpoint.finished().keys().collect::<Vec<_>>(),
+            vec!["correct"]
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let filename = file.path().to_str().unwrap();
+        checkpoint.save(filename).unwrap();
+
+        let previous = results_from_json(&fs::read_to_string(filename).unwrap()).unwrap();
+        let mut tested = Vec::new();
+        let resumed = resume_from_configuration(&config, previous, &mut |student, _, _| {
+            tested.push(String::from(student))
+        })
+        .unwrap();
+        // Only the students who hadn't finished are tested again
+        assert_eq!(
+            tested.into_iter().unique().collect::<Vec<_>>(),
+            vec!["panics", "wrong"]
+        );
+        let statuses = |results: &ClassResults| {
+            results
+                .iter()
+                .flat_map(|(student, cases)| {
+                    cases.iter().map(move |(case, result)| {
+                        (student.clone(), case.clone(), result_status(result))
+                    })
+                })
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(statuses(&resumed), statuses(&full_results));
+    }
+}
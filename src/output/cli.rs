@@ -0,0 +1,91 @@
+use std::io::{self, Stdout, Write};
+
+use itertools::Itertools;
+use prettytable::{Cell, Row};
+
+use super::super::ClassResults;
+use super::table::result_symbol;
+use super::{case_names, OutputMode};
+
+/// An OutputMode which prints a compact table to some output stream,
+/// with a symbol for each case but without the "Passed" and "Total"
+/// columns of the "print" mode
+pub struct Print<T> {
+    writer: T,
+}
+
+impl<T> Print<T> {
+    pub fn with_output(writer: T) -> Self {
+        Print { writer }
+    }
+}
+impl Print<Stdout> {
+    pub fn with_stdout() -> Self {
+        Print::with_output(io::stdout())
+    }
+}
+
+impl<T: Write> OutputMode for Print<T> {
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let case_names = case_names(results);
+        let mut table = prettytable::Table::new();
+        let mut case_row = Row::empty();
+        case_row.add_cell(Cell::new(""));
+        for case in case_names.iter() {
+            case_row.add_cell(Cell::new(case));
+        }
+        table.add_row(case_row);
+        for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+            let mut row = Row::new(
+                case_names
+                    .iter()
+                    .map(|case| {
+                        Cell::new(result_symbol(
+                            student_result
+                                .get(case.as_str())
+                                .expect("Student missing case in their results")
+                                .as_deref(),
+                        ))
+                    })
+                    .collect(),
+            );
+            row.insert_cell(0, Cell::new(student_name));
+            table.add_row(row);
+        }
+        table.print(&mut self.writer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::TestAnswer;
+
+    #[test]
+    fn test_compact_output() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Failure.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Timeout.into()));
+        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("Student B"), student_b);
+        let mut writer = Print::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "+-----------+--------+--------+\n|           | Case 1 | Case 2 |\n+-----------+--------+--------+\n| Student A |        | F      |\n+-----------+--------+--------+\n| Student B | T      | s      |\n+-----------+--------+--------+\n");
+        // Without any students, there's just the (empty) header
+        let mut writer = Print::with_output(Vec::<u8>::new());
+        writer.output_class_results(&HashMap::new()).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "+--+\n|  |\n+--+\n");
+    }
+}
@@ -0,0 +1,183 @@
+use errormake::errormake;
+use itertools::Itertools;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::super::{ClassResults, TestAnswer};
+use super::manifest::sha256_hex;
+use super::OutputMode;
+
+/// An OutputMode which reports groups of students who failed a case
+/// with byte-identical output.
+///
+/// This needs the output of each student's code, so if there were
+/// failures, but none of them have their output (because the run wasn't
+/// configured with `capture_output`), it returns a `NoOutputError`.
+pub struct ClusterOutput<T> {
+    writer: T,
+}
+impl<T> ClusterOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        ClusterOutput { writer }
+    }
+}
+
+/// Finds, for each case, the groups of students whose failing outputs
+/// hash to the same value.
+///
+/// The returned map takes each case name to the clusters found for it,
+/// and each cluster is the output's SHA-256 hash (in hexadecimal) along
+/// with the (sorted) names of the students in it. Only clusters with
+/// more than one student are included.
+fn find_clusters(results: &ClassResults) -> HashMap<&str, Vec<(String, Vec<&str>)>> {
+    let mut by_case: HashMap<&str, HashMap<String, Vec<&str>>> = HashMap::new();
+    for (student_name, student_result) in results.iter() {
+        for (case_name, result) in student_result.iter() {
+            let result = match result {
+                Ok(result) if *result.answer() != TestAnswer::Success => result,
+                _ => continue,
+            };
+            if let Some(output) = result.output() {
+                by_case
+                    .entry(case_name)
+                    .or_default()
+                    .entry(sha256_hex(output))
+                    .or_default()
+                    .push(student_name);
+            }
+        }
+    }
+    by_case
+        .into_iter()
+        .map(|(case_name, groups)| {
+            (
+                case_name,
+                groups
+                    .into_iter()
+                    .filter(|(_, students)| students.len() > 1)
+                    .map(|(hash, students)| (hash, students.into_iter().sorted().collect()))
+                    .sorted_by(|a: &(String, Vec<&str>), b| {
+                        b.1.len().cmp(&a.1.len()).then_with(|| a.1.cmp(&b.1))
+                    })
+                    .collect(),
+            )
+        })
+        .filter(|(_, clusters): &(&str, Vec<_>)| !clusters.is_empty())
+        .collect()
+}
+
+impl<T> OutputMode for ClusterOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let failures = || {
+            results
+                .values()
+                .flat_map(|student_result| student_result.values())
+                .filter_map(|result| result.as_ref().ok())
+                // Only these carry output, when it's captured
+                .filter(|result| {
+                    matches!(
+                        result.answer(),
+                        TestAnswer::Failure
+                            | TestAnswer::FailWithMessage(_)
+                            | TestAnswer::Nondeterministic
+                    )
+                })
+        };
+        if failures().next().is_some() && failures().all(|result| result.output().is_none()) {
+            return Err(NoOutputError::with_description(String::from(
+                "The clusters output needs the output of each student's code, so the run must have capture_output = true",
+            ))
+            .into());
+        }
+        let clusters = find_clusters(results);
+        for case_name in clusters.keys().sorted() {
+            writeln!(self.writer, "{}:", case_name)?;
+            for (hash, students) in clusters[case_name].iter() {
+                writeln!(
+                    self.writer,
+                    "  {} ({} students): {}",
+                    &hash[..16],
+                    students.len(),
+                    students.join(", ")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+errormake!(#[doc="The results don't have the output needed to find clusters"] pub NoOutputError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaseResult;
+
+    fn failing_with(output: &str) -> Result<CaseResult, Box<dyn std::error::Error + 'static>> {
+        Ok(CaseResult::from(TestAnswer::Failure).with_output(output.to_string()))
+    }
+
+    #[test]
+    fn test_identical_failures() {
+        let mut data = HashMap::new();
+        for (name, output) in [
+            ("Student A", "42\n"),
+            ("Student B", "41\n"),
+            ("Student C", "41\n"),
+            ("Student D", "41\n"),
+        ]
+        .iter()
+        {
+            let mut student = HashMap::new();
+            student.insert(String::from("Case 1"), failing_with(output));
+            student.insert(
+                String::from("Case 2"),
+                Ok(CaseResult::from(TestAnswer::Success).with_output(String::from("ok\n"))),
+            );
+            data.insert(String::from(*name), student);
+        }
+        let clusters = find_clusters(&data);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters["Case 1"].len(), 1);
+        assert_eq!(
+            clusters["Case 1"][0].1,
+            vec!["Student B", "Student C", "Student D"]
+        );
+        let mut writer = ClusterOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert!(output.starts_with("Case 1:\n"));
+        assert_eq!(
+            output,
+            format!(
+                "Case 1:\n  {} (3 students): Student B, Student C, Student D\n",
+                &sha256_hex("41\n")[..16]
+            )
+        );
+    }
+
+    #[test]
+    fn test_no_captured_output() {
+        let mut data = HashMap::new();
+        let mut student = HashMap::new();
+        student.insert(String::from("Case 1"), Ok(TestAnswer::Failure.into()));
+        data.insert(String::from("Student A"), student);
+        let mut writer = ClusterOutput::with_output(Vec::<u8>::new());
+        let error = writer.output_class_results(&data).unwrap_err();
+        assert!(error.is::<NoOutputError<std::convert::Infallible>>());
+        // Without any failures, there's just nothing to report
+        let mut student = HashMap::new();
+        student.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        data.insert(String::from("Student A"), student);
+        let mut writer = ClusterOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        assert!(writer.writer.is_empty());
+    }
+}
@@ -1,18 +1,18 @@
 use itertools::Itertools;
 
-use std::io::{self, Stdout, Write};
+use std::io::Write;
 
 use super::super::{ClassResults, TestAnswer};
-use super::OutputMode;
+use super::{case_names, weighted_score, OutputMode};
 
+/// An OutputMode which writes a row of CSV for each student, with how
+/// many cases they passed, their score out of the possible points (where
+/// each case is worth its weight), how long their slowest case took to
+/// run (in seconds, left empty if none of their cases were timed), and a
+/// symbol for their result on each case.
 pub struct CsvOutput<T> {
     writer: T,
 }
-impl CsvOutput<Stdout> {
-    pub fn with_stdout() -> Self {
-        Self::with_output(io::stdout())
-    }
-}
 impl<T> CsvOutput<T> {
     pub fn with_output(writer: T) -> Self {
         CsvOutput { writer }
@@ -27,44 +27,53 @@ where
         &mut self,
         results: &ClassResults,
     ) -> Result<(), Box<dyn std::error::Error + 'static>> {
-        let case_names: Vec<String> = results
+        let case_names = case_names(results);
+        let header = ["Name", "Passed", "Total", "Score", "Possible", "Max time"]
             .iter()
-            .next()
-            .expect("There weren't any test cases")
-            .1
-            .keys()
-            .sorted()
-            .cloned()
-            .collect();
-        write!(self.writer, "Name,Passed,Total,")?;
-        writeln!(self.writer, "{}", case_names.join(","))?;
+            .copied()
+            .chain(case_names.iter().map(|case| case.as_str()))
+            .join(",");
+        writeln!(self.writer, "{}", header)?;
         for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+            let max_time = student_result
+                .values()
+                .filter_map(|result| result.as_ref().ok()?.duration())
+                .max()
+                .map(|duration| format!("{:.3}", duration.as_secs_f64()))
+                .unwrap_or_default();
+            let (score, possible) = weighted_score(student_result);
             write!(
                 self.writer,
-                "{},{},{},",
+                "{},{},{},{},{},{},",
                 student_name,
                 student_result
                     .values()
-                    .filter(|a| if let Ok(TestAnswer::Success) = a {
-                        true
-                    } else {
-                        false
-                    })
+                    .filter(|a| matches!(a.as_deref(), Ok(TestAnswer::Success)))
+                    .count(),
+                student_result
+                    .values()
+                    .filter(|a| !matches!(a.as_deref(), Ok(TestAnswer::Skipped)))
                     .count(),
-                case_names.len()
+                score,
+                possible,
+                max_time
             )?;
             let cases: Vec<_> = case_names
                 .iter()
                 .map(|case| {
                     match student_result
-                        .get(case)
+                        .get(case.as_str())
                         .expect("Student missing test case in result")
+                        .as_deref()
                     {
                         Ok(TestAnswer::Success) => " ",
                         Ok(TestAnswer::Failure) => "F",
                         Ok(TestAnswer::FailWithMessage(_)) => "F",
-                        Ok(TestAnswer::CompileError) => "C",
+                        Ok(TestAnswer::CompileError(_)) => "C",
                         Ok(TestAnswer::Timeout) => "T",
+                        Ok(TestAnswer::Nondeterministic) => "N",
+                        Ok(TestAnswer::Skipped) => "s",
+                        Ok(TestAnswer::NoSubmission(_)) => "-",
                         Err(_) => "!",
                     }
                     .to_string()
@@ -79,35 +88,100 @@ where
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::time::Duration;
 
     use super::*;
+    use crate::CaseResult;
 
     fn make_testing_data() -> ClassResults {
         let mut data = HashMap::new();
         let mut student_a = HashMap::new();
-        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success));
-        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Success));
-        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success));
+        student_a.insert(
+            String::from("Case 1"),
+            Ok(CaseResult::from(TestAnswer::Success).with_duration(Duration::from_millis(250))),
+        );
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(CaseResult::from(TestAnswer::Success).with_duration(Duration::from_millis(1500))),
+        );
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
         data.insert(String::from("Student A"), student_a);
         let mut student_b = HashMap::new();
-        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success));
-        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Failure));
-        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout));
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Failure.into()));
+        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout.into()));
         data.insert(String::from("Student B"), student_b);
         let mut student_c = HashMap::new();
-        student_c.insert(String::from("Case 1"), Ok(TestAnswer::CompileError));
-        student_c.insert(String::from("Case 2"), Ok(TestAnswer::CompileError));
-        student_c.insert(String::from("Case 3"), Ok(TestAnswer::CompileError));
+        student_c.insert(
+            String::from("Case 1"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 3"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
         data.insert(String::from("Student C"), student_c);
         data
     }
 
+    #[test]
+    fn test_skipped_cases() {
+        let mut data = make_testing_data();
+        for student_result in data.values_mut() {
+            student_result.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+        }
+        let mut writer = CsvOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "Name,Passed,Total,Score,Possible,Max time,Case 1,Case 2,Case 3,Case 4\nStudent A,3,3,3,3,1.500, , , ,s\nStudent B,1,3,1,3,, ,F,T,s\nStudent C,0,3,0,3,,C,C,C,s\n");
+    }
+
     #[test]
     fn test_print_output() {
         let data = make_testing_data();
         let mut writer = CsvOutput::with_output(Vec::<u8>::new());
         writer.output_class_results(&data).unwrap();
         let output = std::str::from_utf8(&writer.writer).unwrap();
-        assert_eq!(output, "Name,Passed,Total,Case 1,Case 2,Case 3\nStudent A,3,3, , , \nStudent B,1,3, ,F,T\nStudent C,0,3,C,C,C\n");
+        assert_eq!(output, "Name,Passed,Total,Score,Possible,Max time,Case 1,Case 2,Case 3\nStudent A,3,3,3,3,1.500, , , \nStudent B,1,3,1,3,, ,F,T\nStudent C,0,3,0,3,,C,C,C\n");
+    }
+
+    #[test]
+    fn test_timeout_time() {
+        let mut data = make_testing_data();
+        data.get_mut("Student B").unwrap().insert(
+            String::from("Case 3"),
+            Ok(CaseResult::from(TestAnswer::Timeout).with_duration(Duration::from_secs(5))),
+        );
+        let mut writer = CsvOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "Name,Passed,Total,Score,Possible,Max time,Case 1,Case 2,Case 3\nStudent A,3,3,3,3,1.500, , , \nStudent B,1,3,1,3,5.000, ,F,T\nStudent C,0,3,0,3,,C,C,C\n");
+    }
+
+    #[test]
+    fn test_no_students() {
+        let mut writer = CsvOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&HashMap::new()).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "Name,Passed,Total,Score,Possible,Max time\n");
+    }
+
+    #[test]
+    fn test_weighted_scores() {
+        let mut data = make_testing_data();
+        for student_result in data.values_mut() {
+            for (case, points) in [("Case 2", 2.5), ("Case 3", 0.5)].iter() {
+                let result = student_result.remove(*case).unwrap().unwrap();
+                student_result.insert(String::from(*case), Ok(result.with_points(*points)));
+            }
+        }
+        let mut writer = CsvOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(output, "Name,Passed,Total,Score,Possible,Max time,Case 1,Case 2,Case 3\nStudent A,3,3,4,4,1.500, , , \nStudent B,1,3,1,4,, ,F,T\nStudent C,0,3,0,4,,C,C,C\n");
     }
 }
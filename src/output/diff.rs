@@ -0,0 +1,100 @@
+use itertools::Itertools;
+
+use super::super::ClassResults;
+use super::json::result_status;
+
+/// A change in the result of one student on one case between two runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultChange {
+    /// The student whose result changed
+    pub student: String,
+    /// The case whose result changed
+    pub case: String,
+    /// The status in the old run (as in `result_status`), or "missing"
+    /// if there was no result
+    pub old_status: String,
+    /// The status in the new run (as in `result_status`), or "missing"
+    /// if there is no result
+    pub new_status: String,
+}
+
+/// Finds each (student, case) pair whose status is different between
+/// the old and new results, sorted by student and then case.
+///
+/// Only the status is compared, so a change in just the message of a
+/// failure or error isn't counted.
+pub fn diff_results(old: &ClassResults, new: &ClassResults) -> Vec<ResultChange> {
+    let status = |results: &ClassResults, student: &str, case: &str| {
+        results
+            .get(student)
+            .and_then(|student_results| student_results.get(case))
+            .map_or("missing", |result| result_status(result).0)
+    };
+    old.iter()
+        .chain(new.iter())
+        .flat_map(|(student, student_results)| {
+            student_results
+                .keys()
+                .map(move |case| (student.as_str(), case.as_str()))
+        })
+        .unique()
+        .sorted()
+        .filter_map(|(student, case)| {
+            let old_status = status(old, student, case);
+            let new_status = status(new, student, case);
+            match old_status == new_status {
+                true => None,
+                false => Some(ResultChange {
+                    student: String::from(student),
+                    case: String::from(case),
+                    old_status: String::from(old_status),
+                    new_status: String::from(new_status),
+                }),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::super::TestAnswer;
+    use super::*;
+
+    fn make_testing_data(case_2: TestAnswer) -> ClassResults {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 2"), Ok(case_2.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Timeout.into()));
+        student_b.insert(String::from("Case 2"), Err("Missing file".into()));
+        data.insert(String::from("Student B"), student_b);
+        data
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let old = make_testing_data(TestAnswer::Failure);
+        let new = make_testing_data(TestAnswer::Success);
+        assert_eq!(
+            diff_results(&old, &new),
+            vec![ResultChange {
+                student: String::from("Student A"),
+                case: String::from("Case 2"),
+                old_status: String::from("failure"),
+                new_status: String::from("success"),
+            }]
+        );
+        assert!(diff_results(&old, &old).is_empty());
+        // Only the status matters, not the message
+        let reworded = make_testing_data(TestAnswer::FailWithMessage(String::from("Wrong")));
+        assert!(diff_results(&old, &reworded).is_empty());
+        let mut fewer = make_testing_data(TestAnswer::Failure);
+        fewer.remove("Student B");
+        assert_eq!(diff_results(&old, &fewer).len(), 2);
+        assert_eq!(diff_results(&old, &fewer)[0].new_status, "missing");
+    }
+}
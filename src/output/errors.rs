@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::super::ClassResults;
+
+/// Collects the distinct errors (as opposed to test results) from a run,
+/// along with how many times each one happened.
+///
+/// The errors are sorted from most to least common, so that an error
+/// which affects the whole class (such as a misconfiguration) comes
+/// first.
+pub fn error_summary(results: &ClassResults) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for error in results
+        .values()
+        .flat_map(|student_results| student_results.values())
+        .filter_map(|result| result.as_ref().err())
+    {
+        *counts.entry(error.to_string()).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::TestAnswer;
+    use super::*;
+
+    #[test]
+    fn test_error_summary() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Err("No such file".into()));
+        student_a.insert(String::from("Case 2"), Err("No such file".into()));
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Failure.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Err("No such file".into()));
+        student_b.insert(String::from("Case 2"), Err("Broken pipe".into()));
+        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
+        data.insert(String::from("Student B"), student_b);
+        assert_eq!(
+            error_summary(&data),
+            vec![
+                (String::from("No such file"), 3),
+                (String::from("Broken pipe"), 1)
+            ]
+        );
+        data.clear();
+        assert!(error_summary(&data).is_empty());
+    }
+}
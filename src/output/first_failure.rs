@@ -0,0 +1,83 @@
+use itertools::Itertools;
+
+use std::io::Write;
+
+use super::super::{ClassResults, TestAnswer};
+use super::OutputMode;
+
+/// An OutputMode which lists, for each student, the first case (in
+/// sorted order) which they didn't pass.
+pub struct FirstFailureOutput<T> {
+    writer: T,
+}
+impl<T> FirstFailureOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        FirstFailureOutput { writer }
+    }
+}
+
+impl<T> OutputMode for FirstFailureOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+            let first_failure = student_result
+                .iter()
+                .sorted_by_key(|a| a.0)
+                .find(|(_, result)| {
+                    !matches!(
+                        result.as_deref(),
+                        Ok(TestAnswer::Success) | Ok(TestAnswer::Skipped)
+                    )
+                });
+            match first_failure {
+                Some((case_name, _)) => writeln!(self.writer, "{}: {}", student_name, case_name)?,
+                None => writeln!(self.writer, "{}: all passed", student_name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_first_failures() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Success.into()));
+        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout.into()));
+        data.insert(String::from("Student B"), student_b);
+        let mut student_c = HashMap::new();
+        student_c.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_c.insert(String::from("Case 2"), Ok(TestAnswer::Failure.into()));
+        student_c.insert(String::from("Case 3"), Ok(TestAnswer::Failure.into()));
+        data.insert(String::from("Student C"), student_c);
+        let mut student_d = HashMap::new();
+        student_d.insert(String::from("Case 1"), Err("Couldn't run".into()));
+        student_d.insert(String::from("Case 2"), Ok(TestAnswer::Success.into()));
+        student_d.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
+        data.insert(String::from("Student D"), student_d);
+        let mut writer = FirstFailureOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(
+            output,
+            "Student A: all passed\nStudent B: Case 3\nStudent C: Case 2\nStudent D: Case 1\n"
+        );
+    }
+}
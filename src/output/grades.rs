@@ -0,0 +1,127 @@
+use std::io::{self, Stdout, Write};
+
+use itertools::Itertools;
+
+use super::super::conf::GradeScale;
+use super::super::ClassResults;
+use super::{weighted_score, OutputMode};
+
+/// An OutputMode which writes a CSV gradebook, giving each student's
+/// score, the percentage of the possible points that is, and the letter
+/// grade for that percentage.
+///
+/// Each case is worth the number of points given for it, which students
+/// earn by passing it. Skipped cases don't count towards the possible
+/// points. Students below every threshold in the scale get an empty
+/// grade.
+pub struct GradeOutput<T> {
+    writer: T,
+    scale: GradeScale,
+}
+impl GradeOutput<Stdout> {
+    pub fn with_stdout(scale: GradeScale) -> Self {
+        Self::with_output(io::stdout(), scale)
+    }
+}
+impl<T> GradeOutput<T> {
+    /// Makes an output which writes to `writer`, grading with `scale`
+    pub fn with_output(writer: T, scale: GradeScale) -> Self {
+        GradeOutput { writer, scale }
+    }
+}
+
+impl<T> OutputMode for GradeOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        writeln!(self.writer, "Name,Score,Possible,Percent,Grade")?;
+        for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+            let (score, possible) = weighted_score(student_result);
+            // It's rounded to the one decimal place which is printed
+            // before the letter is found, so the two always agree
+            let percent = match possible > 0.0 {
+                true => (1000.0 * score / possible).round() / 10.0,
+                false => 0.0,
+            };
+            writeln!(
+                self.writer,
+                "{},{},{},{:.1},{}",
+                student_name,
+                score,
+                possible,
+                percent,
+                self.scale.letter_for(percent).unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::super::{CaseResult, TestAnswer};
+    use super::*;
+
+    #[test]
+    fn test_grade_scale() {
+        let scale = GradeScale::from_toml(
+            &"90 = \"A\"\n80 = \"B\"\n70 = \"C\"\n60 = \"D\"\n0 = \"F\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(scale.letter_for(100.0), Some("A"));
+        assert_eq!(scale.letter_for(90.0), Some("A"));
+        assert_eq!(scale.letter_for(89.9), Some("B"));
+        assert_eq!(scale.letter_for(12.0), Some("F"));
+        assert!(GradeScale::from_toml(&"ninety = \"A\"\n".parse().unwrap()).is_err());
+        assert!(GradeScale::from_toml(&"90 = 4.0\n".parse().unwrap()).is_err());
+
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(CaseResult::from(TestAnswer::Success).with_points(7.0)),
+        );
+        student_a.insert(
+            String::from("Case 3"),
+            Ok(CaseResult::from(TestAnswer::Failure).with_points(2.0)),
+        );
+        student_a.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_b.insert(
+            String::from("Case 2"),
+            Ok(CaseResult::from(TestAnswer::Timeout).with_points(7.0)),
+        );
+        // Errors don't carry their case's points, so they're worth 1
+        student_b.insert(String::from("Case 3"), Err("Missing file".into()));
+        student_b.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("Student B"), student_b);
+        // 89.96% rounds up to an A
+        let mut student_c = HashMap::new();
+        student_c.insert(
+            String::from("Case 1"),
+            Ok(CaseResult::from(TestAnswer::Success).with_points(2249.0)),
+        );
+        student_c.insert(
+            String::from("Case 2"),
+            Ok(CaseResult::from(TestAnswer::Failure).with_points(251.0)),
+        );
+        data.insert(String::from("Student C"), student_c);
+        let mut output = GradeOutput::with_output(Vec::<u8>::new(), scale);
+        output.output_class_results(&data).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output.writer).unwrap(),
+            "Name,Score,Possible,Percent,Grade\nStudent A,8,10,80.0,B\nStudent B,1,9,11.1,F\nStudent C,2249,2500,90.0,A\n"
+        );
+    }
+}
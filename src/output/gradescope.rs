@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use super::super::test::StudentResults;
+use super::super::{ClassResults, TestAnswer};
+use super::{case_weight, compile_error_message, OutputMode};
+
+/// An OutputMode which writes a Gradescope `results.json` for each
+/// student, as `<student>.json` in the given directory.
+///
+/// Each case becomes a Gradescope test, worth the number of points
+/// attached to its result (see `case_weight`), whose output starts with the case's description
+/// (if it has one). Skipped cases are left out, and hidden cases only
+/// say whether they passed, without any message about what went wrong.
+pub struct GradescopeOutput {
+    directory: PathBuf,
+}
+impl GradescopeOutput {
+    /// Makes an output which writes into `directory` (creating it if
+    /// needed)
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        GradescopeOutput {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the Gradescope results for a single student
+    fn student_json(&self, student_result: &StudentResults) -> Value {
+        let tests: Vec<Value> = student_result
+            .iter()
+            .sorted_by_key(|a| a.0)
+            .filter(|(_, result)| !matches!(result.as_deref(), Ok(TestAnswer::Skipped)))
+            .map(|(case_name, result)| {
+                let max_score = case_weight(result);
+                let (score, output) = match result.as_deref() {
+                    Ok(TestAnswer::Success) => (max_score, String::from("Passed")),
+                    Ok(TestAnswer::Failure) => (0.0, String::from("Incorrect output")),
+                    Ok(TestAnswer::FailWithMessage(message)) => (0.0, message.clone()),
+                    Ok(TestAnswer::Timeout) => (0.0, String::from("Timed out")),
+                    Ok(TestAnswer::CompileError(message)) => (0.0, compile_error_message(message)),
+                    Ok(TestAnswer::Nondeterministic) => (
+                        0.0,
+                        String::from("Output changed between two runs with the same input"),
+                    ),
+                    Ok(TestAnswer::NoSubmission(message)) => (0.0, message.clone()),
+                    Ok(TestAnswer::Skipped) => unreachable!("Skipped cases were filtered out"),
+                    Err(e) => (0.0, format!("Error while testing: {}", e)),
+                };
+                let passed = matches!(result.as_deref(), Ok(TestAnswer::Success));
+                let hidden = result.as_ref().is_ok_and(|result| result.is_hidden());
+                let output = if hidden && !passed {
+                    String::from("Failed (the details of this case are hidden)")
+                } else {
+                    output
+                };
+                let output = match result.as_ref().ok().and_then(|result| result.description()) {
+                    Some(description) => format!("{}\n{}", description, output),
+                    None => output,
+                };
+                json!({
+                    "name": case_name,
+                    "score": score,
+                    "max_score": max_score,
+                    "output": output,
+                })
+            })
+            .collect();
+        let score: f64 = tests.iter().filter_map(|test| test["score"].as_f64()).sum();
+        json!({
+            "score": score,
+            "tests": tests,
+        })
+    }
+}
+
+impl OutputMode for GradescopeOutput {
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        fs::create_dir_all(&self.directory)?;
+        for (student_name, student_result) in results.iter() {
+            fs::write(
+                self.directory.join(format!("{}.json", student_name)),
+                serde_json::to_string_pretty(&self.student_json(student_result))?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::super::conf::TestConfig;
+    use super::super::super::test::test_from_configuration;
+    use super::super::super::CaseResult;
+    use super::super::get_output_mode_for_file;
+    use super::*;
+
+    #[test]
+    fn test_gradescope_schema() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(
+                CaseResult::from(TestAnswer::FailWithMessage(String::from("Off by one")))
+                    .with_points(2.0),
+            ),
+        );
+        student_a.insert(
+            String::from("Case 3"),
+            Ok(CaseResult::from(TestAnswer::Success)
+                .with_description(String::from("Handles empty input"))
+                .with_points(2.5)),
+        );
+        student_a.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("Student A"), student_a);
+        let directory = tempfile::tempdir().unwrap();
+        let mut output = GradescopeOutput::new(directory.path());
+        output.output_class_results(&data).unwrap();
+        let written: Value = serde_json::from_str(
+            &fs::read_to_string(directory.path().join("Student A.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            written,
+            json!({
+                "score": 3.5,
+                "tests": [
+                    {"name": "Case 1", "score": 1.0, "max_score": 1.0, "output": "Passed"},
+                    {"name": "Case 2", "score": 0.0, "max_score": 2.0, "output": "Off by one"},
+                    {"name": "Case 3", "score": 2.5, "max_score": 2.5, "output": "Handles empty input\nPassed"},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_hidden_case_details() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(
+            String::from("public"),
+            Ok(TestAnswer::FailWithMessage(String::from("Expected 7, got 8")).into()),
+        );
+        student_a.insert(
+            String::from("secret"),
+            Ok(CaseResult::from(TestAnswer::FailWithMessage(String::from(
+                "Expected 42, got 41",
+            )))
+            .with_points(3.0)
+            .with_hidden(true)),
+        );
+        data.insert(String::from("Student A"), student_a);
+        let directory = tempfile::tempdir().unwrap();
+        let mut output = GradescopeOutput::new(directory.path());
+        output.output_class_results(&data).unwrap();
+        let written: Value = serde_json::from_str(
+            &fs::read_to_string(directory.path().join("Student A.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            written,
+            json!({
+                "score": 0.0,
+                "tests": [
+                    {"name": "public", "score": 0.0, "max_score": 1.0, "output": "Expected 7, got 8"},
+                    {"name": "secret", "score": 0.0, "max_score": 3.0, "output": "Failed (the details of this case are hidden)"},
+                ],
+            })
+        );
+        // The instructor's CSV still has the hidden case's result, worth
+        // the same points
+        let csv_file = directory.path().join("results.csv");
+        get_output_mode_for_file("csv", csv_file.to_str().unwrap(), false)
+            .unwrap()
+            .unwrap()
+            .output_class_results(&data)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&csv_file).unwrap(),
+            "Name,Passed,Total,Score,Possible,Max time,public,secret\nStudent A,0,2,0,4,,F,F\n"
+        );
+    }
+
+    #[test]
+    fn test_case_descriptions() {
+        let config = TestConfig::from_file("test-data/python-desc-test.toml").unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let mut output = GradescopeOutput::new(directory.path());
+        output.output_class_results(&results).unwrap();
+        let written: Value = serde_json::from_str(
+            &fs::read_to_string(directory.path().join("correct.json")).unwrap(),
+        )
+        .unwrap();
+        // Descriptions come from 1.desc and the inline case, and 2 has none
+        assert_eq!(
+            written,
+            json!({
+                "score": 3.0,
+                "tests": [
+                    {"name": "1", "score": 1.0, "max_score": 1.0, "output": "Adds two small numbers\nPassed"},
+                    {"name": "2", "score": 1.0, "max_score": 1.0, "output": "Passed"},
+                    {"name": "zero", "score": 1.0, "max_score": 1.0, "output": "Adds zeros\nPassed"},
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt::Display;
+use std::io::Write;
+
+use itertools::Itertools;
+
+use super::super::{CaseResult, ClassResults, TestAnswer};
+use super::junit::escape_xml;
+use super::{case_names, compile_error_message, OutputMode};
+
+/// The styles for the report, which color each cell by its result
+const STYLE: &str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 4px 8px; text-align: center; }
+th:first-child, td:first-child { text-align: left; }
+td.success { background-color: #b7e1b0; }
+td.failure { background-color: #f2b5b5; }
+td.timeout { background-color: #f7e59e; }
+td.compile_error { background-color: #cccccc; }
+td.error { background-color: #e0a0e0; }
+";
+
+/// An OutputMode which writes a standalone HTML page with a table of the
+/// results, where each case's cell is colored by its result: green for
+/// passing, red for failing, yellow for timing out, and gray for not
+/// compiling. Hovering over a cell shows its message, if it has one.
+pub struct HtmlOutput<T> {
+    writer: T,
+}
+impl<T> HtmlOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        HtmlOutput { writer }
+    }
+}
+
+/// Returns the class of the cell for a result (which picks its color),
+/// the text in it, and its message, if any
+fn cell_contents<E: Display>(
+    result: Result<&CaseResult, E>,
+) -> (&'static str, &'static str, Option<String>) {
+    match result.map(|result| result.answer()) {
+        Ok(TestAnswer::Success) => ("success", "", None),
+        Ok(TestAnswer::Failure) => ("failure", "F", None),
+        Ok(TestAnswer::FailWithMessage(message)) => ("failure", "F", Some(message.clone())),
+        Ok(TestAnswer::Nondeterministic) => (
+            "failure",
+            "N",
+            Some(String::from(
+                "Output changed between two runs with the same input",
+            )),
+        ),
+        Ok(TestAnswer::Timeout) => ("timeout", "T", None),
+        Ok(TestAnswer::CompileError(message)) => {
+            ("compile_error", "C", Some(compile_error_message(message)))
+        }
+        Ok(TestAnswer::Skipped) => ("skipped", "s", None),
+        Ok(TestAnswer::NoSubmission(message)) => ("compile_error", "-", Some(message.clone())),
+        Err(e) => ("error", "!", Some(e.to_string())),
+    }
+}
+
+impl<T> OutputMode for HtmlOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let case_names = case_names(results);
+        writeln!(self.writer, "<!DOCTYPE html>")?;
+        writeln!(self.writer, "<html>")?;
+        writeln!(self.writer, "<head>")?;
+        writeln!(self.writer, "<meta charset=\"utf-8\">")?;
+        writeln!(self.writer, "<title>Test results</title>")?;
+        write!(self.writer, "<style>\n{}</style>\n", STYLE)?;
+        writeln!(self.writer, "</head>")?;
+        writeln!(self.writer, "<body>")?;
+        writeln!(self.writer, "<table>")?;
+        write!(self.writer, "<tr><th></th><th>Passed</th><th>Total</th>")?;
+        for case in case_names.iter() {
+            write!(self.writer, "<th>{}</th>", escape_xml(case))?;
+        }
+        writeln!(self.writer, "</tr>")?;
+        for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+            write!(
+                self.writer,
+                "<tr><td>{}</td><td>{}</td><td>{}</td>",
+                escape_xml(student_name),
+                student_result
+                    .values()
+                    .filter(|a| matches!(a.as_deref(), Ok(TestAnswer::Success)))
+                    .count(),
+                student_result
+                    .values()
+                    .filter(|a| !matches!(a.as_deref(), Ok(TestAnswer::Skipped)))
+                    .count()
+            )?;
+            for case in case_names.iter() {
+                let (class, text, message) = cell_contents(
+                    student_result
+                        .get(case.as_str())
+                        .expect("Student missing case in their results")
+                        .as_ref(),
+                );
+                match message {
+                    Some(message) => write!(
+                        self.writer,
+                        "<td class=\"{}\" title=\"{}\">{}</td>",
+                        class,
+                        escape_xml(&message),
+                        text
+                    )?,
+                    None => write!(self.writer, "<td class=\"{}\">{}</td>", class, text)?,
+                }
+            }
+            writeln!(self.writer, "</tr>")?;
+        }
+        writeln!(self.writer, "</table>")?;
+        writeln!(self.writer, "</body>")?;
+        writeln!(self.writer, "</html>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_html_output() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::FailWithMessage(String::from("expected <3>, got \"4\"")).into()),
+        );
+        data.insert(String::from("Student <A>"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Timeout.into()));
+        student_b.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        data.insert(String::from("Student B"), student_b);
+        let mut writer = HtmlOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        assert!(output.starts_with("<!DOCTYPE html>\n<html>\n"));
+        assert!(output.ends_with("</table>\n</body>\n</html>\n"));
+        assert!(output.contains(
+            "<tr><th></th><th>Passed</th><th>Total</th><th>Case 1</th><th>Case 2</th></tr>\n"
+        ));
+        assert!(output.contains("<tr><td>Student &lt;A&gt;</td><td>1</td><td>2</td><td class=\"success\"></td><td class=\"failure\" title=\"expected &lt;3&gt;, got &quot;4&quot;\">F</td></tr>\n"));
+        assert!(output.contains("<tr><td>Student B</td><td>0</td><td>2</td><td class=\"timeout\">T</td><td class=\"compile_error\" title=\"Failed to compile\">C</td></tr>\n"));
+    }
+}
@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::io::Write;
+use std::time::Duration;
+
+use errormake::errormake;
+use serde_json::{json, Map, Value};
+
+use super::super::test::StudentResults;
+use super::super::{CaseResult, ClassResults, TestAnswer};
+use super::OutputMode;
+
+/// An OutputMode which writes the results as JSON, which can be read back
+/// in with `results_from_json` (for example, to compare two runs).
+///
+/// The JSON is an object mapping each student to an object, which maps
+/// each case to `{"status": ..., "message": ..., "description": ...,
+/// "points": ..., "hidden": true, "peak_memory_kb": ..., "seconds": ...}`
+/// (see `result_status` for the statuses), where "points" is how many
+/// points the case is worth (so that weighted scores can be worked out
+/// from it), and "seconds" is how long the student's code took to run.
+/// The message, description, points, peak memory use, and time are left
+/// out if there aren't any, and "hidden" is left out unless the case's
+/// details are hidden from students.
+pub struct JsonOutput<T> {
+    writer: T,
+}
+impl<T> JsonOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        JsonOutput { writer }
+    }
+}
+
+impl<T> OutputMode for JsonOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        serde_json::to_writer_pretty(&mut self.writer, &results_to_json(results))?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+}
+
+/// Returns the name of the status of a result, along with the message
+/// it carries, if any.
+///
+/// The statuses are "success", "failure", "timeout", "compile_error",
+/// "nondeterministic", "skipped", "no_submission", and "error" (for
+/// results which are errors, rather than test answers).
+pub fn result_status(
+    result: &Result<CaseResult, Box<dyn Error + 'static>>,
+) -> (&'static str, Option<String>) {
+    match result.as_deref() {
+        Ok(TestAnswer::Success) => ("success", None),
+        Ok(TestAnswer::Failure) => ("failure", None),
+        Ok(TestAnswer::FailWithMessage(message)) => ("failure", Some(message.clone())),
+        Ok(TestAnswer::CompileError(message)) => ("compile_error", Some(message.clone())),
+        Ok(TestAnswer::Timeout) => ("timeout", None),
+        Ok(TestAnswer::Nondeterministic) => ("nondeterministic", None),
+        Ok(TestAnswer::Skipped) => ("skipped", None),
+        Ok(TestAnswer::NoSubmission(message)) => ("no_submission", Some(message.clone())),
+        Err(e) => ("error", Some(e.to_string())),
+    }
+}
+
+/// Converts the results into JSON, in the format written by
+/// `JsonOutput`.
+pub fn results_to_json(results: &ClassResults) -> Value {
+    Value::Object(
+        results
+            .iter()
+            .map(|(student_name, student_results)| {
+                let cases: Map<String, Value> = student_results
+                    .iter()
+                    .map(|(case_name, result)| {
+                        let (status, message) = result_status(result);
+                        let mut case = Map::new();
+                        case.insert(String::from("status"), json!(status));
+                        if let Some(message) = message {
+                            case.insert(String::from("message"), json!(message));
+                        }
+                        if let Some(description) =
+                            result.as_ref().ok().and_then(|result| result.description())
+                        {
+                            case.insert(String::from("description"), json!(description));
+                        }
+                        if let Some(points) =
+                            result.as_ref().ok().and_then(|result| result.points())
+                        {
+                            case.insert(String::from("points"), json!(points));
+                        }
+                        if result.as_ref().is_ok_and(|result| result.is_hidden()) {
+                            case.insert(String::from("hidden"), json!(true));
+                        }
+                        if let Some(peak_memory_kb) = result
+                            .as_ref()
+                            .ok()
+                            .and_then(|result| result.peak_memory_kb())
+                        {
+                            case.insert(String::from("peak_memory_kb"), json!(peak_memory_kb));
+                        }
+                        if let Some(duration) =
+                            result.as_ref().ok().and_then(|result| result.duration())
+                        {
+                            case.insert(String::from("seconds"), json!(duration.as_secs_f64()));
+                        }
+                        (case_name.clone(), Value::Object(case))
+                    })
+                    .collect();
+                (student_name.clone(), Value::Object(cases))
+            })
+            .collect(),
+    )
+}
+
+/// Reads results from JSON in the format written by `JsonOutput`.
+///
+/// Results which were errors are read back as errors with the same
+/// message, and captured output isn't kept.
+pub fn results_from_json(json: &str) -> Result<ClassResults, Box<dyn Error + 'static>> {
+    let format_error =
+        |description: &str| ResultsFormatError::with_description(String::from(description));
+    let value: Value = serde_json::from_str(json)?;
+    value
+        .as_object()
+        .ok_or_else(|| format_error("The results must be a JSON object"))?
+        .iter()
+        .map(|(student_name, cases)| {
+            let cases = cases
+                .as_object()
+                .ok_or_else(|| format_error("Each student's results must be a JSON object"))?;
+            let student_results: StudentResults = cases
+                .iter()
+                .map(|(case_name, case)| {
+                    let status = case["status"]
+                        .as_str()
+                        .ok_or_else(|| format_error("Each case must have a string \"status\""))?;
+                    let message = case["message"].as_str().map(String::from);
+                    let result: Result<CaseResult, _> = match (status, message) {
+                        ("success", _) => Ok(TestAnswer::Success.into()),
+                        ("failure", Some(message)) => {
+                            Ok(TestAnswer::FailWithMessage(message).into())
+                        }
+                        ("failure", None) => Ok(TestAnswer::Failure.into()),
+                        ("compile_error", message) => {
+                            Ok(TestAnswer::CompileError(message.unwrap_or_default()).into())
+                        }
+                        ("timeout", _) => Ok(TestAnswer::Timeout.into()),
+                        ("nondeterministic", _) => Ok(TestAnswer::Nondeterministic.into()),
+                        ("skipped", _) => Ok(TestAnswer::Skipped.into()),
+                        ("no_submission", message) => {
+                            Ok(TestAnswer::NoSubmission(message.unwrap_or_default()).into())
+                        }
+                        ("error", message) => Err(message.unwrap_or_default().into()),
+                        (status, _) => {
+                            return Err(ResultsFormatError::with_description(format!(
+                                "Unrecognized status: {}",
+                                status
+                            )))
+                        }
+                    };
+                    let result = match case["description"].as_str() {
+                        Some(description) => {
+                            result.map(|result| result.with_description(String::from(description)))
+                        }
+                        None => result,
+                    };
+                    let result = match case["points"].as_f64() {
+                        Some(points) => result.map(|result| result.with_points(points)),
+                        None => result,
+                    };
+                    let result = match case["hidden"].as_bool() {
+                        Some(hidden) => result.map(|result| result.with_hidden(hidden)),
+                        None => result,
+                    };
+                    let result = match case["peak_memory_kb"].as_u64() {
+                        Some(peak_memory_kb) => {
+                            result.map(|result| result.with_peak_memory_kb(peak_memory_kb))
+                        }
+                        None => result,
+                    };
+                    let result = match case["seconds"].as_f64() {
+                        Some(seconds) if seconds >= 0.0 => result
+                            .map(|result| result.with_duration(Duration::from_secs_f64(seconds))),
+                        _ => result,
+                    };
+                    Ok((case_name.clone(), result))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok((student_name.clone(), student_results))
+        })
+        .collect()
+}
+
+errormake!(#[doc="An error in the format of saved results"] pub ResultsFormatError);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(
+            String::from("Case 1"),
+            Ok(CaseResult::from(TestAnswer::Success)
+                .with_description(String::from("Handles empty input"))
+                .with_points(2.5)
+                .with_hidden(true)
+                .with_peak_memory_kb(2048)
+                .with_duration(Duration::from_millis(1250))),
+        );
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::FailWithMessage(String::from("Off by one")).into()),
+        );
+        student_a.insert(String::from("Case 3"), Err("Missing file".into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut writer = JsonOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let read = results_from_json(std::str::from_utf8(&writer.writer).unwrap()).unwrap();
+        let student_a = &read["Student A"];
+        assert_eq!(student_a.len(), 3);
+        assert_eq!(student_a["Case 1"].as_ref().unwrap(), &TestAnswer::Success);
+        assert_eq!(
+            student_a["Case 1"].as_ref().unwrap().description(),
+            Some("Handles empty input")
+        );
+        assert_eq!(
+            student_a["Case 1"].as_ref().unwrap().peak_memory_kb(),
+            Some(2048)
+        );
+        assert_eq!(student_a["Case 1"].as_ref().unwrap().points(), Some(2.5));
+        assert_eq!(student_a["Case 2"].as_ref().unwrap().description(), None);
+        assert_eq!(student_a["Case 2"].as_ref().unwrap().points(), None);
+        assert!(student_a["Case 1"].as_ref().unwrap().is_hidden());
+        assert!(!student_a["Case 2"].as_ref().unwrap().is_hidden());
+        assert_eq!(student_a["Case 2"].as_ref().unwrap().peak_memory_kb(), None);
+        assert_eq!(
+            student_a["Case 1"].as_ref().unwrap().duration(),
+            Some(Duration::from_millis(1250))
+        );
+        assert_eq!(student_a["Case 2"].as_ref().unwrap().duration(), None);
+        assert_eq!(
+            student_a["Case 2"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from("Off by one"))
+        );
+        assert_eq!(
+            student_a["Case 3"].as_ref().unwrap_err().to_string(),
+            "Missing file"
+        );
+        assert!(
+            results_from_json("{\"Student A\": {\"Case 1\": {\"status\": \"great\"}}}").is_err()
+        );
+        assert!(results_from_json("[]").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::io::Write;
+
+use itertools::Itertools;
+
+use super::super::{ClassResults, TestAnswer};
+use super::{compile_error_message, OutputMode};
+
+/// An OutputMode which writes the results as JUnit XML, which CI
+/// systems (such as Jenkins and GitLab) know how to show.
+///
+/// There's a `<testsuite>` for each student, containing a `<testcase>`
+/// for each case. Failed cases have a `<failure>`, and cases which
+/// timed out, didn't compile, or couldn't be tested have an `<error>`.
+pub struct JUnitOutput<T> {
+    writer: T,
+}
+impl<T> JUnitOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        JUnitOutput { writer }
+    }
+}
+
+/// What is written inside a `<testcase>` for a result
+enum Outcome {
+    Passed,
+    Failure(String),
+    Error(String),
+    Skipped,
+}
+
+impl<T> OutputMode for JUnitOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        writeln!(self.writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(self.writer, "<testsuites name=\"stipulate\">")?;
+        for (student_name, student_results) in results.iter().sorted_by_key(|a| a.0) {
+            let outcomes: Vec<(&String, Outcome)> = student_results
+                .iter()
+                .sorted_by_key(|a| a.0)
+                .map(|(case_name, result)| {
+                    let outcome = match result.as_deref() {
+                        Ok(TestAnswer::Success) => Outcome::Passed,
+                        Ok(TestAnswer::Failure) => {
+                            Outcome::Failure(String::from("Incorrect output"))
+                        }
+                        Ok(TestAnswer::FailWithMessage(message)) => {
+                            Outcome::Failure(message.clone())
+                        }
+                        Ok(TestAnswer::Nondeterministic) => Outcome::Failure(String::from(
+                            "Output changed between two runs with the same input",
+                        )),
+                        Ok(TestAnswer::Timeout) => Outcome::Error(String::from("Timed out")),
+                        Ok(TestAnswer::CompileError(message)) => {
+                            Outcome::Error(compile_error_message(message))
+                        }
+                        Ok(TestAnswer::NoSubmission(message)) => Outcome::Error(message.clone()),
+                        Ok(TestAnswer::Skipped) => Outcome::Skipped,
+                        Err(e) => Outcome::Error(format!("Error while testing: {}", e)),
+                    };
+                    (case_name, outcome)
+                })
+                .collect();
+            let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|(_, o)| f(o)).count();
+            writeln!(
+                self.writer,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">",
+                escape_xml(student_name),
+                outcomes.len(),
+                count(|o| matches!(o, Outcome::Failure(_))),
+                count(|o| matches!(o, Outcome::Error(_))),
+                count(|o| matches!(o, Outcome::Skipped)),
+            )?;
+            for (case_name, outcome) in outcomes.iter() {
+                let open_tag = format!(
+                    "    <testcase name=\"{}\" classname=\"{}\"",
+                    escape_xml(case_name),
+                    escape_xml(student_name)
+                );
+                match outcome {
+                    Outcome::Passed => writeln!(self.writer, "{} />", open_tag)?,
+                    Outcome::Failure(message) | Outcome::Error(message) => {
+                        let tag = match outcome {
+                            Outcome::Failure(_) => "failure",
+                            _ => "error",
+                        };
+                        let message = escape_xml(message);
+                        writeln!(self.writer, "{}>", open_tag)?;
+                        writeln!(
+                            self.writer,
+                            "      <{} message=\"{}\">{}</{}>",
+                            tag, message, message, tag
+                        )?;
+                        writeln!(self.writer, "    </testcase>")?;
+                    }
+                    Outcome::Skipped => {
+                        writeln!(self.writer, "{}>", open_tag)?;
+                        writeln!(self.writer, "      <skipped />")?;
+                        writeln!(self.writer, "    </testcase>")?;
+                    }
+                }
+            }
+            writeln!(self.writer, "  </testsuite>")?;
+        }
+        writeln!(self.writer, "</testsuites>")?;
+        Ok(())
+    }
+}
+
+/// Escapes the characters which are special in XML text and attributes.
+///
+/// Characters which XML 1.0 doesn't allow at all, even escaped (such as
+/// the escape at the start of an ANSI color code, which student output
+/// may contain), are replaced with U+FFFD, the replacement character.
+pub(super) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_junit_output() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::FailWithMessage(String::from("expected <3>, got \"4\" & more")).into()),
+        );
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Timeout.into()));
+        student_a.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("O'Brien <ob>"), student_a);
+        let mut writer = JUnitOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        let expected = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"stipulate\">
+  <testsuite name=\"O&apos;Brien &lt;ob&gt;\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\">
+    <testcase name=\"Case 1\" classname=\"O&apos;Brien &lt;ob&gt;\" />
+    <testcase name=\"Case 2\" classname=\"O&apos;Brien &lt;ob&gt;\">
+      <failure message=\"expected &lt;3&gt;, got &quot;4&quot; &amp; more\">expected &lt;3&gt;, got &quot;4&quot; &amp; more</failure>
+    </testcase>
+    <testcase name=\"Case 3\" classname=\"O&apos;Brien &lt;ob&gt;\">
+      <error message=\"Timed out\">Timed out</error>
+    </testcase>
+    <testcase name=\"Case 4\" classname=\"O&apos;Brien &lt;ob&gt;\">
+      <skipped />
+    </testcase>
+  </testsuite>
+</testsuites>
+";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_control_characters() {
+        assert_eq!(
+            escape_xml("\u{1b}[31mred\u{1b}[0m\0\ttab\nline"),
+            "\u{fffd}[31mred\u{fffd}[0m\u{fffd}\ttab\nline"
+        );
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(
+            String::from("Case 1"),
+            Ok(TestAnswer::FailWithMessage(String::from("got \u{1b}[1m5\u{1b}[0m")).into()),
+        );
+        data.insert(String::from("Student A"), student_a);
+        let mut writer = JUnitOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        assert!(!output.contains('\u{1b}'));
+        assert!(output.contains(
+            "<failure message=\"got \u{fffd}[1m5\u{fffd}[0m\">got \u{fffd}[1m5\u{fffd}[0m</failure>"
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::process::{Command, Stdio};
+
+use sha2::{Digest, Sha256};
+use toml::value::{Table, Value};
+
+use super::super::test::load_test_cases;
+use super::super::TestConfig;
+
+/// Makes a manifest describing the environment of a test run, so that
+/// the run can be audited or reproduced later.
+///
+/// The manifest is a toml document containing the version of stipulate,
+/// the command used to run student code (and the version it reports),
+/// the text of the config file (`config_text`), and a SHA-256 hash of
+/// the input and expected output of each test case.
+pub fn run_manifest(
+    config: &TestConfig,
+    config_text: &str,
+) -> Result<String, Box<dyn Error + 'static>> {
+    let cases = load_test_cases(config)?;
+    let command = config.command(config.target_dir());
+    let mut manifest = Table::new();
+    manifest.insert(
+        String::from("stipulate_version"),
+        Value::String(String::from(env!("CARGO_PKG_VERSION"))),
+    );
+    manifest.insert(
+        String::from("name"),
+        Value::String(String::from(config.name())),
+    );
+    if let Some(version) = command_version(&command) {
+        manifest.insert(String::from("command_version"), Value::String(version));
+    }
+    manifest.insert(String::from("command"), Value::String(command));
+    manifest.insert(
+        String::from("config"),
+        Value::String(String::from(config_text)),
+    );
+    manifest.insert(
+        String::from("cases"),
+        Value::Table(
+            cases
+                .iter()
+                .map(|(name, case)| {
+                    let mut hashes = Table::new();
+                    hashes.insert(
+                        String::from("input_sha256"),
+                        Value::String(sha256_hex(&case.get_input()?)),
+                    );
+                    hashes.insert(
+                        String::from("output_sha256"),
+                        Value::String(sha256_hex(&case.get_output()?)),
+                    );
+                    Ok((name.clone(), Value::Table(hashes)))
+                })
+                .collect::<Result<_, Box<dyn std::error::Error + 'static>>>()?,
+        ),
+    );
+    Ok(toml::to_string(&Value::Table(manifest))?)
+}
+
+/// Returns the SHA-256 hash of the given text, in hexadecimal
+pub(super) fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Asks the given command for its version (with `--version`), and
+/// returns the first line it prints, if it gives one.
+fn command_version(command: &str) -> Option<String> {
+    let output = Command::new(command)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_has_case_hashes() {
+        let config_text = std::fs::read_to_string("test-data/python-test-1.toml").unwrap();
+        let config = TestConfig::from_toml_values(config_text.parse().unwrap()).unwrap();
+        let manifest: Value = run_manifest(&config, &config_text)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            manifest["stipulate_version"].as_str(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(manifest["config"].as_str(), Some(config_text.as_str()));
+        let cases = manifest["cases"].as_table().unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(
+            cases["1"]["input_sha256"].as_str(),
+            Some(sha256_hex("3\n4\n").as_str())
+        );
+        assert_eq!(
+            cases["2"]["output_sha256"].as_str(),
+            Some(sha256_hex("8\n").as_str())
+        );
+        assert_eq!(
+            sha256_hex(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -1,12 +1,55 @@
+mod case_list;
+mod checkpoint;
+#[cfg(feature = "table-output")]
+mod cli;
+mod clusters;
 mod csv;
+mod diff;
+mod errors;
+mod first_failure;
+mod grades;
+mod gradescope;
+mod html;
+mod json;
+mod junit;
+mod manifest;
+mod pass_rates;
+mod progress;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod summary;
 #[cfg(feature = "table-output")]
 mod table;
+mod tee;
+mod toml;
 
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs::File;
+use std::io::{self, Write};
+use std::sync::RwLock;
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+
+pub use case_list::case_listing;
+pub use checkpoint::Checkpoint;
+#[cfg(feature = "table-output")]
+pub use cli::Print;
+pub use diff::{diff_results, ResultChange};
+pub use errors::error_summary;
+pub use grades::GradeOutput;
+pub use gradescope::GradescopeOutput;
+pub use json::{result_status, results_from_json, results_to_json};
+pub use manifest::run_manifest;
+pub use pass_rates::{case_pass_rates, outlier_case_warnings};
+pub use progress::case_progress_line;
+pub use tee::Tee;
 
 // type ClassResults = HashMap<String, StudentResults>;
-// type StudentResults = HashMap<String, Result<TestAnswer, Box<dyn Error + 'static>>>;
-use super::ClassResults;
+// type StudentResults = HashMap<String, Result<CaseResult, Box<dyn Error + 'static>>>;
+use super::test::{StudentResults, DEFAULT_POINTS};
+use super::{CaseResult, ClassResults, TestAnswer};
 
 pub trait OutputMode {
     fn output_class_results(
@@ -15,24 +58,228 @@ pub trait OutputMode {
     ) -> Result<(), Box<dyn std::error::Error + 'static>>;
 }
 
-pub fn get_output_mode(name: &str) -> Option<Box<dyn OutputMode + 'static>> {
-    match name {
-        #[cfg(feature = "table-output")]
-        "print" => Some(Box::new(table::Table::with_stdout())),
-        "csv" => Some(Box::new(csv::CsvOutput::with_stdout())),
-        _ => None,
+/// Describes a compile error for a student, followed by what went wrong
+/// (such as the compiler's messages), if that's known
+fn compile_error_message(message: &str) -> String {
+    match message.is_empty() {
+        true => String::from("Failed to compile"),
+        false => format!("Failed to compile:\n{}", message),
     }
 }
 
+/// Returns the number of points a case is worth: the points attached to
+/// its result (see `CaseResult::points`), or else the default number of
+/// points (which is what results that are errors are worth).
+pub fn case_weight(result: &Result<CaseResult, Box<dyn Error + 'static>>) -> f64 {
+    result
+        .as_ref()
+        .ok()
+        .and_then(|result| result.points())
+        .unwrap_or(DEFAULT_POINTS)
+}
+
+/// Returns the number of points a student earned (by passing cases), and
+/// the number of points they could have earned, where each case is worth
+/// its weight (see `case_weight`). Skipped cases don't count towards
+/// either.
+pub fn weighted_score(student_results: &StudentResults) -> (f64, f64) {
+    student_results
+        .iter()
+        .filter(|(_, result)| !matches!(result.as_deref(), Ok(TestAnswer::Skipped)))
+        .fold((0.0, 0.0), |(score, possible), (_, result)| {
+            let points = case_weight(result);
+            match result.as_deref() {
+                Ok(TestAnswer::Success) => (score + points, possible + points),
+                _ => (score, possible + points),
+            }
+        })
+}
+
+/// The names of the cases in the results, in sorted order, taken from
+/// any one of the students (who all have the same cases). There are
+/// none if there aren't any students.
+fn case_names(results: &ClassResults) -> Vec<&String> {
+    match results.values().next() {
+        Some(student_results) => student_results.keys().sorted().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// A function which makes an output mode writing to the given writer
+pub type OutputModeFactory = dyn Fn(Box<dyn Write>) -> Box<dyn OutputMode> + Send + Sync;
+
+lazy_static! {
+    /// The output modes registered with `register_output_mode`, by name
+    static ref REGISTERED_OUTPUT_MODES: RwLock<HashMap<String, Box<OutputModeFactory>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers an output mode under the given name, so that it can be
+/// looked up by `get_output_mode` and friends, like the built-in ones.
+///
+/// The factory is given the writer to write the output to (which is
+/// standard output or a file). A registered mode is used instead of a
+/// built-in one with the same name (including "sqlite", and "gradescope"
+/// and "grades" from the command line), and registering a name again
+/// replaces the earlier mode.
+pub fn register_output_mode<F>(name: &str, factory: F)
+where
+    F: Fn(Box<dyn Write>) -> Box<dyn OutputMode> + Send + Sync + 'static,
+{
+    REGISTERED_OUTPUT_MODES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(String::from(name), Box::new(factory));
+}
+
+/// Returns whether an output mode with the given name was registered
+/// with `register_output_mode`
+pub fn is_registered_output_mode(name: &str) -> bool {
+    REGISTERED_OUTPUT_MODES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .contains_key(name)
+}
+
+/// Returns whether there's an output mode with the given name, either
+/// registered or built in
+fn output_mode_exists(name: &str) -> bool {
+    is_registered_output_mode(name) || get_output_mode_for_writer(name, io::sink()).is_some()
+}
+
+/// Returns the registered output mode with the given name, writing to
+/// `writer`, if there is one
+fn registered_output_mode(name: &str, writer: Box<dyn Write>) -> Option<Box<dyn OutputMode>> {
+    REGISTERED_OUTPUT_MODES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(name)
+        .map(|factory| factory(writer))
+}
+
+/// Returns the output mode with the given name, writing to standard
+/// output. Modes registered with `register_output_mode` are looked for
+/// first, and then the built-in ones.
+pub fn get_output_mode(name: &str) -> Option<Box<dyn OutputMode + 'static>> {
+    get_output_mode_for_writer(name, io::stdout())
+}
+
+/// Returns the output mode with the given name, writing to the given
+/// file. If `tee` is set, the output is also echoed to standard output.
+///
+/// This is `Ok(None)` if there's no output mode with that name, in which
+/// case the file isn't touched, and an error if the file couldn't be
+/// created.
+///
+/// The "sqlite" mode (with the "sqlite" feature) can only write to a
+/// file, which is opened as a database instead of being overwritten.
 pub fn get_output_mode_for_file(
     name: &str,
     filename: &str,
+    tee: bool,
+) -> Result<Option<Box<dyn OutputMode + 'static>>, Box<dyn Error + 'static>> {
+    #[cfg(feature = "sqlite")]
+    if name == "sqlite" && !is_registered_output_mode(name) {
+        return Ok(Some(Box::new(sqlite::SqliteOutput::open(filename)?)));
+    }
+    if !output_mode_exists(name) {
+        return Ok(None);
+    }
+    let file = File::create(filename)?;
+    Ok(match tee {
+        true => get_output_mode_for_writer(name, Tee::new(file, io::stdout())),
+        false => get_output_mode_for_writer(name, file),
+    })
+}
+
+/// Returns the output mode with the given name, writing to `writer`.
+/// Modes registered with `register_output_mode` are looked for first,
+/// and then the built-in ones.
+pub fn get_output_mode_for_writer<W: Write + 'static>(
+    name: &str,
+    writer: W,
 ) -> Option<Box<dyn OutputMode + 'static>> {
-    let file = File::create(filename).ok()?;
+    if is_registered_output_mode(name) {
+        return registered_output_mode(name, Box::new(writer));
+    }
     match name {
         #[cfg(feature = "table-output")]
-        "print" => Some(Box::new(table::Table::with_output(file))),
-        "csv" => Some(Box::new(csv::CsvOutput::with_output(file))),
+        "print" => Some(Box::new(table::Table::with_output(writer))),
+        #[cfg(feature = "table-output")]
+        "compact" => Some(Box::new(cli::Print::with_output(writer))),
+        "csv" => Some(Box::new(csv::CsvOutput::with_output(writer))),
+        "clusters" => Some(Box::new(clusters::ClusterOutput::with_output(writer))),
+        "first_failure" => Some(Box::new(first_failure::FirstFailureOutput::with_output(
+            writer,
+        ))),
+        "html" => Some(Box::new(html::HtmlOutput::with_output(writer))),
+        "json" => Some(Box::new(json::JsonOutput::with_output(writer))),
+        "junit" => Some(Box::new(junit::JUnitOutput::with_output(writer))),
+        "summary" => Some(Box::new(summary::SummaryOutput::with_output(writer))),
+        "toml" => Some(Box::new(toml::TomlOutput::with_output(writer))),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestAnswer;
+
+    /// Writes just the number of students
+    struct CountOutput {
+        writer: Box<dyn Write>,
+    }
+    impl OutputMode for CountOutput {
+        fn output_class_results(
+            &mut self,
+            results: &ClassResults,
+        ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+            writeln!(self.writer, "{} students", results.len())?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_register_output_mode() {
+        assert!(get_output_mode("count").is_none());
+        assert!(!is_registered_output_mode("count"));
+        register_output_mode("count", |writer| Box::new(CountOutput { writer }));
+        assert!(get_output_mode("count").is_some());
+        assert!(is_registered_output_mode("count"));
+        assert!(!is_registered_output_mode("csv"));
+        // The built-in modes are still there
+        assert!(get_output_mode("csv").is_some());
+        let mut results = ClassResults::new();
+        for student in ["alice", "bob"].iter() {
+            let mut student_results = HashMap::new();
+            student_results.insert(String::from("1"), Ok(TestAnswer::Success.into()));
+            results.insert(String::from(*student), student_results);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("count.txt");
+        get_output_mode_for_file("count", file.to_str().unwrap(), false)
+            .unwrap()
+            .unwrap()
+            .output_class_results(&results)
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "2 students\n");
+    }
+
+    #[test]
+    fn test_output_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("results.json");
+        std::fs::write(&file, "old results").unwrap();
+        // An unknown mode leaves the file alone
+        assert!(
+            get_output_mode_for_file("jsn", file.to_str().unwrap(), false)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old results");
+        // A file that can't be created is an error, not an unknown mode
+        let missing = dir.path().join("missing/results.json");
+        assert!(get_output_mode_for_file("json", missing.to_str().unwrap(), false).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::super::{ClassResults, TestAnswer};
+
+/// Computes the percentage of students who passed each case, sorted by
+/// case name.
+///
+/// Only students whose code ran on the case count, so skipped results,
+/// missing submissions, compile errors and errors don't count either
+/// way, and cases which nobody's code ran on are left out.
+pub fn case_pass_rates(results: &ClassResults) -> Vec<(String, f64)> {
+    // For each case, the number of students who passed and the number
+    // whose code ran on it
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (case_name, result) in results
+        .values()
+        .flat_map(|student_results| student_results.iter())
+    {
+        let passed = match result.as_deref() {
+            Ok(TestAnswer::Skipped)
+            | Ok(TestAnswer::NoSubmission(_))
+            | Ok(TestAnswer::CompileError(_))
+            | Err(_) => continue,
+            Ok(TestAnswer::Success) => 1,
+            _ => 0,
+        };
+        let count = counts.entry(case_name).or_insert((0, 0));
+        count.0 += passed;
+        count.1 += 1;
+    }
+    counts
+        .into_iter()
+        .sorted_by_key(|(case_name, _)| *case_name)
+        .map(|(case_name, (passed, total))| {
+            (
+                String::from(case_name),
+                100.0 * passed as f64 / total as f64,
+            )
+        })
+        .collect()
+}
+
+/// Returns a warning for each case whose pass rate (see
+/// `case_pass_rates`) is at most `low` percent, since its expected
+/// output may be wrong, or at least `high` percent, since it may be
+/// too easy to tell students apart. Either check is left out if its
+/// threshold isn't given.
+pub fn outlier_case_warnings(
+    results: &ClassResults,
+    low: Option<f64>,
+    high: Option<f64>,
+) -> Vec<String> {
+    case_pass_rates(results)
+        .into_iter()
+        .filter_map(|(case_name, rate)| {
+            if low.is_some_and(|low| rate <= low) {
+                Some(format!(
+                    "Case \"{}\": only {:.0}% of students passed (is the expected output right?)",
+                    case_name, rate
+                ))
+            } else if high.is_some_and(|high| rate >= high) {
+                Some(format!(
+                    "Case \"{}\": {:.0}% of students passed (is it too easy?)",
+                    case_name, rate
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outlier_cases() {
+        let mut data = HashMap::new();
+        for (student, second) in [("A", TestAnswer::Success), ("B", TestAnswer::Failure)].iter() {
+            let mut student_results = HashMap::new();
+            student_results.insert(String::from("Case 1"), Ok(TestAnswer::Failure.into()));
+            student_results.insert(String::from("Case 2"), Ok(second.clone().into()));
+            student_results.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
+            student_results.insert(String::from("Case 4"), Ok(TestAnswer::Skipped.into()));
+            data.insert(String::from(*student), student_results);
+        }
+        assert_eq!(
+            case_pass_rates(&data),
+            vec![
+                (String::from("Case 1"), 0.0),
+                (String::from("Case 2"), 50.0),
+                (String::from("Case 3"), 100.0),
+            ]
+        );
+        assert_eq!(
+            outlier_case_warnings(&data, Some(10.0), None),
+            vec![String::from(
+                "Case \"Case 1\": only 0% of students passed (is the expected output right?)"
+            )]
+        );
+        assert_eq!(
+            outlier_case_warnings(&data, Some(10.0), Some(95.0)).len(),
+            2
+        );
+        assert!(outlier_case_warnings(&data, None, None).is_empty());
+    }
+
+    #[test]
+    fn test_students_whose_code_didnt_run() {
+        let mut data = HashMap::new();
+        for (student, result) in [
+            ("A", Ok(TestAnswer::Success.into())),
+            ("B", Ok(TestAnswer::Failure.into())),
+            ("C", Ok(TestAnswer::NoSubmission(String::new()).into())),
+            ("D", Ok(TestAnswer::CompileError(String::new()).into())),
+            ("E", Err("Couldn't test student".into())),
+        ] {
+            let mut student_results = HashMap::new();
+            student_results.insert(String::from("Case 1"), result);
+            data.insert(String::from(student), student_results);
+        }
+        // Only A and B ran, so the non-submitters don't pull the case
+        // below the threshold
+        assert_eq!(case_pass_rates(&data), vec![(String::from("Case 1"), 50.0)]);
+        assert!(outlier_case_warnings(&data, Some(25.0), None).is_empty());
+    }
+}
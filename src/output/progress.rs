@@ -0,0 +1,54 @@
+use std::error::Error;
+
+use super::super::{CaseResult, TestAnswer};
+
+/// Returns a line describing the result of one case for one student,
+/// such as `smith/case_03: Failure`, for showing progress during a run
+/// (see `test_from_configuration_with_progress`).
+pub fn case_progress_line(
+    student_name: &str,
+    case_name: &str,
+    result: &Result<CaseResult, Box<dyn Error + 'static>>,
+) -> String {
+    let status = match result.as_deref() {
+        Ok(TestAnswer::FailWithMessage(message)) => format!("Failure ({})", message),
+        Ok(TestAnswer::NoSubmission(message)) => format!("NoSubmission ({})", message),
+        // The compiler's messages are usually too long for one line
+        Ok(TestAnswer::CompileError(_)) => String::from("CompileError"),
+        Ok(answer) => format!("{:?}", answer),
+        Err(e) => format!("Error ({})", e),
+    };
+    format!("{}/{}: {}", student_name, case_name, status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::{test_from_configuration_with_progress, TestConfig};
+    use super::*;
+
+    #[test]
+    fn test_progress_lines() {
+        let config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        let mut lines = Vec::new();
+        let results =
+            test_from_configuration_with_progress(&config, &mut |student, case, result| {
+                lines.push(case_progress_line(student, case, result))
+            })
+            .unwrap();
+        // There's a line for every result, given as each case finished
+        assert_eq!(
+            lines.len(),
+            results.values().map(|cases| cases.len()).sum::<usize>()
+        );
+        assert!(lines.contains(&String::from("correct/1: Success")));
+        assert!(lines.contains(&String::from(
+            "wrong/2: Failure (Line 1: expected \"8\", got \"-20\")"
+        )));
+        // Students are tested one at a time, in order
+        assert!(lines[..2].iter().all(|line| line.starts_with("correct/")));
+        assert_eq!(
+            case_progress_line("smith", "case_03", &Err("Missing file".into())),
+            "smith/case_03: Error (Missing file)"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use itertools::Itertools;
+use rusqlite::{params, Connection};
+
+use super::super::ClassResults;
+use super::json::result_status;
+use super::OutputMode;
+
+/// Writes results into a SQLite database, with one row per student and
+/// case.
+///
+/// The `results` table is created if it doesn't exist yet, and each run
+/// is appended to it with a new run id, so that results from many runs
+/// can be kept in the same database.
+pub struct SqliteOutput {
+    connection: Connection,
+}
+impl SqliteOutput {
+    /// Opens (or creates) the database in the given file
+    pub fn open(filename: &str) -> rusqlite::Result<Self> {
+        Ok(Self::with_connection(Connection::open(filename)?))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        SqliteOutput { connection }
+    }
+}
+
+impl OutputMode for SqliteOutput {
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "CREATE TABLE IF NOT EXISTS results (
+                run_id INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                student TEXT NOT NULL,
+                test_case TEXT NOT NULL,
+                status TEXT NOT NULL,
+                message TEXT
+            )",
+            [],
+        )?;
+        let run_id: i64 = transaction.query_row(
+            "SELECT COALESCE(MAX(run_id), 0) + 1 FROM results",
+            [],
+            |row| row.get(0),
+        )?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO results (run_id, timestamp, student, test_case, status, message)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (student_name, student_result) in results.iter().sorted_by_key(|a| a.0) {
+                for (case_name, result) in student_result.iter().sorted_by_key(|a| a.0) {
+                    let (status, message) = result_status(result);
+                    insert.execute(params![
+                        run_id,
+                        timestamp,
+                        student_name,
+                        case_name,
+                        status,
+                        message
+                    ])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::super::TestAnswer;
+    use super::*;
+
+    fn make_testing_data() -> ClassResults {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::FailWithMessage(String::from("Off by one")).into()),
+        );
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Timeout.into()));
+        student_b.insert(String::from("Case 2"), Err("Missing file".into()));
+        data.insert(String::from("Student B"), student_b);
+        data
+    }
+
+    #[test]
+    fn test_in_memory_database() {
+        let data = make_testing_data();
+        let mut output = SqliteOutput::with_connection(Connection::open_in_memory().unwrap());
+        output.output_class_results(&data).unwrap();
+        output.output_class_results(&data).unwrap();
+        let rows: Vec<(i64, String, String, String, Option<String>)> = output
+            .connection
+            .prepare(
+                "SELECT run_id, student, test_case, status, message FROM results
+                 ORDER BY run_id, student, test_case",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(
+            rows[1],
+            (
+                1,
+                String::from("Student A"),
+                String::from("Case 2"),
+                String::from("failure"),
+                Some(String::from("Off by one"))
+            )
+        );
+        assert_eq!(rows[2].3, "timeout");
+        assert_eq!(rows[3].3, "error");
+        assert_eq!(rows[3].4.as_deref(), Some("Missing file"));
+        // The second run is appended with its own id
+        assert_eq!(rows[4].0, 2);
+        assert_eq!(rows[4].3, "success");
+    }
+}
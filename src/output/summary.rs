@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use itertools::Itertools;
+
+use super::super::{ClassResults, TestAnswer};
+use super::OutputMode;
+
+/// The headings of the columns in the table of cases
+const HEADINGS: [&str; 7] = [
+    "Case",
+    "Passed",
+    "Failed",
+    "Timed out",
+    "Didn't compile",
+    "Not run",
+    "Skipped",
+];
+
+/// An OutputMode which writes statistics about the class as a whole,
+/// instead of each student's results: a table of how many students got
+/// each result on each case, followed by how many students passed each
+/// number of cases.
+pub struct SummaryOutput<T> {
+    writer: T,
+}
+impl<T> SummaryOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        SummaryOutput { writer }
+    }
+}
+
+/// How many students got each kind of result on a case
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CaseCounts {
+    passed: usize,
+    failed: usize,
+    timed_out: usize,
+    compile_error: usize,
+    /// Students with no submission, or who couldn't be tested
+    not_run: usize,
+    skipped: usize,
+}
+impl CaseCounts {
+    /// The counts in the order of the table's columns
+    fn columns(&self) -> [usize; 6] {
+        [
+            self.passed,
+            self.failed,
+            self.timed_out,
+            self.compile_error,
+            self.not_run,
+            self.skipped,
+        ]
+    }
+}
+
+/// Counts the results of each case, sorted by case name
+fn case_counts(results: &ClassResults) -> Vec<(&str, CaseCounts)> {
+    let mut counts: HashMap<&str, CaseCounts> = HashMap::new();
+    for (case_name, result) in results
+        .values()
+        .flat_map(|student_results| student_results.iter())
+    {
+        let count = counts.entry(case_name).or_default();
+        match result.as_deref() {
+            Ok(TestAnswer::Success) => count.passed += 1,
+            Ok(TestAnswer::Failure)
+            | Ok(TestAnswer::FailWithMessage(_))
+            | Ok(TestAnswer::Nondeterministic) => count.failed += 1,
+            Ok(TestAnswer::Timeout) => count.timed_out += 1,
+            Ok(TestAnswer::CompileError(_)) => count.compile_error += 1,
+            Ok(TestAnswer::NoSubmission(_)) | Err(_) => count.not_run += 1,
+            Ok(TestAnswer::Skipped) => count.skipped += 1,
+        }
+    }
+    counts
+        .into_iter()
+        .sorted_by_key(|(case_name, _)| *case_name)
+        .collect()
+}
+
+/// Maps each number of cases passed to how many students passed that
+/// many
+fn score_distribution(results: &ClassResults) -> BTreeMap<usize, usize> {
+    let mut distribution = BTreeMap::new();
+    for student_results in results.values() {
+        let passed = student_results
+            .values()
+            .filter(|result| matches!(result.as_deref(), Ok(TestAnswer::Success)))
+            .count();
+        *distribution.entry(passed).or_insert(0) += 1;
+    }
+    distribution
+}
+
+impl<T> OutputMode for SummaryOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let counts = case_counts(results);
+        let rows: Vec<Vec<String>> = counts
+            .iter()
+            .map(|(case_name, count)| {
+                std::iter::once(String::from(*case_name))
+                    .chain(count.columns().iter().map(usize::to_string))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = HEADINGS
+            .iter()
+            .enumerate()
+            .map(|(i, heading)| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .chain(std::iter::once(heading.len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let write_row = |writer: &mut T, row: &[&str]| {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .join("  ");
+            writeln!(writer, "{}", line.trim_end())
+        };
+        write_row(&mut self.writer, &HEADINGS)?;
+        for row in rows.iter() {
+            write_row(
+                &mut self.writer,
+                &row.iter().map(String::as_str).collect::<Vec<_>>(),
+            )?;
+        }
+        // Skipped cases don't count towards anyone's score
+        let total = counts
+            .iter()
+            .filter(|(_, count)| count.skipped < results.len())
+            .count();
+        writeln!(self.writer)?;
+        writeln!(self.writer, "Cases passed (out of {}):", total)?;
+        for (passed, students) in score_distribution(results).iter().rev() {
+            writeln!(
+                self.writer,
+                "{:>width$}: {} {}",
+                passed,
+                students,
+                match students {
+                    1 => "student",
+                    _ => "students",
+                },
+                width = total.to_string().len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_testing_data() -> ClassResults {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
+        data.insert(String::from("Student A"), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Failure.into()));
+        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout.into()));
+        data.insert(String::from("Student B"), student_b);
+        let mut student_c = HashMap::new();
+        student_c.insert(
+            String::from("Case 1"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 3"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        data.insert(String::from("Student C"), student_c);
+        data
+    }
+
+    #[test]
+    fn test_summary_counts() {
+        let data = make_testing_data();
+        let counts = case_counts(&data);
+        assert_eq!(
+            counts,
+            vec![
+                (
+                    "Case 1",
+                    CaseCounts {
+                        passed: 2,
+                        compile_error: 1,
+                        ..CaseCounts::default()
+                    }
+                ),
+                (
+                    "Case 2",
+                    CaseCounts {
+                        passed: 1,
+                        failed: 1,
+                        compile_error: 1,
+                        ..CaseCounts::default()
+                    }
+                ),
+                (
+                    "Case 3",
+                    CaseCounts {
+                        passed: 1,
+                        timed_out: 1,
+                        compile_error: 1,
+                        ..CaseCounts::default()
+                    }
+                ),
+            ]
+        );
+        let distribution: Vec<(usize, usize)> = score_distribution(&data).into_iter().collect();
+        assert_eq!(distribution, vec![(0, 1), (1, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn test_summary_output() {
+        let mut writer = SummaryOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&make_testing_data()).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        assert_eq!(
+            output,
+            "Case    Passed  Failed  Timed out  Didn't compile  Not run  Skipped
+Case 1  2       0       0          1               0        0
+Case 2  1       1       0          1               0        0
+Case 3  1       0       1          1               0        0
+
+Cases passed (out of 3):
+3: 1 student
+1: 1 student
+0: 1 student
+"
+        );
+    }
+}
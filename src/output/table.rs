@@ -2,8 +2,11 @@ use itertools::Itertools;
 use prettytable::{Cell, Row};
 
 use super::super::{ClassResults, TestAnswer};
-use super::OutputMode;
-/// An OutputMode which prints a table to some output stream
+use super::{case_names, weighted_score, OutputMode};
+/// An OutputMode which prints a table to some output stream, with how
+/// many cases each student passed, their score out of the possible
+/// points (where each case is worth its weight), and a symbol for their
+/// result on each case
 pub struct Table<T> {
     writer: T,
 }
@@ -13,30 +16,20 @@ impl<T> Table<T> {
         Table { writer }
     }
 }
-impl Table<std::io::Stdout> {
-    pub fn with_stdout() -> Self {
-        Table::with_output(std::io::stdout())
-    }
-}
 
 impl<T: std::io::Write> OutputMode for Table<T> {
     fn output_class_results(
         &mut self,
         results: &ClassResults,
     ) -> Result<(), Box<dyn std::error::Error + 'static>> {
-        let case_names: Vec<&String> = results
-            .iter()
-            .next()
-            .expect("There weren't any test cases")
-            .1
-            .keys()
-            .sorted()
-            .collect();
+        let case_names = case_names(results);
         let mut table = prettytable::Table::new();
         let mut case_row = Row::empty();
         case_row.add_cell(Cell::new(""));
         case_row.add_cell(Cell::new("Passed"));
         case_row.add_cell(Cell::new("Total"));
+        case_row.add_cell(Cell::new("Score"));
+        case_row.add_cell(Cell::new("Possible"));
         for case in case_names.iter() {
             case_row.add_cell(Cell::new(case));
         }
@@ -46,19 +39,12 @@ impl<T: std::io::Write> OutputMode for Table<T> {
                 case_names
                     .iter()
                     .map(|case| {
-                        Cell::new(
-                            match student_result
+                        Cell::new(result_symbol(
+                            student_result
                                 .get(case.as_str())
                                 .expect("Student missing case in their results")
-                            {
-                                Ok(TestAnswer::Success) => " ",
-                                Ok(TestAnswer::Failure) => "F",
-                                Ok(TestAnswer::FailWithMessage(_)) => "F",
-                                Ok(TestAnswer::Timeout) => "T",
-                                Ok(TestAnswer::CompileError) => "C",
-                                Err(_) => "!",
-                            },
-                        )
+                                .as_deref(),
+                        ))
                     })
                     .collect(),
             );
@@ -70,17 +56,28 @@ impl<T: std::io::Write> OutputMode for Table<T> {
                         "{}",
                         student_result
                             .values()
-                            .filter(|a| if let Ok(TestAnswer::Success) = a {
-                                true
-                            } else {
-                                false
-                            })
+                            .filter(|a| matches!(a.as_deref(), Ok(TestAnswer::Success)))
+                            .count()
+                    )
+                    .as_str(),
+                ),
+            );
+            row.insert_cell(
+                2,
+                Cell::new(
+                    format!(
+                        "{}",
+                        student_result
+                            .values()
+                            .filter(|a| !matches!(a.as_deref(), Ok(TestAnswer::Skipped)))
                             .count()
                     )
                     .as_str(),
                 ),
             );
-            row.insert_cell(2, Cell::new(format!("{}", case_names.len()).as_str()));
+            let (score, possible) = weighted_score(student_result);
+            row.insert_cell(3, Cell::new(&score.to_string()));
+            row.insert_cell(4, Cell::new(&possible.to_string()));
             table.add_row(row);
         }
         table.print(&mut self.writer)?;
@@ -88,6 +85,21 @@ impl<T: std::io::Write> OutputMode for Table<T> {
     }
 }
 
+/// The symbol shown in a table's cell for a result (blank for a pass)
+pub(super) fn result_symbol<E>(result: Result<&TestAnswer, E>) -> &'static str {
+    match result {
+        Ok(TestAnswer::Success) => " ",
+        Ok(TestAnswer::Failure) => "F",
+        Ok(TestAnswer::FailWithMessage(_)) => "F",
+        Ok(TestAnswer::Timeout) => "T",
+        Ok(TestAnswer::CompileError(_)) => "C",
+        Ok(TestAnswer::Nondeterministic) => "N",
+        Ok(TestAnswer::Skipped) => "s",
+        Ok(TestAnswer::NoSubmission(_)) => "-",
+        Err(_) => "!",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -97,19 +109,28 @@ mod tests {
     fn make_testing_data() -> ClassResults {
         let mut data = HashMap::new();
         let mut student_a = HashMap::new();
-        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success));
-        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Success));
-        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success));
+        student_a.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 2"), Ok(TestAnswer::Success.into()));
+        student_a.insert(String::from("Case 3"), Ok(TestAnswer::Success.into()));
         data.insert(String::from("Student A"), student_a);
         let mut student_b = HashMap::new();
-        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success));
-        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Failure));
-        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout));
+        student_b.insert(String::from("Case 1"), Ok(TestAnswer::Success.into()));
+        student_b.insert(String::from("Case 2"), Ok(TestAnswer::Failure.into()));
+        student_b.insert(String::from("Case 3"), Ok(TestAnswer::Timeout.into()));
         data.insert(String::from("Student B"), student_b);
         let mut student_c = HashMap::new();
-        student_c.insert(String::from("Case 1"), Ok(TestAnswer::CompileError));
-        student_c.insert(String::from("Case 2"), Ok(TestAnswer::CompileError));
-        student_c.insert(String::from("Case 3"), Ok(TestAnswer::CompileError));
+        student_c.insert(
+            String::from("Case 1"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 2"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
+        student_c.insert(
+            String::from("Case 3"),
+            Ok(TestAnswer::CompileError(String::new()).into()),
+        );
         data.insert(String::from("Student C"), student_c);
         data
     }
@@ -120,6 +141,32 @@ mod tests {
         let mut writer = Table::with_output(Vec::<u8>::new());
         writer.output_class_results(&data).unwrap();
         let output = std::str::from_utf8(&writer.writer).unwrap();
-        assert_eq!(output, "+-----------+--------+-------+--------+--------+--------+\n|           | Passed | Total | Case 1 | Case 2 | Case 3 |\n+-----------+--------+-------+--------+--------+--------+\n| Student A | 3      | 3     |        |        |        |\n+-----------+--------+-------+--------+--------+--------+\n| Student B | 1      | 3     |        | F      | T      |\n+-----------+--------+-------+--------+--------+--------+\n| Student C | 0      | 3     | C      | C      | C      |\n+-----------+--------+-------+--------+--------+--------+\n");
+        assert_eq!(output, "+-----------+--------+-------+-------+----------+--------+--------+--------+\n|           | Passed | Total | Score | Possible | Case 1 | Case 2 | Case 3 |\n+-----------+--------+-------+-------+----------+--------+--------+--------+\n| Student A | 3      | 3     | 3     | 3        |        |        |        |\n+-----------+--------+-------+-------+----------+--------+--------+--------+\n| Student B | 1      | 3     | 1     | 3        |        | F      | T      |\n+-----------+--------+-------+-------+----------+--------+--------+--------+\n| Student C | 0      | 3     | 0     | 3        | C      | C      | C      |\n+-----------+--------+-------+-------+----------+--------+--------+--------+\n");
+    }
+
+    #[test]
+    fn test_skipped_cases() {
+        let mut data = make_testing_data();
+        data.get_mut("Student B")
+            .unwrap()
+            .insert(String::from("Case 2"), Ok(TestAnswer::Skipped.into()));
+        let mut writer = Table::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert!(output.contains("| Student A | 3      | 3     |"));
+        assert!(output.contains(
+            "| Student B | 1      | 2     | 1     | 2        |        | s      | T      |"
+        ));
+    }
+
+    #[test]
+    fn test_no_students() {
+        let mut writer = Table::with_output(Vec::<u8>::new());
+        writer.output_class_results(&HashMap::new()).unwrap();
+        let output = std::str::from_utf8(&writer.writer).unwrap();
+        assert_eq!(
+            output,
+            "+--+--------+-------+-------+----------+\n|  | Passed | Total | Score | Possible |\n+--+--------+-------+-------+----------+\n"
+        );
     }
 }
@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+
+/// A writer which sends everything written to it to two other writers.
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+impl<A, B> Tee<A, B> {
+    /// Makes a writer which writes to both `first` and `second`
+    pub fn new(first: A, second: B) -> Self {
+        Tee { first, second }
+    }
+
+    /// Returns the two writers this writes to
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_both_receive_output() {
+        let student = "Student A";
+        let mut tee = Tee::new(Vec::<u8>::new(), Vec::<u8>::new());
+        write!(tee, "Name,Passed,Total\n{},{},{}\n", student, 3, 3).unwrap();
+        tee.flush().unwrap();
+        let (first, second) = tee.into_inner();
+        assert_eq!(first, b"Name,Passed,Total\nStudent A,3,3\n");
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::Write;
+
+use toml::value::Table;
+use toml::Value;
+
+use super::super::{ClassResults, TestAnswer};
+use super::json::result_status;
+use super::{weighted_score, OutputMode};
+
+/// An OutputMode which writes the results as TOML, like the config
+/// files, for tools which already read it.
+///
+/// There's a `[students.<name>]` table for each student, with how many
+/// cases they passed ("passed") out of how many weren't skipped
+/// ("total"), their score out of the possible points ("score" and
+/// "possible", where each case is worth its weight), and a
+/// `[students.<name>.cases]` table mapping each case to its status (see
+/// `result_status` for the statuses). Names which can't be bare keys
+/// are quoted.
+pub struct TomlOutput<T> {
+    writer: T,
+}
+impl<T> TomlOutput<T> {
+    pub fn with_output(writer: T) -> Self {
+        TomlOutput { writer }
+    }
+}
+
+/// Converts the results into TOML, in the format written by
+/// `TomlOutput`
+fn results_to_toml(results: &ClassResults) -> Value {
+    let students: Table = results
+        .iter()
+        .map(|(student_name, student_results)| {
+            let cases: Table = student_results
+                .iter()
+                .map(|(case_name, result)| {
+                    (
+                        case_name.clone(),
+                        Value::String(String::from(result_status(result).0)),
+                    )
+                })
+                .collect();
+            let passed = student_results
+                .values()
+                .filter(|result| matches!(result.as_deref(), Ok(TestAnswer::Success)))
+                .count();
+            let total = student_results
+                .values()
+                .filter(|result| !matches!(result.as_deref(), Ok(TestAnswer::Skipped)))
+                .count();
+            let (score, possible) = weighted_score(student_results);
+            let mut student = Table::new();
+            student.insert(String::from("passed"), Value::Integer(passed as i64));
+            student.insert(String::from("total"), Value::Integer(total as i64));
+            student.insert(String::from("score"), Value::Float(score));
+            student.insert(String::from("possible"), Value::Float(possible));
+            student.insert(String::from("cases"), Value::Table(cases));
+            (student_name.clone(), Value::Table(student))
+        })
+        .collect();
+    let mut root = Table::new();
+    root.insert(String::from("students"), Value::Table(students));
+    Value::Table(root)
+}
+
+impl<T> OutputMode for TomlOutput<T>
+where
+    T: Write,
+{
+    fn output_class_results(
+        &mut self,
+        results: &ClassResults,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        write!(
+            self.writer,
+            "{}",
+            toml::to_string(&results_to_toml(results))?
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut data = HashMap::new();
+        let mut student_a = HashMap::new();
+        student_a.insert(String::from("1"), Ok(TestAnswer::Success.into()));
+        student_a.insert(
+            String::from("edge case"),
+            Ok(TestAnswer::FailWithMessage(String::from("Off by one")).into()),
+        );
+        student_a.insert(String::from("skip.me"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("Student \"A\""), student_a);
+        let mut student_b = HashMap::new();
+        student_b.insert(String::from("1"), Ok(TestAnswer::Timeout.into()));
+        student_b.insert(String::from("edge case"), Err("Missing file".into()));
+        student_b.insert(String::from("skip.me"), Ok(TestAnswer::Skipped.into()));
+        data.insert(String::from("b"), student_b);
+        let mut writer = TomlOutput::with_output(Vec::<u8>::new());
+        writer.output_class_results(&data).unwrap();
+        let output = String::from_utf8(writer.writer).unwrap();
+        assert!(output.contains("[students.b]\n"));
+        assert!(output.contains("[students.\"Student \\\"A\\\"\".cases]\n"));
+        let read: Value = output.parse().unwrap();
+        let student_a = &read["students"]["Student \"A\""];
+        assert_eq!(student_a["passed"].as_integer(), Some(1));
+        assert_eq!(student_a["total"].as_integer(), Some(2));
+        assert_eq!(student_a["score"].as_float(), Some(1.0));
+        assert_eq!(student_a["possible"].as_float(), Some(2.0));
+        assert_eq!(student_a["cases"]["1"].as_str(), Some("success"));
+        assert_eq!(student_a["cases"]["edge case"].as_str(), Some("failure"));
+        assert_eq!(student_a["cases"]["skip.me"].as_str(), Some("skipped"));
+        let student_b = &read["students"]["b"];
+        assert_eq!(student_b["passed"].as_integer(), Some(0));
+        assert_eq!(student_b["cases"]["1"].as_str(), Some("timeout"));
+        assert_eq!(student_b["cases"]["edge case"].as_str(), Some("error"));
+    }
+}
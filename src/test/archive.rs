@@ -0,0 +1,116 @@
+//! Extraction of test data which is given as an archive, instead of as a
+//! directory
+
+use std::error::Error;
+use std::path::Path;
+
+use errormake::errormake;
+
+/// The extensions of gzipped tarballs which can be used as a tests
+/// directory
+const TAR_GZ_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz"];
+
+/// The extension of zip files which can be used as a tests directory
+const ZIP_EXTENSION: &str = ".zip";
+
+/// Whether the tests directory at the given path is actually an archive
+/// (going by its extension), which has to be extracted before the cases
+/// in it can be found. A directory is never an archive, whatever it's
+/// called.
+pub fn is_archive(path: &str) -> bool {
+    (TAR_GZ_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) || path.ends_with(ZIP_EXTENSION))
+        && !Path::new(path).is_dir()
+}
+
+/// Extracts the archive at the given path to a temporary directory, and
+/// calls `f` with the path to the tests in it. The directory is deleted
+/// once `f` returns, so it has to read everything it needs by then.
+///
+/// If everything in the archive is in a single top-level directory (as
+/// with `tar czf tests.tar.gz tests`), that directory is what's passed
+/// to `f`.
+#[cfg(feature = "archive-tests")]
+pub fn with_extracted<T>(
+    path: &str,
+    f: impl FnOnce(&str) -> Result<T, Box<dyn Error + 'static>>,
+) -> Result<T, Box<dyn Error + 'static>> {
+    use std::fs::{self, File};
+
+    let extract_error = |e: &dyn std::fmt::Display| {
+        ArchiveError::with_description(format!("Couldn't extract {}: {}", path, e))
+    };
+    let file = File::open(path).map_err(|e| extract_error(&e))?;
+    let dir = tempfile::tempdir().map_err(|e| extract_error(&e))?;
+    match path.ends_with(ZIP_EXTENSION) {
+        true => zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(dir.path()))
+            .map_err(|e| extract_error(&e))?,
+        false => tar::Archive::new(flate2::read::GzDecoder::new(file))
+            .unpack(dir.path())
+            .map_err(|e| extract_error(&e))?,
+    }
+    let entries = fs::read_dir(dir.path())
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| extract_error(&e))?;
+    let root = match entries.as_slice() {
+        [entry] if entry.path().is_dir() => entry.path(),
+        _ => dir.path().to_path_buf(),
+    };
+    let root = root
+        .to_str()
+        .ok_or_else(|| extract_error(&"the temporary directory's path isn't valid UTF-8"))?;
+    f(root)
+}
+
+/// Without the "archive-tests" feature, archives can't be extracted.
+#[cfg(not(feature = "archive-tests"))]
+pub fn with_extracted<T>(
+    path: &str,
+    _f: impl FnOnce(&str) -> Result<T, Box<dyn Error + 'static>>,
+) -> Result<T, Box<dyn Error + 'static>> {
+    Err(ArchiveError::with_description(format!(
+        "Cannot extract tests from {}: stipulate was built without the \"archive-tests\" feature",
+        path
+    ))
+    .into())
+}
+
+errormake!(#[doc="An error extracting test data from an archive"] pub ArchiveError);
+
+#[cfg(all(test, feature = "archive-tests"))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tests.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for (name, contents) in [("1.in", "3\n4\n"), ("1.out", "7\n")].iter() {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        let path = path.to_str().unwrap();
+        assert!(is_archive(path));
+        let mut extracted_to = String::new();
+        let output = with_extracted(path, |tests| {
+            extracted_to = String::from(tests);
+            Ok(std::fs::read_to_string(Path::new(tests).join("1.out"))?)
+        })
+        .unwrap();
+        assert_eq!(output, "7\n");
+        // It's cleaned up afterwards
+        assert!(!Path::new(&extracted_to).exists());
+        // A directory isn't an archive, even with an archive's name
+        std::fs::create_dir(dir.path().join("dir.zip")).unwrap();
+        assert!(!is_archive(dir.path().join("dir.zip").to_str().unwrap()));
+        assert!(
+            with_extracted(dir.path().join("missing.tgz").to_str().unwrap(), |_| Ok(())).is_err()
+        );
+    }
+}
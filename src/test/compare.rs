@@ -0,0 +1,1313 @@
+//! Ways of deciding whether a student's output is correct
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::TestAnswer;
+
+/// Decides whether the output of a student's code is correct for a test
+/// case.
+///
+/// Implement this to grade output with rules that a plain comparison
+/// can't express. It is also implemented for any closure with the same
+/// signature as `compare`.
+pub trait Comparator: Send + Sync {
+    /// Judges the `actual` output of a student's code against the
+    /// `expected` output for a test case, where `input` is what was
+    /// given to the student's code on standard input.
+    fn compare(&self, input: &str, expected: &str, actual: &str) -> TestAnswer;
+}
+
+impl<F> Comparator for F
+where
+    F: Fn(&str, &str, &str) -> TestAnswer + Send + Sync,
+{
+    fn compare(&self, input: &str, expected: &str, actual: &str) -> TestAnswer {
+        self(input, expected, actual)
+    }
+}
+
+/// A `Comparator` which passes only if the output is exactly the same
+/// as the expected output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExactComparator;
+impl Comparator for ExactComparator {
+    fn compare(&self, _input: &str, expected: &str, actual: &str) -> TestAnswer {
+        match actual == expected {
+            true => TestAnswer::Success,
+            false => TestAnswer::Failure,
+        }
+    }
+}
+
+/// The built-in ways of comparing output, which can be chosen in the
+/// config file.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Comparison {
+    /// The output must be exactly the same as the expected output
+    #[default]
+    Exact,
+    /// The output must be exactly the same as the expected output,
+    /// except that a single newline at the end of either is ignored
+    /// (so "X" and "X\n" are the same)
+    ExactIgnoreFinalNewline,
+    /// The output must be exactly the same as the expected output,
+    /// except for any whitespace at the very end (so "X" and "X \n\n"
+    /// are the same)
+    TrimEnd,
+    /// The output must have the same lines as the expected output,
+    /// except for whitespace at the end of each line, and blank lines at
+    /// the end of the output (so "X \nY" and "X\nY\n\n" are the same)
+    TrimLines,
+    /// The output must have the same lines as the expected output, in
+    /// any order
+    SortedLines,
+    /// Like `SortedLines`, but lines which are numbers are put in
+    /// numeric order (so "2" comes before "10"), ahead of any lines
+    /// which aren't numbers
+    SortedNumeric,
+    /// Both outputs are read as CSV, and must have the same cells. The
+    /// first row is a header, which must match in place, and if
+    /// `sort_rows` is set, then the rest of the rows may be in any
+    /// order. Failures report the first cell which is different.
+    Csv { sort_rows: bool },
+    /// Each line is a record, made of fields split by `field_sep` (with
+    /// no quoting), and the output must have the same records as the
+    /// expected output. If `sort` is set, the records may be in any
+    /// order. Failures report the first record which isn't matched.
+    RecordSet { field_sep: char, sort: bool },
+    /// The output must have each line the same number of times as the
+    /// expected output, in any order. Failures report the first line
+    /// (in sorted order) whose count is different.
+    LineCounts,
+}
+impl Comparison {
+    /// Returns the comparison with the given name in the config file,
+    /// if there is one
+    pub fn from_name(name: &str) -> Option<Comparison> {
+        match name {
+            "exact" => Some(Comparison::Exact),
+            "ignore_final_newline" => Some(Comparison::ExactIgnoreFinalNewline),
+            "trim" => Some(Comparison::TrimEnd),
+            "trim_lines" => Some(Comparison::TrimLines),
+            "sorted_lines" => Some(Comparison::SortedLines),
+            "sorted_numeric" => Some(Comparison::SortedNumeric),
+            "csv" => Some(Comparison::Csv { sort_rows: false }),
+            "csv_sorted_rows" => Some(Comparison::Csv { sort_rows: true }),
+            "records" => Some(Comparison::RecordSet {
+                field_sep: '\t',
+                sort: false,
+            }),
+            "sorted_records" => Some(Comparison::RecordSet {
+                field_sep: '\t',
+                sort: true,
+            }),
+            "line_counts" => Some(Comparison::LineCounts),
+            _ => None,
+        }
+    }
+
+    /// Finds the first line where `actual` is different from
+    /// `expected`, for the comparisons which keep lines in order (so
+    /// there is a first line which is wrong). Returns `None` for the
+    /// other comparisons, or if no line is different.
+    fn first_difference<'a>(&self, expected: &'a str, actual: &'a str) -> Option<Difference> {
+        let (expected_lines, actual_lines): (Vec<&str>, Vec<&str>) = match self {
+            Comparison::Exact => {
+                let without_newline = |text: &'a str| text.strip_suffix('\n').unwrap_or(text);
+                if without_newline(expected) == without_newline(actual) {
+                    return Some(Difference::FinalNewline {
+                        expected: expected.ends_with('\n'),
+                    });
+                }
+                (
+                    without_newline(expected).split('\n').collect(),
+                    without_newline(actual).split('\n').collect(),
+                )
+            }
+            Comparison::ExactIgnoreFinalNewline => (
+                strip_final_newline(expected).split('\n').collect(),
+                strip_final_newline(actual).split('\n').collect(),
+            ),
+            Comparison::TrimEnd => (
+                expected.trim_end().split('\n').collect(),
+                actual.trim_end().split('\n').collect(),
+            ),
+            Comparison::TrimLines => (trimmed_lines(expected), trimmed_lines(actual)),
+            _ => return None,
+        };
+        (0..expected_lines.len().max(actual_lines.len()))
+            .find(|&index| expected_lines.get(index) != actual_lines.get(index))
+            .map(|index| Difference::Line {
+                index,
+                expected_ended: index >= expected_lines.len(),
+                actual_ended: index >= actual_lines.len(),
+            })
+    }
+}
+
+/// Where output first differs from the expected output
+enum Difference {
+    /// Only the newline at the end is different, and `expected` says
+    /// whether there should be one
+    FinalNewline { expected: bool },
+    /// The line at `index` (from 0) is different, where either output
+    /// may have ended before it
+    Line {
+        index: usize,
+        expected_ended: bool,
+        actual_ended: bool,
+    },
+}
+impl Difference {
+    /// Describes the difference for a failure message, quoting the lines
+    /// from the given outputs. Normalizing output keeps its lines in
+    /// place, so these can be the outputs from before normalizing, which
+    /// are what the student and instructor wrote.
+    fn describe(&self, expected: &str, actual: &str) -> String {
+        match self {
+            Difference::FinalNewline { expected: true } => {
+                String::from("Expected a newline at the end of the output")
+            }
+            Difference::FinalNewline { expected: false } => {
+                String::from("Expected no newline at the end of the output")
+            }
+            Difference::Line {
+                index,
+                expected_ended,
+                actual_ended,
+            } => {
+                let line = |text: &str, ended: bool| match text.split('\n').nth(*index) {
+                    Some(line) if !ended => format!("{:?}", line),
+                    _ => String::from("the end of the output"),
+                };
+                format!(
+                    "Line {}: expected {}, got {}",
+                    index + 1,
+                    line(expected, *expected_ended),
+                    line(actual, *actual_ended)
+                )
+            }
+        }
+    }
+}
+impl Comparator for Comparison {
+    fn compare(&self, input: &str, expected: &str, actual: &str) -> TestAnswer {
+        let matches = match self {
+            Comparison::Exact => return ExactComparator.compare(input, expected, actual),
+            Comparison::ExactIgnoreFinalNewline => {
+                strip_final_newline(expected) == strip_final_newline(actual)
+            }
+            Comparison::TrimEnd => expected.trim_end() == actual.trim_end(),
+            Comparison::TrimLines => trimmed_lines(expected) == trimmed_lines(actual),
+            Comparison::SortedLines => {
+                sorted_lines(expected, |a, b| a.cmp(b)) == sorted_lines(actual, |a, b| a.cmp(b))
+            }
+            Comparison::SortedNumeric => {
+                sorted_lines(expected, numeric_order) == sorted_lines(actual, numeric_order)
+            }
+            Comparison::Csv { sort_rows } => return compare_csv(expected, actual, *sort_rows),
+            Comparison::RecordSet { field_sep, sort } => {
+                return compare_records(expected, actual, *field_sep, *sort)
+            }
+            Comparison::LineCounts => return compare_line_counts(expected, actual),
+        };
+        match matches {
+            true => TestAnswer::Success,
+            false => TestAnswer::Failure,
+        }
+    }
+}
+
+/// A `Comparison`, along with normalizations which are applied to both
+/// the expected and actual output before they are compared.
+///
+/// When a comparison which keeps lines in order (such as "exact") fails,
+/// the result says which line was the first one to be different, with
+/// what was expected there and what the student's code printed.
+///
+/// The normalizations can be combined with any comparison, and with
+/// each other. They're applied before the comparison, so a comparison
+/// which trims whitespace (such as `Comparison::TrimLines`) sees the
+/// collapsed, lowercased text: with both, "A  B \n" and "a b\n" are
+/// the same.
+///
+/// If a float tolerance (absolute or relative) is given, the output is
+/// instead compared token by token, allowing numbers to be off by up to
+/// the tolerance.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct ComparisonOptions {
+    comparison: Comparison,
+    collapse_spaces: bool,
+    ignore_case: bool,
+    float_tolerance: Option<f64>,
+    relative_tolerance: Option<f64>,
+}
+impl ComparisonOptions {
+    /// Makes options which use the given comparison, with no
+    /// normalizations
+    pub fn new(comparison: Comparison) -> Self {
+        ComparisonOptions {
+            comparison,
+            collapse_spaces: false,
+            ignore_case: false,
+            float_tolerance: None,
+            relative_tolerance: None,
+        }
+    }
+
+    /// Sets whether runs of spaces and tabs are replaced with a single
+    /// space before comparing. Unlike ignoring whitespace entirely, this
+    /// keeps the line structure of the output.
+    pub fn with_collapse_spaces(mut self, collapse_spaces: bool) -> Self {
+        self.collapse_spaces = collapse_spaces;
+        self
+    }
+
+    /// Sets whether both outputs are made lowercase before comparing, so
+    /// that "YES" and "yes" are the same.
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Sets the tolerance for comparing numbers. If it's given, then the
+    /// outputs are split into whitespace-separated tokens, which must
+    /// match one-for-one: numbers may differ by up to the tolerance, and
+    /// everything else must be the same. This takes the place of the
+    /// comparison.
+    pub fn with_float_tolerance(mut self, float_tolerance: Option<f64>) -> Self {
+        self.float_tolerance = float_tolerance;
+        self
+    }
+
+    /// Sets the relative tolerance for comparing numbers, as a fraction
+    /// of the expected number (so 0.01 allows numbers to be off by 1%).
+    /// Like `with_float_tolerance`, this takes the place of the
+    /// comparison, and if both are given, a number passes if it's within
+    /// either of them.
+    pub fn with_relative_tolerance(mut self, relative_tolerance: Option<f64>) -> Self {
+        self.relative_tolerance = relative_tolerance;
+        self
+    }
+
+    /// The comparison used after normalizing
+    pub fn comparison(&self) -> Comparison {
+        self.comparison
+    }
+
+    /// Whether runs of spaces and tabs are collapsed to one space
+    pub fn collapse_spaces(&self) -> bool {
+        self.collapse_spaces
+    }
+
+    /// Whether capitalization is ignored
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// The tolerance for comparing numbers, if numbers are compared
+    /// with a tolerance
+    pub fn float_tolerance(&self) -> Option<f64> {
+        self.float_tolerance
+    }
+
+    /// The tolerance for comparing numbers relative to the expected
+    /// number, if numbers are compared with one
+    pub fn relative_tolerance(&self) -> Option<f64> {
+        self.relative_tolerance
+    }
+
+    /// Applies the normalizations in these options to the given text
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        lazy_static! {
+            static ref SPACE_RUN: Regex = Regex::new(r"[ \t]+").unwrap();
+        }
+        let text = match self.collapse_spaces {
+            true => SPACE_RUN.replace_all(text, " "),
+            false => Cow::Borrowed(text),
+        };
+        match self.ignore_case {
+            true => Cow::Owned(text.to_lowercase()),
+            false => text,
+        }
+    }
+}
+impl Comparator for ComparisonOptions {
+    fn compare(&self, input: &str, expected: &str, actual: &str) -> TestAnswer {
+        let normalized_expected = self.normalize(expected);
+        let normalized_actual = self.normalize(actual);
+        if self.float_tolerance.is_some() || self.relative_tolerance.is_some() {
+            return compare_with_tolerance(
+                &normalized_expected,
+                &normalized_actual,
+                self.float_tolerance.unwrap_or(0.0),
+                self.relative_tolerance.unwrap_or(0.0),
+            );
+        }
+        match self
+            .comparison
+            .compare(input, &normalized_expected, &normalized_actual)
+        {
+            TestAnswer::Failure => match self
+                .comparison
+                .first_difference(&normalized_expected, &normalized_actual)
+            {
+                Some(difference) => {
+                    TestAnswer::FailWithMessage(difference.describe(expected, actual))
+                }
+                None => TestAnswer::Failure,
+            },
+            answer => answer,
+        }
+    }
+}
+
+/// Compares the whitespace-separated tokens of the outputs, allowing
+/// each number to differ by up to `absolute`, or by up to `relative`
+/// times the expected number, whichever is larger.
+///
+/// On failure, the message says which token was wrong. If the only
+/// problem is numbers being out of tolerance, it reports the number
+/// which was off by the most, along with how far off it was.
+fn compare_with_tolerance(
+    expected: &str,
+    actual: &str,
+    absolute: f64,
+    relative: f64,
+) -> TestAnswer {
+    let expected: Vec<&str> = expected.split_whitespace().collect();
+    let actual: Vec<&str> = actual.split_whitespace().collect();
+    if expected.len() != actual.len() {
+        return TestAnswer::FailWithMessage(format!(
+            "Expected {} tokens of output, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    // The index, difference, and tolerance of the number which was off
+    // by the most
+    let mut worst: Option<(usize, f64, f64)> = None;
+    for (index, (expected_token, actual_token)) in expected.iter().zip(&actual).enumerate() {
+        match (expected_token.parse::<f64>(), actual_token.parse::<f64>()) {
+            (Ok(expected_value), Ok(actual_value)) => {
+                let delta = match (expected_value - actual_value).abs() {
+                    // Differences involving NaN are never in tolerance
+                    delta if delta.is_nan() => f64::INFINITY,
+                    delta => delta,
+                };
+                let tolerance = absolute.max(relative * expected_value.abs());
+                if delta > tolerance && worst.is_none_or(|(_, worst, _)| delta > worst) {
+                    worst = Some((index, delta, tolerance));
+                }
+            }
+            _ if expected_token == actual_token => {}
+            _ => {
+                return TestAnswer::FailWithMessage(format!(
+                    "Token {}: expected {:?}, got {:?}",
+                    index + 1,
+                    expected_token,
+                    actual_token
+                ))
+            }
+        }
+    }
+    match worst {
+        Some((index, delta, tolerance)) => TestAnswer::FailWithMessage(format!(
+            "Token {}: expected {}, got {} (off by {}, tolerance {})",
+            index + 1,
+            expected[index],
+            actual[index],
+            delta,
+            tolerance
+        )),
+        None => TestAnswer::Success,
+    }
+}
+
+/// A `Comparator` for programs which check their own answers: the case
+/// passes if the last line the program prints is the given check line,
+/// whatever the expected output is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfCheckComparator {
+    check_line: String,
+}
+impl SelfCheckComparator {
+    /// Makes a comparator which passes output whose last line is
+    /// `check_line` (ignoring whitespace at the end of the line)
+    pub fn new(check_line: String) -> Self {
+        SelfCheckComparator { check_line }
+    }
+}
+impl Comparator for SelfCheckComparator {
+    fn compare(&self, _input: &str, _expected: &str, actual: &str) -> TestAnswer {
+        match actual.lines().last().map(str::trim_end) {
+            Some(line) if line == self.check_line => TestAnswer::Success,
+            Some(line) => TestAnswer::FailWithMessage(format!(
+                "The program's self-check printed {:?} instead of {:?}",
+                line, self.check_line
+            )),
+            None => TestAnswer::FailWithMessage(String::from(
+                "The program didn't print a self-check line",
+            )),
+        }
+    }
+}
+
+/// Expected output which passes any output that isn't empty, for cases
+/// which only require the program to print something. (Cases which
+/// require the program to print nothing can just expect empty output.)
+pub const NONEMPTY_SENTINEL: &str = "#nonempty";
+
+/// The first line of expected output which is made of a regex for each
+/// line of the output.
+pub const REGEX_LINES_SENTINEL: &str = "#regex-lines";
+
+/// The first line of expected output whose other lines are a regex
+/// which the whole output must match.
+pub const REGEX_SENTINEL: &str = "#regex";
+
+/// Judges the `actual` output of a student's code against the
+/// `expected` output, after applying the masks to both.
+///
+/// If the expected output is `NONEMPTY_SENTINEL` (optionally followed by
+/// a newline), then any nonempty output passes and the comparator isn't
+/// consulted.
+///
+/// If the first line of the expected output is `REGEX_LINES_SENTINEL`,
+/// then each of the remaining lines is a regex which must match all of
+/// the corresponding line of the output (after masking), and the output
+/// must have the same number of lines. A failure says which line didn't
+/// match.
+///
+/// If the first line of the expected output is `REGEX_SENTINEL`, then
+/// the rest of it is a regex which must match all of the output (after
+/// masking), with a final newline left off of both. The regex may span
+/// several lines, and `.` only matches newlines if the regex turns on
+/// the `s` flag (with `(?s)`). A failure gives the pattern which didn't
+/// match.
+pub fn judge_output(
+    comparator: &dyn Comparator,
+    masks: &[Mask],
+    input: &str,
+    expected: &str,
+    actual: &str,
+) -> TestAnswer {
+    if strip_final_newline(expected) == NONEMPTY_SENTINEL {
+        return match actual.is_empty() {
+            true => TestAnswer::Failure,
+            false => TestAnswer::Success,
+        };
+    }
+    if let Some(patterns) = expected
+        .strip_prefix(REGEX_LINES_SENTINEL)
+        .and_then(|rest| rest.strip_prefix('\n'))
+    {
+        return match_regex_lines(patterns, &apply_masks(masks, actual));
+    }
+    if let Some(pattern) = expected
+        .strip_prefix(REGEX_SENTINEL)
+        .and_then(|rest| rest.strip_prefix('\n'))
+    {
+        return match_regex(
+            strip_final_newline(pattern),
+            strip_final_newline(&apply_masks(masks, actual)),
+        );
+    }
+    comparator.compare(
+        input,
+        &apply_masks(masks, expected),
+        &apply_masks(masks, actual),
+    )
+}
+
+/// Checks that the whole of `actual` matches the regex `pattern`
+fn match_regex(pattern: &str, actual: &str) -> TestAnswer {
+    match Regex::new(&format!("^(?:{})$", pattern)) {
+        Ok(regex) if regex.is_match(actual) => TestAnswer::Success,
+        Ok(_) => TestAnswer::FailWithMessage(format!("The output doesn't match /{}/", pattern)),
+        Err(e) => TestAnswer::FailWithMessage(format!("Invalid regex: {}", e)),
+    }
+}
+
+/// Checks that each line of `actual` matches the whole of the regex on
+/// the same line of `patterns`.
+fn match_regex_lines(patterns: &str, actual: &str) -> TestAnswer {
+    let patterns: Vec<&str> = patterns.lines().collect();
+    let lines: Vec<&str> = actual.lines().collect();
+    if patterns.len() != lines.len() {
+        return TestAnswer::FailWithMessage(format!(
+            "Expected {} lines of output, got {}",
+            patterns.len(),
+            lines.len()
+        ));
+    }
+    for (number, (pattern, line)) in patterns.iter().zip(lines).enumerate() {
+        let matches = match Regex::new(&format!("^(?:{})$", pattern)) {
+            Ok(regex) => regex.is_match(line),
+            Err(e) => {
+                return TestAnswer::FailWithMessage(format!(
+                    "Invalid regex for line {}: {}",
+                    number + 1,
+                    e
+                ))
+            }
+        };
+        if !matches {
+            return TestAnswer::FailWithMessage(format!(
+                "Line {} ({:?}) doesn't match /{}/",
+                number + 1,
+                line,
+                pattern
+            ));
+        }
+    }
+    TestAnswer::Success
+}
+
+/// A replacement made to both the expected and actual output before they
+/// are compared, to hide details which legitimately differ between runs
+/// (such as absolute paths).
+#[derive(Debug, Clone)]
+pub struct Mask {
+    pattern: Regex,
+    replacement: String,
+}
+impl Mask {
+    /// Makes a mask which replaces all matches of `pattern` with
+    /// `replacement` (which may refer to capture groups, as in
+    /// `Regex::replace_all`).
+    pub fn new(pattern: Regex, replacement: String) -> Self {
+        Mask {
+            pattern,
+            replacement,
+        }
+    }
+
+    /// Applies this mask to the given text
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.pattern.replace_all(text, self.replacement.as_str())
+    }
+}
+
+/// Applies each of the masks, in order, to the given text
+pub fn apply_masks<'a>(masks: &[Mask], text: &'a str) -> Cow<'a, str> {
+    masks
+        .iter()
+        .fold(Cow::Borrowed(text), |text, mask| match mask.apply(&text) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(masked) => Cow::Owned(masked),
+        })
+}
+
+/// Reads `text` as CSV, returning each row (including the header) as a
+/// list of cells. Rows don't all need to have the same length.
+fn read_csv(text: &str) -> Result<Vec<Vec<String>>, csv::Error> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .map(|record| Ok(record?.iter().map(String::from).collect()))
+        .collect()
+}
+
+/// Compares the expected and actual output as CSV (see
+/// `Comparison::Csv`), with a message giving the first cell which is
+/// different. Rows are numbered from 1, counting the header, and in
+/// sorted order if `sort_rows` is set.
+fn compare_csv(expected: &str, actual: &str, sort_rows: bool) -> TestAnswer {
+    let mut expected = match read_csv(expected) {
+        Ok(rows) => rows,
+        Err(e) => return TestAnswer::FailWithMessage(format!("Expected output isn't CSV: {}", e)),
+    };
+    let mut actual = match read_csv(actual) {
+        Ok(rows) => rows,
+        Err(e) => return TestAnswer::FailWithMessage(format!("Output isn't valid CSV: {}", e)),
+    };
+    if sort_rows {
+        for rows in [&mut expected, &mut actual] {
+            if let Some(data) = rows.get_mut(1..) {
+                data.sort();
+            }
+        }
+    }
+    for (row, (expected_row, actual_row)) in expected.iter().zip(&actual).enumerate() {
+        if let Some((column, (expected_cell, actual_cell))) = expected_row
+            .iter()
+            .zip(actual_row)
+            .enumerate()
+            .find(|(_, (e, a))| e != a)
+        {
+            return TestAnswer::FailWithMessage(format!(
+                "Row {}, column {}: expected {:?}, got {:?}",
+                row + 1,
+                column + 1,
+                expected_cell,
+                actual_cell
+            ));
+        }
+        if expected_row.len() != actual_row.len() {
+            return TestAnswer::FailWithMessage(format!(
+                "Row {}: expected {} cells, got {}",
+                row + 1,
+                expected_row.len(),
+                actual_row.len()
+            ));
+        }
+    }
+    match expected.len() == actual.len() {
+        true => TestAnswer::Success,
+        false => TestAnswer::FailWithMessage(format!(
+            "Expected {} rows, got {}",
+            expected.len(),
+            actual.len()
+        )),
+    }
+}
+
+/// Compares the lines of the expected and actual output as records of
+/// fields split by `field_sep` (see `Comparison::RecordSet`), with a
+/// message giving the first record which isn't matched.
+fn compare_records(expected: &str, actual: &str, field_sep: char, sort: bool) -> TestAnswer {
+    let records = |text: &'_ str| -> Vec<Vec<String>> {
+        text.lines()
+            .map(|line| line.split(field_sep).map(String::from).collect())
+            .collect()
+    };
+    let expected = records(expected);
+    let mut actual = records(actual);
+    if !sort {
+        if let Some((index, (expected_record, actual_record))) = expected
+            .iter()
+            .zip(&actual)
+            .enumerate()
+            .find(|(_, (e, a))| e != a)
+        {
+            return TestAnswer::FailWithMessage(format!(
+                "Record {}: expected {:?}, got {:?}",
+                index + 1,
+                expected_record,
+                actual_record
+            ));
+        }
+    }
+    // Match up the records in any order, removing each one matched
+    for expected_record in &expected {
+        match actual.iter().position(|record| record == expected_record) {
+            Some(index) => {
+                actual.remove(index);
+            }
+            None => {
+                return TestAnswer::FailWithMessage(format!(
+                    "Missing record: {:?}",
+                    expected_record
+                ))
+            }
+        }
+    }
+    match actual.first() {
+        Some(record) => TestAnswer::FailWithMessage(format!("Unexpected record: {:?}", record)),
+        None => TestAnswer::Success,
+    }
+}
+
+/// Compares the number of times each line appears in the expected and
+/// actual output (see `Comparison::LineCounts`)
+fn compare_line_counts(expected: &str, actual: &str) -> TestAnswer {
+    let line_counts = |text: &'_ str| -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for line in text.lines() {
+            *counts.entry(String::from(line)).or_insert(0) += 1;
+        }
+        counts
+    };
+    let expected = line_counts(expected);
+    let actual = line_counts(actual);
+    let mismatch = expected
+        .keys()
+        .chain(actual.keys())
+        .sorted()
+        .map(|line| {
+            (
+                line,
+                expected.get(line).copied().unwrap_or(0),
+                actual.get(line).copied().unwrap_or(0),
+            )
+        })
+        .find(|(_, expected_count, actual_count)| expected_count != actual_count);
+    match mismatch {
+        Some((line, expected_count, actual_count)) => TestAnswer::FailWithMessage(format!(
+            "Line {:?}: expected {} time{}, got {}",
+            line,
+            expected_count,
+            if expected_count == 1 { "" } else { "s" },
+            actual_count
+        )),
+        None => TestAnswer::Success,
+    }
+}
+
+/// Removes one newline (either "\n" or "\r\n") from the end of `text`,
+/// if there is one
+fn strip_final_newline(text: &str) -> &str {
+    text.strip_suffix('\n')
+        .map(|text| text.strip_suffix('\r').unwrap_or(text))
+        .unwrap_or(text)
+}
+
+/// Returns the lines of `text` without the whitespace at the end of
+/// each, leaving out any blank lines at the end
+fn trimmed_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+/// Returns the lines of `text`, sorted with the given ordering
+fn sorted_lines<F>(text: &str, order: F) -> Vec<&str>
+where
+    F: FnMut(&&str, &&str) -> Ordering,
+{
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines.sort_by(order);
+    lines
+}
+
+/// Orders lines which parse as numbers by their value, before all lines
+/// which don't, which are ordered lexically.
+fn numeric_order(a: &&str, b: &&str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal).then(a.cmp(b)),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compares JSON-ish output where quoted values (strings following
+    /// a ':') ignore case, but everything else, including quoted keys,
+    /// must match exactly.
+    fn values_case_insensitive(_input: &str, expected: &str, actual: &str) -> TestAnswer {
+        let expected: Vec<_> = expected.split('"').collect();
+        let actual: Vec<_> = actual.split('"').collect();
+        let matches = expected.len() == actual.len()
+            && (0..expected.len()).all(|i| {
+                let is_value = i % 2 == 1 && expected[i - 1].trim_end().ends_with(':');
+                match is_value {
+                    true => expected[i].to_lowercase() == actual[i].to_lowercase(),
+                    false => expected[i] == actual[i],
+                }
+            });
+        match matches {
+            true => TestAnswer::Success,
+            false => TestAnswer::Failure,
+        }
+    }
+
+    #[test]
+    fn test_exact() {
+        assert_eq!(
+            ExactComparator.compare("", "a\n", "a\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(ExactComparator.compare("", "a\n", "a"), TestAnswer::Failure);
+    }
+
+    #[test]
+    fn test_context_sensitive() {
+        let comparator: &dyn Comparator = &values_case_insensitive;
+        let expected = "{\"name\": \"Alice\"}\n";
+        assert_eq!(
+            comparator.compare("", expected, "{\"name\": \"ALICE\"}\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            comparator.compare("", expected, "{\"NAME\": \"Alice\"}\n"),
+            TestAnswer::Failure
+        );
+        // The input is available to decide on the rules to use
+        let echoes_input = |input: &str, _: &str, actual: &str| match input == actual {
+            true => TestAnswer::Success,
+            false => TestAnswer::Failure,
+        };
+        assert_eq!(
+            echoes_input.compare("hi\n", "", "hi\n"),
+            TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_sorted_numeric() {
+        let comparison = Comparison::SortedNumeric;
+        assert_eq!(
+            comparison.compare("", "2\n10\n", "2\n10\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            comparison.compare("", "2\n10\n", "10\n2\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            comparison.compare("", "2\n10\n", "2\n11\n"),
+            TestAnswer::Failure
+        );
+        assert_eq!(
+            comparison.compare("", "total\n-1\n3.5\n", "3.5\ntotal\n-1\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            sorted_lines("10\nb\n2\na\n", numeric_order),
+            vec!["2", "10", "a", "b"]
+        );
+        assert_eq!(sorted_lines("10\n2\n", |a, b| a.cmp(b)), vec!["10", "2"]);
+        assert_eq!(
+            Comparison::SortedLines.compare("", "a\nb\n", "b\na\n"),
+            TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let expected = "name,score\nalice,90\nbob,85\n";
+        let reordered = "name,score\nbob,85\nalice,90\n";
+        let sorted = Comparison::Csv { sort_rows: true };
+        assert_eq!(sorted.compare("", expected, reordered), TestAnswer::Success);
+        // Quoting doesn't matter, only the cells
+        assert_eq!(
+            sorted.compare("", expected, "\"name\",score\nbob,\"85\"\nalice,90"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            Comparison::Csv { sort_rows: false }.compare("", expected, reordered),
+            TestAnswer::FailWithMessage(String::from(
+                "Row 2, column 1: expected \"alice\", got \"bob\""
+            ))
+        );
+        // The header isn't sorted with the rest of the rows
+        assert_eq!(
+            sorted.compare("", expected, "alice,90\nname,score\nbob,85\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Row 1, column 1: expected \"name\", got \"alice\""
+            ))
+        );
+        assert_eq!(
+            sorted.compare("", expected, "name,score\nalice,90\nbob,86\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Row 3, column 2: expected \"85\", got \"86\""
+            ))
+        );
+        assert_eq!(
+            sorted.compare("", expected, "name,score\nalice,90\n"),
+            TestAnswer::FailWithMessage(String::from("Expected 3 rows, got 2"))
+        );
+        assert_eq!(
+            sorted.compare("", expected, "name,score,grade\nalice,90\nbob,85\n"),
+            TestAnswer::FailWithMessage(String::from("Row 1: expected 2 cells, got 3"))
+        );
+    }
+
+    #[test]
+    fn test_record_set() {
+        let expected = "alice\t90\nbob\t85\ncarol\t85\n";
+        let sorted = Comparison::RecordSet {
+            field_sep: '\t',
+            sort: true,
+        };
+        assert_eq!(
+            sorted.compare("", expected, "carol\t85\nalice\t90\nbob\t85\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            sorted.compare("", expected, "carol\t85\nalice\t90\nbob\t58\n"),
+            TestAnswer::FailWithMessage(String::from("Missing record: [\"bob\", \"85\"]"))
+        );
+        assert_eq!(
+            sorted.compare("", expected, "carol\t85\nalice\t90\nbob\t85\nbob\t85\n"),
+            TestAnswer::FailWithMessage(String::from("Unexpected record: [\"bob\", \"85\"]"))
+        );
+        // Spaces are part of the fields, since only tabs separate them
+        assert_ne!(
+            sorted.compare("", expected, "carol\t85\nalice\t 90\nbob\t85\n"),
+            TestAnswer::Success
+        );
+        let in_order = Comparison::RecordSet {
+            field_sep: ',',
+            sort: false,
+        };
+        assert_eq!(
+            in_order.compare("", "a,1\nb,2\n", "b,2\na,1\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Record 1: expected [\"a\", \"1\"], got [\"b\", \"2\"]"
+            ))
+        );
+        assert_eq!(
+            in_order.compare("", "a,1\nb,2\n", "a,1\nb,2"),
+            TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_line_counts() {
+        let expected = "apple\nbanana\napple\ncherry\napple\n";
+        let counts = Comparison::from_name("line_counts").unwrap();
+        assert_eq!(
+            counts.compare("", expected, "cherry\napple\napple\nbanana\napple\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            counts.compare("", expected, "banana\napple\ncherry\napple\n"),
+            TestAnswer::FailWithMessage(String::from("Line \"apple\": expected 3 times, got 2"))
+        );
+        assert_eq!(
+            counts.compare(
+                "",
+                expected,
+                "apple\nbanana\nbanana\ncherry\napple\napple\n"
+            ),
+            TestAnswer::FailWithMessage(String::from("Line \"banana\": expected 1 time, got 2"))
+        );
+        assert_eq!(
+            counts.compare("", expected, "apple\nbanana\napple\ncherry\napple\ndate\n"),
+            TestAnswer::FailWithMessage(String::from("Line \"date\": expected 0 times, got 1"))
+        );
+    }
+
+    #[test]
+    fn test_masks() {
+        let masks = vec![
+            Mask::new(Regex::new(r"/tmp/\S+").unwrap(), String::from("<DIR>")),
+            Mask::new(
+                Regex::new(r"took (\d+)ms").unwrap(),
+                String::from("took ?ms"),
+            ),
+        ];
+        let expected = apply_masks(&masks, "Wrote /tmp/abc/out.txt\n");
+        let actual = apply_masks(&masks, "Wrote /tmp/stipulate-1234/out.txt\n");
+        assert_eq!(expected, "Wrote <DIR>\n");
+        assert_eq!(expected, actual);
+        assert_eq!(apply_masks(&masks, "took 15ms"), "took ?ms");
+        assert!(matches!(apply_masks(&masks, "unmasked"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_collapse_spaces() {
+        let options = ComparisonOptions::new(Comparison::Exact).with_collapse_spaces(true);
+        assert_eq!(
+            options.compare("", "name  age\nbob   7\n", "name\tage\nbob 7\n"),
+            TestAnswer::Success
+        );
+        // Line structure still matters
+        assert_eq!(
+            options.compare("", "name age\nbob 7\n", "name age bob 7\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"name age\", got \"name age bob 7\""
+            ))
+        );
+        assert_eq!(
+            options.compare("", "a b\n", "ab\n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"a b\", got \"ab\""))
+        );
+        assert_eq!(
+            ComparisonOptions::default().compare("", "a  b\n", "a b\n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"a  b\", got \"a b\""))
+        );
+        // Normalizations combine with any comparison
+        let options = ComparisonOptions::new(Comparison::SortedLines).with_collapse_spaces(true);
+        assert_eq!(
+            options.compare("", "x  1\ny 22\n", "y    22\nx 1\n"),
+            TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_first_difference() {
+        let exact = ComparisonOptions::default();
+        assert_eq!(
+            exact.compare("", "1\n2\n3\n", "1\n2\n4\n"),
+            TestAnswer::FailWithMessage(String::from("Line 3: expected \"3\", got \"4\""))
+        );
+        assert_eq!(
+            exact.compare("", "1\n2\n3\n", "1\n2\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Line 3: expected \"3\", got the end of the output"
+            ))
+        );
+        assert_eq!(
+            exact.compare("", "1\n", "1\r\n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"1\", got \"1\\r\""))
+        );
+        let trim_lines = ComparisonOptions::new(Comparison::TrimLines);
+        assert_eq!(
+            trim_lines.compare("", "1 \n2\n\n", "1\n2\n3\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Line 3: expected the end of the output, got \"3\""
+            ))
+        );
+        // Comparisons which don't keep lines in order can't say which
+        // line is wrong
+        assert_eq!(
+            ComparisonOptions::new(Comparison::SortedLines).compare("", "1\n2\n", "1\n3\n"),
+            TestAnswer::Failure
+        );
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let options = ComparisonOptions::new(Comparison::Exact).with_ignore_case(true);
+        assert_eq!(options.compare("", "YES\n", "yes\n"), TestAnswer::Success);
+        // Failures quote the output as it was printed
+        assert_eq!(
+            options.compare("", "Yes\n", "yes \n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"Yes\", got \"yes \""))
+        );
+        assert_eq!(
+            ComparisonOptions::default().compare("", "YES\n", "yes\n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"YES\", got \"yes\""))
+        );
+        // With collapsed spaces, the spacing inside lines doesn't matter
+        let collapse = ComparisonOptions::new(Comparison::Exact).with_collapse_spaces(true);
+        assert_eq!(
+            collapse.compare("", "A B\n", "A   B\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            collapse.compare("", "A B\n", "a   b\n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"A B\", got \"a   b\""))
+        );
+        let both = collapse.with_ignore_case(true);
+        assert_eq!(both.compare("", "A B\n", "a   b\n"), TestAnswer::Success);
+        assert_eq!(
+            both.compare("", "A B\n", "a   b \n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"A B\", got \"a   b \""))
+        );
+        // Trimming happens after both
+        let trimmed = ComparisonOptions::new(Comparison::TrimLines)
+            .with_collapse_spaces(true)
+            .with_ignore_case(true);
+        assert_eq!(
+            trimmed.compare("", "A B\n", "a   b \n\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            ComparisonOptions::new(Comparison::TrimLines)
+                .with_ignore_case(true)
+                .compare("", "A B\n", "a   b \n"),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"A B\", got \"a   b \""))
+        );
+    }
+
+    #[test]
+    fn test_float_tolerance() {
+        let options = ComparisonOptions::default().with_float_tolerance(Some(1e-3));
+        assert_eq!(
+            options.compare("", "pi = 3.14159\n", "pi =  3.141590\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            options.compare("", "pi = 3.14159\n", "pi = 3.1416\n"),
+            TestAnswer::Success
+        );
+        // The number which was off by the most is reported
+        assert_eq!(
+            options.compare("", "1.0 2.0 3.0\n", "1.01 2.5 3.1\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Token 2: expected 2.0, got 2.5 (off by 0.5, tolerance 0.001)"
+            ))
+        );
+        assert_eq!(
+            options.compare("", "x = 1\n", "y = 1\n"),
+            TestAnswer::FailWithMessage(String::from("Token 1: expected \"x\", got \"y\""))
+        );
+        assert_eq!(
+            options.compare("", "1 2\n", "1\n"),
+            TestAnswer::FailWithMessage(String::from("Expected 2 tokens of output, got 1"))
+        );
+        assert!(matches!(
+            options.compare("", "1\n", "NaN\n"),
+            TestAnswer::FailWithMessage(_)
+        ));
+    }
+
+    #[test]
+    fn test_relative_tolerance() {
+        let options = ComparisonOptions::default().with_relative_tolerance(Some(0.01));
+        assert_eq!(
+            options.compare("", "1000 0.5\n", "1009 0.504\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            options.compare("", "1000 2\n", "1000 2.5\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Token 2: expected 2, got 2.5 (off by 0.5, tolerance 0.02)"
+            ))
+        );
+        // An expected 0 needs an absolute tolerance to allow anything
+        assert!(matches!(
+            options.compare("", "0\n", "0.001\n"),
+            TestAnswer::FailWithMessage(_)
+        ));
+        let both = options.with_float_tolerance(Some(0.01));
+        assert_eq!(
+            both.compare("", "0 1000\n", "0.001 1009\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            both.compare("", "x 1\n", "X 1\n"),
+            TestAnswer::FailWithMessage(String::from("Token 1: expected \"x\", got \"X\""))
+        );
+    }
+
+    #[test]
+    fn test_self_check() {
+        let comparator = SelfCheckComparator::new(String::from("OK"));
+        assert_eq!(
+            comparator.compare("", "42\n", "answer: 41\nOK\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(comparator.compare("", "", "OK"), TestAnswer::Success);
+        assert_eq!(
+            comparator.compare("", "42\n", "answer: 42\nFAIL\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "The program's self-check printed \"FAIL\" instead of \"OK\""
+            ))
+        );
+        // Only the last line counts
+        assert!(matches!(
+            comparator.compare("", "", "OK\nanswer: 42\n"),
+            TestAnswer::FailWithMessage(_)
+        ));
+        assert!(matches!(
+            comparator.compare("", "", ""),
+            TestAnswer::FailWithMessage(_)
+        ));
+    }
+
+    #[test]
+    fn test_empty_and_nonempty_expectations() {
+        let judge = |expected, actual| judge_output(&ExactComparator, &[], "", expected, actual);
+        assert_eq!(judge("", ""), TestAnswer::Success);
+        assert_eq!(judge("", "output\n"), TestAnswer::Failure);
+        assert_eq!(judge("#nonempty", "output\n"), TestAnswer::Success);
+        assert_eq!(judge("#nonempty\n", "\n"), TestAnswer::Success);
+        assert_eq!(judge("#nonempty\n", ""), TestAnswer::Failure);
+        // The sentinel only counts if it's the whole expected output
+        assert_eq!(judge("#nonempty\nmore\n", "output\n"), TestAnswer::Failure);
+    }
+
+    #[test]
+    fn test_regex_lines() {
+        let judge = |expected, actual| judge_output(&ExactComparator, &[], "", expected, actual);
+        let expected = "#regex-lines\nTotal: \\d+\nTook [0-9.]+s\n";
+        assert_eq!(
+            judge(expected, "Total: 42\nTook 0.5s\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            judge(expected, "Total: 42\nTook a while\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Line 2 (\"Took a while\") doesn't match /Took [0-9.]+s/"
+            ))
+        );
+        // Each pattern has to match the whole line
+        assert_eq!(
+            judge(expected, "Total: 42 or so\nTook 0.5s\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "Line 1 (\"Total: 42 or so\") doesn't match /Total: \\d+/"
+            ))
+        );
+        assert_eq!(
+            judge(expected, "Total: 42\n"),
+            TestAnswer::FailWithMessage(String::from("Expected 2 lines of output, got 1"))
+        );
+        // Without the sentinel, the patterns are just expected output
+        assert_eq!(judge("Total: \\d+\n", "Total: 42\n"), TestAnswer::Failure);
+    }
+
+    #[test]
+    fn test_regex() {
+        let judge = |expected, actual| judge_output(&ExactComparator, &[], "", expected, actual);
+        let expected = "#regex\nStarted at \\d\\d:\\d\\d\n(.*\n)*Done\n";
+        assert_eq!(
+            judge(expected, "Started at 09:30\nworking\n0x7ffe\nDone\n"),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            judge(expected, "Started at 09:30\nDone\n"),
+            TestAnswer::Success
+        );
+        // The pattern has to match all of the output
+        assert_eq!(
+            judge(expected, "Started at 09:30\nDone\nExtra\n"),
+            TestAnswer::FailWithMessage(String::from(
+                "The output doesn't match /Started at \\d\\d:\\d\\d\n(.*\n)*Done/"
+            ))
+        );
+        assert!(matches!(
+            judge("#regex\n(unclosed\n", "(unclosed\n"),
+            TestAnswer::FailWithMessage(message) if message.starts_with("Invalid regex")
+        ));
+        // The regex-lines sentinel isn't mistaken for this one
+        assert_eq!(judge("#regex-lines\n\\d+\n", "42\n"), TestAnswer::Success);
+    }
+
+    #[test]
+    fn test_ignore_final_newline() {
+        let comparison = Comparison::ExactIgnoreFinalNewline;
+        assert_eq!(comparison.compare("", "X\n", "X\n"), TestAnswer::Success);
+        assert_eq!(comparison.compare("", "X\n", "X"), TestAnswer::Success);
+        assert_eq!(comparison.compare("", "X", "X\n"), TestAnswer::Success);
+        assert_eq!(comparison.compare("", "X", "X\r\n"), TestAnswer::Success);
+        assert_eq!(comparison.compare("", "X", "X\n\n"), TestAnswer::Failure);
+        assert_eq!(comparison.compare("", "X\nY", "XY"), TestAnswer::Failure);
+        assert_eq!(comparison.compare("", "X ", "X\n"), TestAnswer::Failure);
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let cases = [
+            ("1 2\n3\n", "1 2\n3"),
+            ("1 2\n3\n", "1 2\n3 \n\n"),
+            ("1 2\n3\n", "1 2  \n3\n"),
+            ("1 2\n3\n", "1 2\t\r\n3\r\n"),
+            ("1 2\n3\n", " 1 2\n3\n"),
+            ("1 2\n3\n", "1  2\n3\n"),
+            ("1 2\n3\n", "1 2\n\n3\n"),
+        ];
+        let expectations = [
+            (
+                "exact",
+                [true, false, false, false, false, false, false, false],
+            ),
+            (
+                "trim",
+                [true, true, true, false, false, false, false, false],
+            ),
+            (
+                "trim_lines",
+                [true, true, true, true, true, false, false, false],
+            ),
+        ];
+        for (name, passes) in expectations.iter() {
+            let comparison = Comparison::from_name(name).unwrap();
+            let results: Vec<bool> = std::iter::once(("1 2\n3\n", "1 2\n3\n"))
+                .chain(cases.iter().copied())
+                .map(|(expected, actual)| {
+                    comparison.compare("", expected, actual) == TestAnswer::Success
+                })
+                .collect();
+            assert_eq!(&results[..], &passes[..], "{}", name);
+        }
+    }
+}
@@ -1,182 +1,3152 @@
 //! Functions, enumerations, etc. pertaining to the evaluation of student programs
 
+mod archive;
+mod compare;
 mod process;
+mod remote;
 
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::{self, File};
-use std::io::Read;
-use std::time::Duration;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use errormake::errormake;
+use glob::Pattern;
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use log::{debug, info, warn};
 use regex::Regex;
 
-use super::conf::{TestConfig, TestType};
-use process::test_output_against_strings;
-pub use process::TestAnswer;
+use super::conf::{RunOptions, TestConfig, TestType};
+pub use compare::{
+    Comparator, Comparison, ComparisonOptions, ExactComparator, Mask, SelfCheckComparator,
+    NONEMPTY_SENTINEL, REGEX_LINES_SENTINEL, REGEX_SENTINEL,
+};
+use process::{
+    check_command_exists, check_setup_program_exists, test_output_against_strings,
+    CommandNotFoundError,
+};
+pub use process::{CaseResult, TestAnswer};
+use remote::RemoteCache;
+
+/// The number of points a case is worth if no weight is given for it
+pub(crate) const DEFAULT_POINTS: f64 = 1.0;
+
+/// The name of the file in the target directory which lists folders
+/// that aren't students (see "ignore_folders" in `RunOptions::from_toml`)
+const IGNORE_FILE: &str = ".stipulateignore";
+
+/// The text of a test case's input or expected output.
+///
+/// Text which is just the contents of a file is read from the file each
+/// time it's needed, so that a whole suite of large cases doesn't have
+/// to be kept in memory. Text which had to be computed (such as
+/// filtered input or downloaded output) is kept in memory instead, and
+/// shared with each student.
+#[derive(Debug, Clone)]
+enum CaseText {
+    /// The contents of a file
+    File(PathBuf),
+    /// The contents of several files, concatenated in order
+    Files(Vec<PathBuf>),
+    /// Text which is already in memory
+    Loaded(Arc<str>),
+}
+impl CaseText {
+    /// Returns the text, reading it from its files if needed
+    fn load(&self) -> Result<Arc<str>, Box<dyn Error + 'static>> {
+        match self {
+            CaseText::File(path) => Ok(fs::read_to_string(path)?.into()),
+            CaseText::Files(paths) => Ok(paths
+                .iter()
+                .map(fs::read_to_string)
+                .collect::<Result<String, _>>()?
+                .into()),
+            CaseText::Loaded(text) => Ok(Arc::clone(text)),
+        }
+    }
+}
 
 /// A struct representing a single test case for a directory test. It
-/// contains an input and an output.
+/// contains an input and an output, the expected standard error (if it
+/// has one), the arguments passed to the program for just this case,
+/// the number of points the case is worth, whether the case is skipped,
+/// whether its details are hidden from students, and a description of
+/// the case for students, if it has one.
 pub struct TestCase {
-    input: String,
-    output: String,
+    input: CaseText,
+    output: CaseText,
+    error_output: Option<CaseText>,
+    args: Vec<String>,
+    points: f64,
+    skipped: bool,
+    hidden: bool,
+    description: Option<String>,
 }
 impl TestCase {
-    /// Returns the input string
-    fn get_input(&self) -> &String {
-        &self.input
+    /// Makes a case with the given input and expected output, which is
+    /// worth the default number of points, and has no arguments of its
+    /// own, expected standard error, or description
+    pub fn new(input: String, output: String) -> Self {
+        TestCase {
+            input: CaseText::Loaded(input.into()),
+            output: CaseText::Loaded(output.into()),
+            error_output: None,
+            args: Vec::new(),
+            points: DEFAULT_POINTS,
+            skipped: false,
+            hidden: false,
+            description: None,
+        }
+    }
+
+    /// Sets the expected standard error for this case (which is only
+    /// compared if the options compare both streams)
+    pub fn with_error_output(mut self, error_output: String) -> Self {
+        self.error_output = Some(CaseText::Loaded(error_output.into()));
+        self
+    }
+
+    /// Sets the arguments passed to the program for this case, after the
+    /// ones from the config
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets the number of points this case is worth
+    pub fn with_points(mut self, points: f64) -> Self {
+        self.points = points;
+        self
+    }
+
+    /// Sets whether this case is skipped instead of being run
+    pub fn with_skipped(mut self, skipped: bool) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    /// Sets whether this case's details are kept from students
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Sets the description of this case for students
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Reads the input and output into memory, for cases whose files
+    /// are about to be deleted
+    fn into_loaded(self) -> Result<Self, Box<dyn Error + 'static>> {
+        Ok(TestCase {
+            input: CaseText::Loaded(self.input.load()?),
+            output: CaseText::Loaded(self.output.load()?),
+            error_output: match self.error_output {
+                Some(error_output) => Some(CaseText::Loaded(error_output.load()?)),
+                None => None,
+            },
+            ..self
+        })
+    }
+
+    /// Returns the input string, which may be read from disk (so it
+    /// can fail if the file has since become unreadable)
+    pub fn get_input(&self) -> Result<Arc<str>, Box<dyn Error + 'static>> {
+        self.input.load()
     }
 
-    /// Returns the output string
-    fn get_output(&self) -> &String {
-        &self.output
+    /// Returns the output string, which may be read from disk (so it
+    /// can fail if the file has since become unreadable)
+    pub fn get_output(&self) -> Result<Arc<str>, Box<dyn Error + 'static>> {
+        self.output.load()
+    }
+
+    /// Returns the expected standard error string, which is empty if the
+    /// case doesn't have one (and may be read from disk, so it can fail
+    /// like `get_output`)
+    pub fn get_error_output(&self) -> Result<Arc<str>, Box<dyn Error + 'static>> {
+        match &self.error_output {
+            Some(error_output) => error_output.load(),
+            None => Ok("".into()),
+        }
+    }
+
+    /// Returns the arguments which are passed to the program for this
+    /// case, after the ones from the config
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns the number of points this case is worth
+    pub fn get_points(&self) -> f64 {
+        self.points
+    }
+
+    /// Returns whether this case is skipped instead of being run
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+
+    /// Returns whether this case's details (its input, and what was
+    /// wrong with the output) are kept from students. It still counts
+    /// towards their score.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns the description of this case, if it has one
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
     }
 }
 /// A HashMap mapping test case names to the result of running on that test case
-pub type StudentResults = HashMap<String, Result<TestAnswer, Box<dyn Error + 'static>>>;
+pub type StudentResults = HashMap<String, Result<CaseResult, Box<dyn Error + 'static>>>;
 /// A HashMap mapping student names to their results
 pub type ClassResults = HashMap<String, StudentResults>;
+/// A function which is called with the student name, case name, and
+/// result as each case is finished
+pub type CaseCallback<'a> =
+    dyn FnMut(&str, &str, &Result<CaseResult, Box<dyn Error + 'static>>) + 'a;
 
 /// Tests the given command (cmd and args) against the given cases
 /// (input/ouput pairs), with a specified per-case timeout.
 ///
 /// It returns a vector containing the results of testing on each of the
-/// cases, in the order given.
+/// cases, in the order given. The run options control whether output
+/// is captured and how long messages may be.
+///
+/// This method assumes that the necessary setup has been done already.
 ///
-/// This method assumes that the necessary setup has been done already
+/// Cases which are skipped aren't run, and are given a result of
+/// `TestAnswer::Skipped`. A case's own arguments (if it has any) are
+/// passed after `args`.
+///
+/// The description of each case (if it has one) and the number of
+/// points it's worth are attached to its result, unless the result is
+/// an error.
+///
+/// If the command can't be found on the PATH, it stops at that point and
+/// returns the error, since every other case would fail the same way.
+///
+/// The command is run in `working_dir`, if it's given.
+///
+/// A case which times out is run again, up to the number of times the
+/// options say to retry it (see `retry_timeouts`).
+///
+/// The cases are run in order of their names. A summary of each case's
+/// result (with how long it took) is logged at the info level, under
+/// the given student name, and each result is passed to `on_case` when
+/// it's finished.
+#[allow(clippy::too_many_arguments)]
 fn test_student_against_test_case(
+    student_name: &str,
     cmd: String,
     args: Vec<String>,
     env_vars: &HashMap<String, String>,
+    working_dir: Option<&Path>,
     cases: &HashMap<String, TestCase>,
     timeout: Option<Duration>,
-) -> StudentResults {
+    options: &RunOptions,
+    on_case: &mut CaseCallback,
+) -> Result<StudentResults, Box<dyn Error + 'static>> {
     cases
         .iter()
+        .sorted_by_key(|(case_name, _)| *case_name)
         .map(|(case_name, case_data)| {
-            (
-                case_name.clone(),
-                test_output_against_strings(
-                    &cmd,
-                    &args,
-                    &env_vars,
-                    case_data.get_input(),
-                    case_data.get_output(),
-                    timeout,
+            let describe = |result: CaseResult| {
+                let result = result
+                    .with_points(case_data.get_points())
+                    .with_hidden(case_data.is_hidden());
+                match case_data.get_description() {
+                    Some(description) => result.with_description(String::from(description)),
+                    None => result,
+                }
+            };
+            if case_data.is_skipped() {
+                info!("{}/{}: skipped", student_name, case_name);
+                let result = Ok(describe(TestAnswer::Skipped.into()));
+                on_case(student_name, case_name, &result);
+                return Ok((case_name.clone(), result));
+            }
+            let case_args;
+            let args = match case_data.get_args() {
+                [] => &args,
+                extra => {
+                    case_args = args.iter().chain(extra).cloned().collect::<Vec<_>>();
+                    &case_args
+                }
+            };
+            let start = Instant::now();
+            let (result, input) = match (
+                case_data.get_input(),
+                case_data.get_output(),
+                case_data.get_error_output(),
+            ) {
+                (Ok(input), Ok(output), Ok(error_output)) => (
+                    retry_timeouts(
+                        options.timeout_retries(),
+                        || {
+                            test_output_against_strings(
+                                &cmd,
+                                args,
+                                env_vars,
+                                working_dir,
+                                &input,
+                                &output,
+                                &error_output,
+                                timeout,
+                                options,
+                            )
+                        },
+                        |attempt| {
+                            debug!(
+                                "{}/{}: timed out, so retrying (retry {})",
+                                student_name, case_name, attempt
+                            )
+                        },
+                    ),
+                    Some(input),
                 ),
-            )
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => (Err(e), None),
+            };
+            let result = match result {
+                Err(e) if e.is::<CommandNotFoundError<std::convert::Infallible>>() => {
+                    return Err(e)
+                }
+                result => result,
+            };
+            match &result {
+                Ok(answer) => info!(
+                    "{}/{}: {:?} in {:?}",
+                    student_name,
+                    case_name,
+                    answer.answer(),
+                    start.elapsed()
+                ),
+                Err(e) => info!(
+                    "{}/{}: error ({}) in {:?}",
+                    student_name,
+                    case_name,
+                    e,
+                    start.elapsed()
+                ),
+            }
+            let result = match input {
+                Some(input) if options.include_input_in_message() && !case_data.is_hidden() => {
+                    result.map(|result| with_input_in_message(result, &input))
+                }
+                _ => result,
+            };
+            // This is done last, so that the whole message (with the
+            // input) is within the limit
+            let result = match options.max_message_len() {
+                Some(max_len) => truncate_result(result, max_len),
+                None => result,
+            };
+            let result = result.map(describe);
+            on_case(student_name, case_name, &result);
+            Ok((case_name.clone(), result))
+        })
+        .collect()
+}
+
+/// Runs a case by calling `run`, and calls it again if the case times
+/// out, up to `retries` more times, so that a case only counts as a
+/// timeout if every run of it timed out. The first result which isn't a
+/// timeout is returned (even if it's an error).
+///
+/// `on_retry` is called before each retry, with which retry it is
+/// (starting from 1).
+fn retry_timeouts(
+    retries: usize,
+    mut run: impl FnMut() -> Result<CaseResult, Box<dyn Error + 'static>>,
+    mut on_retry: impl FnMut(usize),
+) -> Result<CaseResult, Box<dyn Error + 'static>> {
+    let mut result = run();
+    for attempt in 1..=retries {
+        if !matches!(result.as_deref(), Ok(TestAnswer::Timeout)) {
+            break;
+        }
+        on_retry(attempt);
+        result = run();
+    }
+    result
+}
+
+/// Passes each of a student's results to `on_case`, in order of case
+/// name, for results which were all decided at once (such as compile
+/// errors)
+fn report_all_cases(student_name: &str, results: &StudentResults, on_case: &mut CaseCallback) {
+    for (case_name, result) in results.iter().sorted_by_key(|a| a.0) {
+        on_case(student_name, case_name, result);
+    }
+}
+
+/// The marker appended to messages which were cut short
+const TRUNCATION_SUFFIX: &str = "… (truncated)";
+
+/// Shortens `message` to at most `max_len` characters, appending a
+/// marker if anything was cut off.
+fn truncate_message(message: &str, max_len: usize) -> String {
+    match message.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}{}", &message[..end], TRUNCATION_SUFFIX),
+        None => String::from(message),
+    }
+}
+
+/// Truncates any message carried by the given result (either in a
+/// `TestAnswer::FailWithMessage` or in an error) to `max_len`
+/// characters.
+fn truncate_result(
+    result: Result<CaseResult, Box<dyn Error + 'static>>,
+    max_len: usize,
+) -> Result<CaseResult, Box<dyn Error + 'static>> {
+    match result {
+        Ok(result) => Ok(result.map_answer(|answer| match answer {
+            TestAnswer::FailWithMessage(message) => {
+                TestAnswer::FailWithMessage(truncate_message(&message, max_len))
+            }
+            answer => answer,
+        })),
+        Err(e) => Err(truncate_message(&e.to_string(), max_len).into()),
+    }
+}
+
+/// Puts the input of the case at the start of the message of a failed
+/// result, so that the student can see what their code was given.
+/// Results which aren't failures are left alone.
+fn with_input_in_message(result: CaseResult, input: &str) -> CaseResult {
+    result.map_answer(|answer| match answer {
+        TestAnswer::Failure => TestAnswer::FailWithMessage(format!("Input:\n{}", input)),
+        TestAnswer::FailWithMessage(message) => {
+            TestAnswer::FailWithMessage(format!("Input:\n{}\n{}", input, message))
+        }
+        answer => answer,
+    })
+}
+
+/// The suffix of expected output files which only apply to the current
+/// platform
+#[cfg(target_family = "windows")]
+const PLATFORM_OUT_SUFFIX: &str = ".out.windows";
+#[cfg(not(target_family = "windows"))]
+const PLATFORM_OUT_SUFFIX: &str = ".out.unix";
+
+/// The suffixes of expected output files for every platform
+const ALL_PLATFORM_OUT_SUFFIXES: &[&str] = &[".out.windows", ".out.unix"];
+
+/// Whether the case has any of the files which `expected_output_text`
+/// looks for
+fn has_expected_output(dir: &str, case: &str) -> bool {
+    [PLATFORM_OUT_SUFFIX, ".out", ".url", ".regex"]
+        .iter()
+        .any(|suffix| Path::new(&format!("{}/{}{}", dir, case, suffix)).exists())
+}
+
+/// Whether the case has any of the files which `input_text` looks for
+fn has_input(dir: &str, case: &str) -> bool {
+    [".in", ".inputs", ".args"]
+        .iter()
+        .any(|suffix| Path::new(&format!("{}/{}{}", dir, case, suffix)).exists())
+}
+
+/// Finds the expected output for the given case in the tests directory.
+///
+/// This is normally the contents of `<case>.out`, but a file for just
+/// the current platform (`<case>.out.windows` or `<case>.out.unix`) is
+/// used instead if there is one. If neither is present and there is a
+/// `<case>.url` file instead, the expected output is downloaded from the
+/// URL written in it (which needs the "remote-tests" feature). If there
+/// is a `<case>.regex` file instead, the output must match the regex in
+/// it (see `REGEX_SENTINEL`).
+///
+/// Files aren't read yet, but it's an error if there isn't one.
+fn expected_output_text(
+    dir: &str,
+    case: &str,
+    remote: &mut RemoteCache,
+) -> Result<CaseText, Box<dyn Error + 'static>> {
+    let platform_path = PathBuf::from(format!("{}/{}{}", dir, case, PLATFORM_OUT_SUFFIX));
+    if platform_path.exists() {
+        return Ok(CaseText::File(platform_path));
+    }
+    let out_path = PathBuf::from(format!("{}/{}.out", dir, case));
+    let url_path = format!("{}/{}.url", dir, case);
+    if !out_path.exists() && Path::new(&url_path).exists() {
+        let output = remote.fetch(fs::read_to_string(url_path)?.trim())?;
+        return Ok(CaseText::Loaded(output.into()));
+    }
+    let regex_path = format!("{}/{}.regex", dir, case);
+    if !out_path.exists() && Path::new(&regex_path).exists() {
+        let pattern = fs::read_to_string(regex_path)?;
+        return Ok(CaseText::Loaded(
+            format!("{}\n{}", REGEX_SENTINEL, pattern).into(),
+        ));
+    }
+    File::open(&out_path)?;
+    Ok(CaseText::File(out_path))
+}
+
+/// Finds the input for the given case in the tests directory, which is
+/// the contents of `<case>.in`, passed through the input filter if there
+/// is one.
+///
+/// If there's no `<case>.in` but there is a `<case>.inputs` file, the
+/// input is instead put together from the files it lists (see
+/// `read_inputs_file`). If there's neither, but there's a `<case>.args`
+/// file (so the case only differs in its arguments), the input is empty.
+///
+/// Without a filter, the files aren't read yet, but it's an error if
+/// they don't exist.
+fn input_text(
+    dir: &str,
+    case: &str,
+    filter: &[String],
+) -> Result<CaseText, Box<dyn Error + 'static>> {
+    let in_path = PathBuf::from(format!("{}/{}.in", dir, case));
+    let inputs_path = format!("{}/{}.inputs", dir, case);
+    let args_path = format!("{}/{}.args", dir, case);
+    let text = if in_path.exists() {
+        File::open(&in_path)?;
+        CaseText::File(in_path)
+    } else if Path::new(&inputs_path).exists() {
+        CaseText::Files(read_inputs_file(dir, &inputs_path)?)
+    } else if Path::new(&args_path).exists() {
+        CaseText::Loaded("".into())
+    } else {
+        File::open(&in_path)?;
+        CaseText::File(in_path)
+    };
+    filter_case_text(text, filter)
+}
+
+/// Passes a case's input through the input filter, if there is one
+fn filter_case_text(
+    text: CaseText,
+    filter: &[String],
+) -> Result<CaseText, Box<dyn Error + 'static>> {
+    match filter.is_empty() {
+        true => Ok(text),
+        false => Ok(CaseText::Loaded(
+            filter_input(filter, String::from(&*text.load()?))?.into(),
+        )),
+    }
+}
+
+/// Reads a `<case>.inputs` file, which lists the files whose contents are
+/// concatenated (in order) to make up the input for a case, such as
+/// `input = ["shared/prefix.in", "shared/3.in"]`. The files are relative
+/// to the tests directory, and should be in a subdirectory of it, so
+/// they aren't taken as cases themselves.
+///
+/// It's an error if any of the listed files don't exist.
+fn read_inputs_file(dir: &str, filename: &str) -> Result<Vec<PathBuf>, Box<dyn Error + 'static>> {
+    let inputs: toml::Value = fs::read_to_string(filename)?.parse()?;
+    let files = match inputs.get("input") {
+        Some(toml::Value::Array(files)) => files
+            .iter()
+            .map(|file| match file {
+                toml::Value::String(file) => Ok(file.clone()),
+                _ => Err(CaseInputsError::with_description(format!(
+                    "\"input\" in {} may only contain strings",
+                    filename
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => {
+            return Err(CaseInputsError::with_description(format!(
+                "{} must have an \"input\" array",
+                filename
+            ))
+            .into())
+        }
+    };
+    input_file_paths(Path::new(dir), &files)
+}
+
+/// Finds the files whose contents make up a case's input (relative to
+/// `dir`), from a `<case>.inputs` file or an inline case's "input"
+/// array, checking that they all exist
+fn input_file_paths(
+    dir: &Path,
+    files: &[String],
+) -> Result<Vec<PathBuf>, Box<dyn Error + 'static>> {
+    let paths: Vec<PathBuf> = files.iter().map(|file| dir.join(file)).collect();
+    for path in &paths {
+        File::open(path)?;
+    }
+    Ok(paths)
+}
+
+/// Reads the arguments for the given case from `<case>.args` in the
+/// tests directory, which has either one argument per line, or a JSON
+/// array of strings (such as `["--size", "10"]`) for arguments which
+/// have newlines in them. A case without the file has no arguments of
+/// its own.
+fn read_case_args(dir: &str, case: &str) -> Result<Vec<String>, Box<dyn Error + 'static>> {
+    let filename = format!("{}/{}.args", dir, case);
+    if !Path::new(&filename).exists() {
+        return Ok(Vec::new());
+    }
+    let text = fs::read_to_string(&filename)?;
+    match text.trim_start().starts_with('[') {
+        true => serde_json::from_str(&text).map_err(|e| {
+            CaseArgsError::with_description(format!(
+                "{} should be a JSON array of strings: {}",
+                filename, e
+            ))
+            .into()
+        }),
+        false => Ok(text.lines().map(String::from).collect()),
+    }
+}
+
+/// Passes the given input through the filter command (the first element
+/// of `filter`, with the rest as its arguments), and returns what it
+/// printed.
+///
+/// It returns an error if the filter couldn't be run or didn't succeed.
+fn filter_input(filter: &[String], input: String) -> Result<String, Box<dyn Error + 'static>> {
+    let (cmd, args) = match filter.split_first() {
+        Some(command) => command,
+        None => return Ok(input),
+    };
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().ok_or_else(|| {
+        InputFilterError::with_description(String::from("Error grabbing input filter stdin"))
+    })?;
+    // Write from another thread, so that a filter which doesn't read all
+    // of its input before writing can't block on a full pipe
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().map_err(|_| {
+        InputFilterError::with_description(String::from("Panicked writing to input filter"))
+    })??;
+    if !output.status.success() {
+        return Err(InputFilterError::with_description(format!(
+            "Input filter {:?} failed with {}",
+            filter, output.status
+        ))
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Reads a manifest of case weights from the given toml file.
+///
+/// The file should be a table mapping case names to the number of
+/// points each case is worth, such as `"case_1" = 2.5`.
+fn read_weights_file(filename: &str) -> Result<HashMap<String, f64>, Box<dyn Error + 'static>> {
+    let weights: toml::Value = fs::read_to_string(filename)?.parse()?;
+    Ok(crate::conf::weights_from_toml(&weights, filename)?)
+}
+
+/// Sets the points for each case which has a weight given, leaving the
+/// rest with the default weight.
+fn apply_weights(cases: &mut HashMap<String, TestCase>, weights: &HashMap<String, f64>) {
+    for (name, case) in cases.iter_mut() {
+        case.points = weights.get(name).copied().unwrap_or(DEFAULT_POINTS);
+    }
+}
+
+/// Describes an error reading the tests or target directory (`kind`),
+/// saying which directory it was
+fn directory_error(
+    kind: &str,
+    dir: &str,
+    error: io::Error,
+) -> DirectoryError<std::convert::Infallible> {
+    DirectoryError::with_description(format!(
+        "Couldn't read the {} directory {}: {}",
+        kind, dir, error
+    ))
+}
+
+/// Reads the patterns of folders to ignore from the target directory's
+/// ignore file, each with whether it un-ignores the folders it matches
+/// (for lines starting with `!`), in the order they're given. Dotfolders
+/// are ignored before any of the file's patterns apply.
+///
+/// If there isn't an ignore file, only dotfolders are ignored.
+fn read_ignore_file(target_dir: &str) -> Result<Vec<(Pattern, bool)>, Box<dyn Error + 'static>> {
+    let path = Path::new(target_dir).join(IGNORE_FILE);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(IgnoreFileError::with_description(format!(
+                "Couldn't read {}: {}",
+                path.display(),
+                e
+            ))
+            .into())
+        }
+    };
+    let mut rules = vec![(Pattern::new(".*").unwrap(), false)];
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (line, negated) = match line.strip_prefix('!') {
+            Some(line) => (line, true),
+            None => (line.strip_prefix('\\').unwrap_or(line), false),
+        };
+        // Only top-level directories can be students, so the slashes
+        // which mark a pattern as anchored or as only matching
+        // directories don't change anything
+        let line = line.trim_start_matches('/').trim_end_matches('/');
+        let pattern = Pattern::new(line).map_err(|e| {
+            IgnoreFileError::with_description(format!(
+                "Invalid pattern \"{}\" in {}: {}",
+                line,
+                path.display(),
+                e
+            ))
+        })?;
+        rules.push((pattern, negated));
+    }
+    Ok(rules)
+}
+
+/// Whether the folder with the given name is ignored by the rules from
+/// `read_ignore_file`, where the last rule which matches it decides
+fn is_ignored(rules: &[(Pattern, bool)], name: &str) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|(pattern, _)| name_matches(pattern, name))
+        .is_some_and(|(_, negated)| !negated)
+}
+
+/// Finds the students in the given target directory, returning the name
+/// and path of each of them, sorted by name.
+///
+/// Each directory in the target directory is a student. If the options
+/// say to follow symlinks, then links to directories count too (except
+/// for links back to the target directory itself, and broken or cyclic
+/// links, which are skipped). If the options say to ignore folders, then
+/// dotfolders and those matching the target directory's ignore file
+/// are skipped.
+fn discover_students(
+    target_dir: &str,
+    options: &RunOptions,
+) -> Result<Vec<(String, PathBuf)>, Box<dyn Error + 'static>> {
+    let canonical_target =
+        fs::canonicalize(target_dir).map_err(|e| directory_error("target", target_dir, e))?;
+    let ignore_rules = match options.ignore_folders() {
+        true => read_ignore_file(target_dir)?,
+        false => Vec::new(),
+    };
+    Ok(fs::read_dir(target_dir)
+        .map_err(|e| directory_error("target", target_dir, e))?
+        .filter_map(|entry| {
+            // Remove non-directories and file i/o errors
+            let entry = entry.ok()?;
+            let filetype = entry.file_type().ok()?;
+            let is_dir = if filetype.is_symlink() && options.follow_symlinks() {
+                // Following a link fails for cyclic links, so they are
+                // skipped along with broken ones
+                fs::metadata(entry.path()).ok()?.is_dir()
+                    && fs::canonicalize(entry.path()).ok()? != canonical_target
+            } else {
+                filetype.is_dir()
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            match is_dir {
+                true if is_ignored(&ignore_rules, &name) => {
+                    debug!("{}: ignored, so not a student", name);
+                    None
+                }
+                true => Some((name, entry.path())),
+                false => None,
+            }
         })
+        .sorted()
+        .collect())
+}
+
+/// Keeps only the students whose folder names match one of the
+/// patterns (either exactly, or as a glob), warning about each pattern
+/// which doesn't match anyone (since it's probably a typo).
+fn filter_students(students: Vec<(String, PathBuf)>, filter: &[Pattern]) -> Vec<(String, PathBuf)> {
+    for pattern in filter {
+        if !students.iter().any(|(name, _)| name_matches(pattern, name)) {
+            warn!("No student's folder matches \"{}\"", pattern);
+        }
+    }
+    students
+        .into_iter()
+        .filter(|(name, _)| passes_filter(filter, name))
         .collect()
 }
 
+/// Whether the name is the pattern exactly, or matches it as a glob
+fn name_matches(pattern: &Pattern, name: &str) -> bool {
+    name == pattern.as_str() || pattern.matches(name)
+}
+
+/// Whether the name matches one of the patterns in the filter, where
+/// every name passes an empty filter
+fn passes_filter(filter: &[Pattern], name: &str) -> bool {
+    filter.is_empty() || filter.iter().any(|pattern| name_matches(pattern, name))
+}
+
+/// Renames each student to the first capture group of `name_regex` in
+/// their folder name (or leaves them with their folder name if it doesn't
+/// match).
+///
+/// If two students would end up with the same name, it returns an error.
+fn apply_student_name_regex(
+    students: Vec<(String, PathBuf)>,
+    name_regex: &Regex,
+) -> Result<Vec<(String, PathBuf)>, Box<dyn Error + 'static>> {
+    let mut folders_by_name: HashMap<String, String> = HashMap::new();
+    students
+        .into_iter()
+        .map(|(folder_name, path)| {
+            let name = name_regex
+                .captures(&folder_name)
+                .and_then(|caps| caps.get(1))
+                .map_or_else(|| folder_name.clone(), |name| String::from(name.as_str()));
+            if let Some(other_folder) = folders_by_name.insert(name.clone(), folder_name.clone()) {
+                return Err(StudentError::with_description(format!(
+                    "Students in \"{}\" and \"{}\" both have the name \"{}\"",
+                    other_folder, folder_name, name
+                ))
+                .into());
+            }
+            Ok((name, path))
+        })
+        .collect()
+}
+
+/// Runs the setup for the student in the given directory, and then tests
+/// them against all of the cases, passing each result to `on_case` when
+/// it's finished.
+///
+/// If the student is missing any of the files which the config says are
+/// required, all of the cases are marked as `TestAnswer::NoSubmission`,
+/// without setting up the student, and if the setup fails, all of the
+/// cases are marked as compile errors. Otherwise, if the options ask for
+/// a warmup, the student's code is run once (with empty input, ignoring
+/// the result) before the cases. If the options say to run student code
+/// in the student's directory, the path given to the config is made
+/// absolute first, so that it still works from there.
+///
+/// Problems specific to this student are recorded in the results, and
+/// an error is only returned if the whole run should be stopped.
+fn test_student_in_dir(
+    config: &TestConfig,
+    student_path: &Path,
+    student_name: &str,
+    test_data: &HashMap<String, TestCase>,
+    on_case: &mut CaseCallback,
+) -> Result<StudentResults, Box<dyn Error + 'static>> {
+    let absolute_path;
+    let student_path = match config.options().run_in_student_dir() {
+        true => {
+            absolute_path = std::env::current_dir()?.join(student_path);
+            absolute_path.as_path()
+        }
+        false => student_path,
+    };
+    let working_dir = match config.options().run_in_student_dir() {
+        true => Some(student_path),
+        false => None,
+    };
+    let student_path = match student_path.to_str() {
+        Some(path) => path,
+        None => {
+            let results = error_for_all_cases(test_data, "Student folder path isn't valid UTF-8");
+            report_all_cases(student_name, &results, on_case);
+            return Ok(results);
+        }
+    };
+    // Otherwise, an unreadable folder would look like an empty submission
+    fs::read_dir(student_path)?;
+    let missing_files: Vec<String> = config
+        .required_files()
+        .into_iter()
+        .filter(|file| !Path::new(student_path).join(file).exists())
+        .collect();
+    if !missing_files.is_empty() {
+        let message = format!("Missing from the submission: {}", missing_files.join(", "));
+        debug!("{}: {}", student_name, message);
+        let results = test_data
+            .iter()
+            .map(|(k, case)| {
+                (
+                    k.clone(),
+                    Ok(unless_skipped(
+                        case,
+                        TestAnswer::NoSubmission(message.clone()),
+                    )),
+                )
+            })
+            .collect();
+        report_all_cases(student_name, &results, on_case);
+        return Ok(results);
+    }
+    if let Err(message) = config.do_setup(student_path) {
+        let message = match config.options().max_message_len() {
+            Some(max_len) => truncate_message(&message, max_len),
+            None => message,
+        };
+        let results = test_data
+            .iter()
+            .map(|(k, case)| {
+                (
+                    k.clone(),
+                    Ok(unless_skipped(
+                        case,
+                        TestAnswer::CompileError(message.clone()),
+                    )),
+                )
+            })
+            .collect();
+        report_all_cases(student_name, &results, on_case);
+        return Ok(results);
+    }
+    let env_vars = config.env_vars(student_path);
+    if config.options().warmup() {
+        let warmup = test_output_against_strings(
+            &config.command(student_path),
+            &config.args(student_path),
+            &env_vars,
+            working_dir,
+            "",
+            "",
+            "",
+            *config.case_timeout(),
+            config.options(),
+        );
+        debug!("{}: warmup finished with {:?}", student_name, warmup);
+    }
+    test_student_against_test_case(
+        student_name,
+        config.command(student_path),
+        config.args(student_path),
+        &env_vars,
+        working_dir,
+        test_data,
+        *config.case_timeout(),
+        config.options(),
+        on_case,
+    )
+}
+
+/// Makes the result of a case which was decided without running it, with
+/// the case's points, which is `answer` unless the case is skipped (so
+/// that it still doesn't count towards anyone's total)
+fn unless_skipped(case: &TestCase, answer: TestAnswer) -> CaseResult {
+    let answer = match case.is_skipped() {
+        true => TestAnswer::Skipped,
+        false => answer,
+    };
+    CaseResult::from(answer)
+        .with_points(case.get_points())
+        .with_hidden(case.is_hidden())
+}
+
+/// Makes results in which every case failed with the given error message
+/// (except the skipped cases, which are still skipped)
+fn error_for_all_cases(cases: &HashMap<String, TestCase>, message: &str) -> StudentResults {
+    cases
+        .iter()
+        .map(|(name, case)| {
+            let result = match case.is_skipped() {
+                true => Ok(unless_skipped(case, TestAnswer::Skipped)),
+                false => Err(StudentError::with_description(String::from(message)).into()),
+            };
+            (name.clone(), result)
+        })
+        .collect()
+}
+
+/// Loads the test cases in one tests directory, in order of their
+/// names (see `load_test_cases`).
+fn load_directory_cases(
+    dir: &str,
+    config: &TestConfig,
+    remote: &mut RemoteCache,
+) -> Result<Vec<(String, TestCase)>, Box<dyn Error + 'static>> {
+    lazy_static! {
+        static ref FILENAME_EXT_REMOVER: Regex = Regex::new(r"(.*)[.][^.]+").unwrap();
+    }
+    let cases: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| directory_error("tests", dir, e))?
+        .filter_map(|file| {
+            let filename = file.ok()?.file_name();
+            let filename = match filename.to_str() {
+                Some(filename) => filename,
+                None => {
+                    warn!(
+                        "Skipping {:?} in {}, since its name isn't valid unicode",
+                        filename, dir
+                    );
+                    return None;
+                }
+            };
+            Some(String::from(
+                match ALL_PLATFORM_OUT_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| filename.strip_suffix(suffix))
+                {
+                    Some(case) => case,
+                    None => FILENAME_EXT_REMOVER
+                        .captures(filename)
+                        .and_then(|caps| caps.get(1))?
+                        .as_str(),
+                },
+            ))
+        })
+        .unique()
+        .sorted()
+        .filter(|case| {
+            let missing = match (has_input(dir, case), has_expected_output(dir, case)) {
+                (true, true) => return true,
+                (false, true) => "input (.in)",
+                (true, false) => "expected output (.out)",
+                (false, false) => "input (.in) or expected output (.out)",
+            };
+            warn!(
+                "Skipping \"{}\" in {}, since it has no {} file",
+                case, dir, missing
+            );
+            false
+        })
+        .filter(|case| passes_filter(config.options().case_filter(), case))
+        .collect();
+    cases
+        .into_iter()
+        .map(|case| {
+            let input = input_text(dir, &case, config.options().input_filter())?;
+            let output = expected_output_text(dir, &case, remote)?;
+            let error_path = PathBuf::from(format!("{}/{}.err", dir, case));
+            let error_output = match error_path.exists() {
+                true => Some(CaseText::File(error_path)),
+                false => None,
+            };
+            let args = read_case_args(dir, &case)?;
+            let skipped = Path::new(&format!("{}/{}.skip", dir, case)).exists();
+            let hidden = Path::new(&format!("{}/{}.hidden", dir, case)).exists();
+            let description = fs::read_to_string(format!("{}/{}.desc", dir, case))
+                .ok()
+                .map(|description| String::from(description.trim()));
+            Ok((
+                case,
+                TestCase {
+                    input,
+                    output,
+                    error_output,
+                    args,
+                    points: DEFAULT_POINTS,
+                    skipped,
+                    hidden,
+                    description,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Loads all of the test cases described by the configuration, mapping
+/// each case's name to its data.
+///
+/// Inputs and expected outputs which come straight from files are read
+/// from disk when they're used, rather than here. The cases in the tests
+/// directory are found in order of their names, and a name which only
+/// has an input or only has an expected output (such as a `.in` file
+/// without a matching `.out`) is skipped with a warning.
+///
+/// A tests directory may also be a `.tar.gz` (or `.tgz`) or `.zip`
+/// archive, which is extracted to a temporary directory (and needs the
+/// "archive-tests" feature). The cases in it are read into memory, and
+/// the temporary directory is deleted before this returns.
+///
+/// If there are several tests directories, the cases in all of them are
+/// loaded, and a case in a later directory replaces one with the same
+/// name in an earlier directory (or it's a `DuplicateCaseError`, if the
+/// options say so).
+///
+/// If the options have an input filter, each case's input is passed
+/// through it here, once for the whole run.
+///
+/// A case is skipped if there's a `<case>.skip` file next to it, its
+/// details are hidden from students if there's a `<case>.hidden` file, and its
+/// description is read from a `<case>.desc` file, if there is one. A
+/// case's input may also be put together from several files, listed in
+/// a `<case>.inputs` file (see `read_inputs_file`), and a case's own
+/// arguments are read from a `<case>.args` file (see `read_case_args`).
+/// A case's expected standard error is read from a `<case>.err` file, if
+/// there is one (which is only used if the options compare both
+/// streams).
+///
+/// The cases given in the config itself (see `RunOptions::inline_cases`)
+/// are added to these, and it's an error if one has the same name as a
+/// case in the tests directory.
+///
+/// If the options have a case filter, only the cases which match it are
+/// loaded (and none of the other cases' files are read).
+///
+/// Each case is worth the number of points given for it in the config's
+/// "weights" section, or else in the weights file, or else the default
+/// number of points.
+///
+/// If there's an issue reading the cases (or the weights for them), then
+/// it will return the relevant error.
+pub fn load_test_cases(
+    config: &TestConfig,
+) -> Result<HashMap<String, TestCase>, Box<dyn Error + 'static>> {
+    let mut test_data: HashMap<String, TestCase> = match config.test_type() {
+        TestType::Directory(dirs) => {
+            let mut test_data = HashMap::new();
+            let mut remote = RemoteCache::default();
+            for dir in dirs {
+                let cases = match archive::is_archive(dir) {
+                    // The extracted files are deleted afterwards, so they
+                    // have to be read now
+                    true => archive::with_extracted(dir, |extracted| {
+                        load_directory_cases(extracted, config, &mut remote)?
+                            .into_iter()
+                            .map(|(case, data)| Ok((case, data.into_loaded()?)))
+                            .collect()
+                    })?,
+                    false => load_directory_cases(dir, config, &mut remote)?,
+                };
+                for (case, data) in cases {
+                    if test_data.contains_key(&case) {
+                        if config.options().error_on_duplicate_cases() {
+                            return Err(DuplicateCaseError::with_description(format!(
+                                "There are two cases named \"{}\" in the tests directories",
+                                case
+                            ))
+                            .into());
+                        }
+                        info!(
+                            "Using the case \"{}\" in {} instead of the earlier one with that name",
+                            case, dir
+                        );
+                    }
+                    test_data.insert(case, data);
+                }
+            }
+            test_data
+        }
+        TestType::Inline => HashMap::new(),
+    };
+    // Inline cases' input files are found next to the other cases, where
+    // there are any which aren't in an archive
+    let inputs_dir = match config.test_type() {
+        TestType::Directory([dir, ..]) if !archive::is_archive(dir) => Path::new(dir),
+        _ => Path::new(""),
+    };
+    for case in config.options().inline_cases() {
+        if !passes_filter(config.options().case_filter(), case.name()) {
+            continue;
+        }
+        if test_data.contains_key(case.name()) {
+            return Err(DuplicateCaseError::with_description(format!(
+                "There are two cases named \"{}\"",
+                case.name()
+            ))
+            .into());
+        }
+        let input = match case.input_files() {
+            [] => CaseText::Loaded(case.input().into()),
+            files => CaseText::Files(input_file_paths(inputs_dir, files)?),
+        };
+        let test_case = TestCase {
+            input: filter_case_text(input, config.options().input_filter())?,
+            ..TestCase::new(String::new(), String::from(case.output()))
+        }
+        .with_hidden(case.is_hidden());
+        let test_case = match case.description() {
+            Some(description) => test_case.with_description(String::from(description)),
+            None => test_case,
+        };
+        test_data.insert(String::from(case.name()), test_case);
+    }
+    for pattern in config.options().case_filter() {
+        if !test_data.keys().any(|case| name_matches(pattern, case)) {
+            warn!("No case matches \"{}\"", pattern);
+        }
+    }
+    let mut weights = match config.options().weights_file() {
+        Some(weights_file) => read_weights_file(weights_file)?,
+        None => HashMap::new(),
+    };
+    weights.extend(
+        config
+            .weights()
+            .iter()
+            .map(|(case, points)| (case.clone(), *points)),
+    );
+    if !weights.is_empty() {
+        apply_weights(&mut test_data, &weights);
+    }
+    Ok(test_data)
+}
+
 /// Runs a test given the configuration, for all students in the
 /// directory given by the configuration.
 ///
-/// If there's an issue loading the folder specified by the config, then
-/// it will return the relevant error. Otherwise, it will return a
-/// HashMap mapping student names to a hash map mapping test names to
-/// that student's results on that test
+/// If there's an issue loading the folder specified by the config, or the
+/// command to run doesn't exist (which is checked before anyone is
+/// tested), then it will return the relevant error.
+/// Otherwise, it will return a HashMap mapping student names to a hash
+/// map mapping test names to that student's results on that test. Each
+/// student's name is the name of their folder, unless the options give a
+/// regex to find the name in it. If the options have a student filter,
+/// only the students whose folders match it are tested, and if they
+/// limit the number of students, only the first ones (sorted by folder
+/// name) are tested.
+///
+/// If a student can't be tested (such as because their folder can't be
+/// read, or testing them panics), each of their cases is an error
+/// saying why, the other students are still tested, and a warning
+/// listing those students is logged at the end.
+///
+/// If the options say to abort when the first students all fail a case,
+/// and they do, then it returns an `AllFailedError`.
+///
+/// If there aren't any cases to run, then it returns a `NoCasesError`
+/// (without testing any students).
 pub fn test_from_configuration(
     config: &TestConfig,
 ) -> Result<ClassResults, Box<dyn Error + 'static>> {
-    lazy_static! {
-        static ref FILENAME_EXT_REMOVER: Regex = Regex::new(r"(.*)[.][^.]+").unwrap();
+    test_from_configuration_with_progress(config, &mut |_, _, _| {})
+}
+
+/// Runs a test given the configuration, like `test_from_configuration`,
+/// but passes each result to `on_case` (along with the student and case
+/// names) as soon as that case is finished, so that progress can be
+/// shown during the run.
+pub fn test_from_configuration_with_progress(
+    config: &TestConfig,
+    on_case: &mut CaseCallback,
+) -> Result<ClassResults, Box<dyn Error + 'static>> {
+    resume_from_configuration(config, ClassResults::new(), on_case)
+}
+
+/// Continues a run which was stopped partway through, like
+/// `test_from_configuration_with_progress`, where `previous` has the
+/// results of the students who were already tested. Those students
+/// aren't tested again, and their results from `previous` are included
+/// in the returned results.
+pub fn resume_from_configuration(
+    config: &TestConfig,
+    previous: ClassResults,
+    on_case: &mut CaseCallback,
+) -> Result<ClassResults, Box<dyn Error + 'static>> {
+    let test_data = load_test_cases(config)?;
+    if test_data.is_empty() {
+        let message = match config.test_type() {
+            _ if !config.options().case_filter().is_empty() => {
+                String::from("No test cases match the case filter")
+            }
+            TestType::Directory(dirs) => format!("No test cases found in {}", dirs.join(", ")),
+            TestType::Inline => String::from("No test cases were given"),
+        };
+        return Err(NoCasesError::with_description(message).into());
     }
-    match config.test_type() {
-        TestType::Directory(dir) => {
-            let cases: Vec<String> = fs::read_dir(dir)?
-                .filter_map(|file| {
-                    match file.map(|f| {
-                        String::from(
-                            f.file_name()
-                                .to_str()
-                                .expect("Error parsing filename as unicode"),
-                        )
-                    }) {
-                        Ok(filename) => Some(String::from(
-                            FILENAME_EXT_REMOVER
-                                .captures(&filename)
-                                .map(|caps| caps.get(1))
-                                .flatten()?
-                                .as_str(),
-                        )),
-                        Err(_) => None,
-                    }
-                })
-                .unique()
-                .collect();
-            let inputs: Vec<String> = cases
-                .iter()
-                .map(|case| {
-                    let mut in_data = String::new();
-                    File::open(format!("{}/{}.in", dir, case))?.read_to_string(&mut in_data)?;
-                    Ok(in_data)
-                })
-                .collect::<Result<Vec<_>, Box<dyn Error + 'static>>>()?;
-            let outputs: Vec<String> = cases
+    // Get the students and test against the cases
+    let mut students = discover_students(config.target_dir(), config.options())?;
+    if !config.options().student_filter().is_empty() {
+        students = filter_students(students, config.options().student_filter());
+    }
+    if let Some(max_students) = config.options().max_students() {
+        students.truncate(max_students);
+    }
+    if let Some(name_regex) = config.options().student_name_regex() {
+        students = apply_student_name_regex(students, name_regex)?;
+    }
+    test_students(config, &test_data, &students, previous, on_case)
+}
+
+/// Tests each of the given students (each a name and the path to their
+/// directory) against the given cases, which don't have to come from a
+/// tests directory, for programs which have them some other way (such
+/// as a grading service which keeps its cases in a database). Make the
+/// cases with `TestCase::new`.
+///
+/// The config still says how to build and run each student's code, and
+/// its options apply as usual, except for the ones about finding the
+/// cases and students (such as the case and student filters), since
+/// those are given directly. Students are tested in the order given.
+///
+/// Errors are the same as for `test_from_configuration`.
+pub fn test_from_cases(
+    config: &TestConfig,
+    cases: &HashMap<String, TestCase>,
+    students: &[(String, PathBuf)],
+) -> Result<ClassResults, Box<dyn Error + 'static>> {
+    if cases.is_empty() {
+        return Err(
+            NoCasesError::with_description(String::from("No test cases were given")).into(),
+        );
+    }
+    test_students(
+        config,
+        cases,
+        students,
+        ClassResults::new(),
+        &mut |_, _, _| {},
+    )
+}
+
+/// Tests a single student, whose code is in the directory at
+/// `student_path`, against the cases described by the configuration, for
+/// programs which test students one at a time (such as when each one
+/// uploads their code). The student doesn't have to be in the config's
+/// target directory.
+///
+/// This does the same work for the student as `test_from_configuration`
+/// does for each student: if they're missing a required file, every case
+/// is a `TestAnswer::NoSubmission`, and if the setup (such as compiling
+/// their code) fails, every case is a `TestAnswer::CompileError` with
+/// what it printed. The cases are loaded each time this is called, so
+/// use `test_from_cases` to load them only once.
+///
+/// It returns an error if the cases can't be loaded (or there aren't
+/// any), the student's directory can't be read, or the command to run
+/// their code doesn't exist.
+pub fn test_student(
+    config: &TestConfig,
+    student_path: &Path,
+) -> Result<StudentResults, Box<dyn Error + 'static>> {
+    let test_data = load_test_cases(config)?;
+    if test_data.is_empty() {
+        return Err(
+            NoCasesError::with_description(String::from("No test cases were given")).into(),
+        );
+    }
+    let student_name = student_path.file_name().map_or_else(
+        || student_path.to_string_lossy(),
+        |name| name.to_string_lossy(),
+    );
+    test_student_in_dir(
+        config,
+        student_path,
+        &student_name,
+        &test_data,
+        &mut |_, _, _| {},
+    )
+}
+
+/// Tests each of the students against the cases, adding their results to
+/// `previous` (and skipping the students who are already in it).
+///
+/// Before anyone is tested, it checks that the programs which set up
+/// their code (such as a compiler) and the command to run it exist (see
+/// `check_command_exists`), so that a missing compiler or interpreter is
+/// a single `CommandNotFoundError` instead of an error for every case.
+fn test_students(
+    config: &TestConfig,
+    test_data: &HashMap<String, TestCase>,
+    students: &[(String, PathBuf)],
+    previous: ClassResults,
+    on_case: &mut CaseCallback,
+) -> Result<ClassResults, Box<dyn Error + 'static>> {
+    if let Some((_, student_path)) = students.first() {
+        let student_path = student_path.to_string_lossy();
+        for program in config.setup_programs(&student_path) {
+            check_setup_program_exists(&program)?;
+        }
+        check_command_exists(&config.command(&student_path), config.options())?;
+    }
+    let mut class_results = previous;
+    // The students who couldn't be tested at all, such as because their
+    // folder couldn't be read
+    let mut untested = Vec::new();
+    // The students tested by this call whose code ran, leaving out the
+    // ones whose results were already in `previous` (see `code_ran`)
+    let mut ran = Vec::new();
+    for (student_name, student_path) in students {
+        if class_results.contains_key(student_name) {
+            debug!("{}: already tested, so skipping", student_name);
+            continue;
+        }
+        // Now, let's test the students
+        let results = match panic::catch_unwind(AssertUnwindSafe(|| {
+            test_student_in_dir(config, student_path, student_name, test_data, on_case)
+        })) {
+            Ok(Ok(results)) => Ok(results),
+            // A command from the PATH is the same for everyone, so nobody
+            // else could be tested either
+            Ok(Err(e)) if e.is::<CommandNotFoundError<std::convert::Infallible>>() => {
+                return Err(e)
+            }
+            Ok(Err(e)) => Err(format!("Couldn't test student: {}", e)),
+            Err(payload) => {
+                let message = match payload.downcast_ref::<&str>() {
+                    Some(message) => String::from(*message),
+                    None => match payload.downcast_ref::<String>() {
+                        Some(message) => message.clone(),
+                        None => String::from("unknown panic"),
+                    },
+                };
+                Err(format!("Panicked while testing student: {}", message))
+            }
+        };
+        let results = results.unwrap_or_else(|message| {
+            warn!("{}: {}", student_name, message);
+            let results = error_for_all_cases(test_data, &message);
+            report_all_cases(student_name, &results, on_case);
+            untested.push(student_name.clone());
+            results
+        });
+        let student_ran = code_ran(&results);
+        class_results.insert(student_name.clone(), results);
+        if !student_ran {
+            continue;
+        }
+        ran.push(student_name);
+        // This is only checked once, when the K-th student's code has run
+        if config.options().abort_if_all_fail() == Some(ran.len()) {
+            let ran_results: Vec<&StudentResults> =
+                ran.iter().map(|name| &class_results[*name]).collect();
+            if let Some(case) = case_failed_by_all(&ran_results) {
+                let message = format!(
+                    "The first {} students whose code ran all failed case \"{}\", so the tests may be misconfigured",
+                    ran.len(),
+                    case
+                );
+                warn!("{}", message);
+                return Err(AllFailedError::with_description(message).into());
+            }
+        }
+    }
+    if !untested.is_empty() {
+        warn!(
+            "Couldn't test {} students (their results say why): {}",
+            untested.len(),
+            untested.join(", ")
+        );
+    }
+    Ok(class_results)
+}
+
+/// Returns whether the student's code actually ran on any case, as
+/// opposed to them not submitting, their code not compiling, or them
+/// not being testable at all
+fn code_ran(results: &StudentResults) -> bool {
+    results.values().any(|result| {
+        !matches!(
+            result.as_deref(),
+            Ok(TestAnswer::NoSubmission(_)) | Ok(TestAnswer::CompileError(_)) | Err(_)
+        )
+    })
+}
+
+/// Returns the first case (in sorted order) which every one of the
+/// students' results failed, if there is one. Only cases where the
+/// student's code ran and gave a wrong answer (or timed out) count as
+/// failed, so skipped cases and errors don't.
+fn case_failed_by_all<'a>(results: &[&'a StudentResults]) -> Option<&'a str> {
+    let cases = results.first()?.keys().sorted();
+    cases
+        .into_iter()
+        .find(|case| {
+            results.iter().all(|student_results| {
+                matches!(
+                    student_results.get(*case).map(|result| result.as_deref()),
+                    Some(Ok(TestAnswer::Failure))
+                        | Some(Ok(TestAnswer::FailWithMessage(_)))
+                        | Some(Ok(TestAnswer::Timeout))
+                        | Some(Ok(TestAnswer::Nondeterministic))
+                )
+            })
+        })
+        .map(String::as_str)
+}
+
+errormake!(#[doc="An error which prevented a student from being tested"] pub StudentError);
+errormake!(#[doc="An error running the input filter on a case"] pub InputFilterError);
+errormake!(#[doc="An error in the contents of a case's inputs file"] pub CaseInputsError);
+errormake!(#[doc="An error in the contents of a case's arguments file"] pub CaseArgsError);
+errormake!(#[doc="An error from two cases having the same name"] pub DuplicateCaseError);
+errormake!(#[doc="The run was stopped because the first students all failed a case"] pub AllFailedError);
+errormake!(#[doc="There weren't any cases to test students with"] pub NoCasesError);
+errormake!(#[doc="An error reading the tests or target directory"] pub DirectoryError);
+errormake!(#[doc="An error in the contents of the target directory's ignore file"] pub IgnoreFileError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_file() {
+        let weights = read_weights_file("test-data/java-test-1-weights.toml").unwrap();
+        let mut cases: HashMap<String, TestCase> = (1..=3)
+            .map(|i| {
+                (
+                    i.to_string(),
+                    TestCase {
+                        input: CaseText::Loaded("".into()),
+                        output: CaseText::Loaded("".into()),
+                        error_output: None,
+                        args: Vec::new(),
+                        points: DEFAULT_POINTS,
+                        skipped: false,
+                        hidden: false,
+                        description: None,
+                    },
+                )
+            })
+            .collect();
+        apply_weights(&mut cases, &weights);
+        assert_eq!(cases["1"].get_points(), 1.0);
+        assert_eq!(cases["2"].get_points(), 2.5);
+        assert_eq!(cases["3"].get_points(), 4.0);
+        // A weights file is held to the same rules as the [weights] table
+        let mut weights_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut weights_file, b"1 = -2\n").unwrap();
+        assert!(read_weights_file(weights_file.path().to_str().unwrap()).is_err());
+        let mut weights_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut weights_file, b"1 = \"two\"\n").unwrap();
+        assert!(read_weights_file(weights_file.path().to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_weights_section() {
+        let students = tempfile::tempdir().unwrap();
+        fs::create_dir(students.path().join("half")).unwrap();
+        // This passes case 1 (3 + 4) but not case 2 (10 + -2)
+        fs::write(
+            students.path().join("half/main.py"),
+            "a = int(input())\nb = int(input())\nprint(abs(a) + abs(b))\n",
+        )
+        .unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"weights\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\n[weights]\n1 = 0.5\n2 = 3\n",
+                students.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases["1"].get_points(), 0.5);
+        assert_eq!(cases["2"].get_points(), 3.0);
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(results["half"]["1"].as_ref().unwrap().points(), Some(0.5));
+        assert_eq!(crate::output::weighted_score(&results["half"]), (0.5, 3.5));
+        // The config's weights override the weights file's
+        let mut weights_file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut weights_file, b"1 = 2\n2 = 4\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"weights\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = \"d\"\nweights_file = {:?}\n[weights]\n2 = 3\n",
+                weights_file.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases["1"].get_points(), 2.0);
+        assert_eq!(cases["2"].get_points(), 3.0);
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"weights\"\ntests_dir = \"t\"\nfile = \"main.py\"\ntarget_dir = \"d\"\n[weights]\n1 = -1\n"
+                .parse()
+                .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_truncate_message() {
+        assert_eq!(truncate_message("abcde", 5), "abcde");
+        assert_eq!(truncate_message("abcdef", 5), "abcde… (truncated)");
+        assert_eq!(truncate_message("", 0), "");
+        assert_eq!(truncate_message("é", 0), "… (truncated)");
+        let result = truncate_result(
+            Ok(TestAnswer::FailWithMessage(String::from("expected 1, got 2")).into()),
+            8,
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            TestAnswer::FailWithMessage(String::from("expected… (truncated)"))
+        );
+        let result = truncate_result(Err("abc".into()), 3);
+        assert_eq!(result.unwrap_err().to_string(), "abc");
+        let result = truncate_result(Err("abcd".into()), 3);
+        assert_eq!(result.unwrap_err().to_string(), "abc… (truncated)");
+    }
+
+    #[test]
+    fn test_warmup() {
+        let target = tempfile::tempdir().unwrap();
+        let student = target.path().join("student");
+        fs::create_dir(&student).unwrap();
+        // Records each time it's run, and then adds up the input
+        fs::write(
+            student.join("main.py"),
+            "import sys\nwith open(sys.argv[1], 'a') as f:\n    f.write('run\\n')\nprint(sum(int(line) for line in sys.stdin))\n",
+        )
+        .unwrap();
+        let runs_file = student.join("runs");
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"warmup\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\nargs = [{:?}]\ntarget_dir = {:?}\n",
+                runs_file.to_str().unwrap(),
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(fs::read_to_string(&runs_file).unwrap().lines().count(), 2);
+        fs::remove_file(&runs_file).unwrap();
+        config.options_mut().set_warmup(true);
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["2"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(fs::read_to_string(&runs_file).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_student_name_regex() {
+        let name_regex = Regex::new(r"^([a-z]+_[a-z]+)_\d+_attempt").unwrap();
+        let students = vec![
+            (
+                String::from("lovelace_ada_12345_attempt_2"),
+                PathBuf::from("a"),
+            ),
+            (String::from("hopper_grace_678_attempt"), PathBuf::from("b")),
+            (String::from("solutions"), PathBuf::from("c")),
+        ];
+        assert_eq!(
+            apply_student_name_regex(students, &name_regex).unwrap(),
+            vec![
+                (String::from("lovelace_ada"), PathBuf::from("a")),
+                (String::from("hopper_grace"), PathBuf::from("b")),
+                (String::from("solutions"), PathBuf::from("c")),
+            ]
+        );
+        let students = vec![
+            (String::from("hopper_grace_1_attempt"), PathBuf::from("a")),
+            (String::from("hopper_grace_2_attempt"), PathBuf::from("b")),
+        ];
+        let error = apply_student_name_regex(students, &name_regex).unwrap_err();
+        assert!(error.to_string().contains("\"hopper_grace\""));
+    }
+
+    #[test]
+    fn test_input_filter() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "# Two numbers\n3\n4\n").unwrap();
+        fs::write(tests.path().join("1.out"), "7\n").unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"filter\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"d\"\ninput_filter = [\"sed\", \"/^#/d\"]\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(&*cases["1"].get_input().unwrap(), "3\n4\n");
+        assert_eq!(&*cases["1"].get_output().unwrap(), "7\n");
+        config
+            .options_mut()
+            .set_input_filter(vec![String::from("false")]);
+        assert!(load_test_cases(&config).is_err());
+        config.options_mut().set_input_filter(Vec::new());
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(&*cases["1"].get_input().unwrap(), "# Two numbers\n3\n4\n");
+    }
+
+    #[test]
+    fn test_cases_read_on_demand() {
+        let tests = tempfile::tempdir().unwrap();
+        for i in 1..=4 {
+            fs::write(tests.path().join(format!("{}.in", i)), format!("{}\n", i)).unwrap();
+            fs::write(
+                tests.path().join(format!("{}.out", i)),
+                format!("{}\n", i * i),
+            )
+            .unwrap();
+        }
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"lazy\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"d\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases.len(), 4);
+        for i in 1..=4 {
+            let case = &cases[&i.to_string()];
+            assert_eq!(&*case.get_input().unwrap(), format!("{}\n", i));
+            assert_eq!(&*case.get_output().unwrap(), format!("{}\n", i * i));
+        }
+        // The files are read when they're used, not when they're loaded
+        fs::write(tests.path().join("2.out"), "four\n").unwrap();
+        assert_eq!(&*cases["2"].get_output().unwrap(), "four\n");
+        fs::remove_file(tests.path().join("3.in")).unwrap();
+        assert!(cases["3"].get_input().is_err());
+        // Loading the cases again leaves it out
+        assert_eq!(
+            load_test_cases(&config)
+                .unwrap()
+                .keys()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec!["1", "2", "4"]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_case_files() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "3\n4\n").unwrap();
+        fs::write(tests.path().join("1.out"), "7\n").unwrap();
+        // An input without its output, an output without its input, and
+        // a stray file, none of which are cases
+        fs::write(tests.path().join("2.in"), "10\n-2\n").unwrap();
+        fs::write(tests.path().join("3.out"), "0\n").unwrap();
+        fs::write(tests.path().join("notes.txt"), "Case ideas\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"unmatched\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases.keys().collect::<Vec<_>>(), vec!["1"]);
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(results["correct"].len(), 1);
+        assert_eq!(
+            results["correct"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_multiple_tests_dirs() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("1.in"), "3\n4\n").unwrap();
+        fs::write(first.path().join("1.out"), "7\n").unwrap();
+        fs::write(first.path().join("2.in"), "1\n1\n").unwrap();
+        fs::write(first.path().join("2.out"), "3\n").unwrap();
+        // This replaces the (wrong) case 2 in the first directory
+        fs::write(second.path().join("2.in"), "1\n1\n").unwrap();
+        fs::write(second.path().join("2.out"), "2\n").unwrap();
+        fs::write(second.path().join("3.in"), "10\n-2\n").unwrap();
+        fs::write(second.path().join("3.out"), "8\n").unwrap();
+        let config_text = format!(
+            "[python]\nname = \"merged\"\ntests_dir = [{:?}, {:?}]\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n",
+            first.path().to_str().unwrap(),
+            second.path().to_str().unwrap()
+        );
+        let config = TestConfig::from_toml_values(config_text.parse().unwrap()).unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(
+            cases.keys().sorted().collect::<Vec<_>>(),
+            vec!["1", "2", "3"]
+        );
+        assert_eq!(&*cases["2"].get_output().unwrap(), "2\n");
+        let results = test_from_configuration(&config).unwrap();
+        for case in ["1", "2", "3"].iter() {
+            assert_eq!(
+                results["correct"][*case].as_ref().unwrap(),
+                &TestAnswer::Success
+            );
+        }
+        // Or it can be an error for both to have case 2
+        let config = TestConfig::from_toml_values(
+            format!("{}error_on_duplicate_cases = true\n", config_text)
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        match load_test_cases(&config) {
+            Err(e) => assert!(e.to_string().contains("two cases named \"2\"")),
+            Ok(_) => panic!("Expected an error for the duplicate case"),
+        }
+    }
+
+    #[test]
+    fn test_case_args() {
+        let tests = tempfile::tempdir().unwrap();
+        let student = tempfile::tempdir().unwrap();
+        fs::create_dir(student.path().join("a")).unwrap();
+        fs::write(
+            student.path().join("a/main.py"),
+            "import sys\nprint(\"|\".join(sys.argv[1:] + sys.stdin.read().split()))\n",
+        )
+        .unwrap();
+        // Cases with only arguments don't need an input file
+        fs::write(tests.path().join("lines.args"), "5\nhello world\n").unwrap();
+        fs::write(tests.path().join("lines.out"), "-v|5|hello world\n").unwrap();
+        fs::write(tests.path().join("json.args"), "[\"a\\nb\", \"c\"]\n").unwrap();
+        fs::write(tests.path().join("json.out"), "-v|a\nb|c\n").unwrap();
+        fs::write(tests.path().join("both.in"), "y\n").unwrap();
+        fs::write(tests.path().join("both.args"), "z\n").unwrap();
+        fs::write(tests.path().join("both.out"), "-v|z|y\n").unwrap();
+        fs::write(tests.path().join("plain.in"), "x\n").unwrap();
+        fs::write(tests.path().join("plain.out"), "-v|x\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"args\"\ntests_dir = {:?}\nfile = \"main.py\"\nargs = [\"-v\"]\ntarget_dir = {:?}\n",
+                tests.path().to_str().unwrap(),
+                student.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases["lines"].get_args(), ["5", "hello world"]);
+        assert_eq!(cases["json"].get_args(), ["a\nb", "c"]);
+        assert!(cases["plain"].get_args().is_empty());
+        let results = test_from_configuration(&config).unwrap();
+        for case in ["lines", "json", "both", "plain"].iter() {
+            assert_eq!(
+                results["a"][*case].as_ref().unwrap(),
+                &TestAnswer::Success,
+                "{}",
+                case
+            );
+        }
+        // A JSON array can only have strings
+        fs::write(tests.path().join("json.args"), "[5]\n").unwrap();
+        match load_test_cases(&config) {
+            Err(e) => assert!(e.to_string().contains("json.args")),
+            Ok(_) => panic!("Expected an error for the bad arguments file"),
+        }
+    }
+
+    #[test]
+    fn test_compare_stderr() {
+        let tests = tempfile::tempdir().unwrap();
+        let student = tempfile::tempdir().unwrap();
+        fs::create_dir(student.path().join("a")).unwrap();
+        fs::write(
+            student.path().join("a/main.py"),
+            "import sys\nn = int(input())\nprint(n * 2)\nif n < 0:\n    print(\"negative\", file=sys.stderr)\n",
+        )
+        .unwrap();
+        fs::write(tests.path().join("positive.in"), "3\n").unwrap();
+        fs::write(tests.path().join("positive.out"), "6\n").unwrap();
+        fs::write(tests.path().join("negative.in"), "-3\n").unwrap();
+        fs::write(tests.path().join("negative.out"), "-6\n").unwrap();
+        fs::write(tests.path().join("negative.err"), "negative\n").unwrap();
+        fs::write(tests.path().join("missing.in"), "-1\n").unwrap();
+        fs::write(tests.path().join("missing.out"), "-2\n").unwrap();
+        let config = |compare_stream: &str| {
+            TestConfig::from_toml_values(
+                format!(
+                    "[python]\nname = \"stderr\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = {:?}\ncompare_stream = \"{}\"\n",
+                    tests.path().to_str().unwrap(),
+                    student.path().to_str().unwrap(),
+                    compare_stream
+                )
+                .parse()
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        let results = test_from_configuration(&config("both")).unwrap();
+        let answer = |case: &str| results["a"][case].as_ref().unwrap().answer().clone();
+        assert_eq!(answer("positive"), TestAnswer::Success);
+        assert_eq!(answer("negative"), TestAnswer::Success);
+        // Without a .err file, nothing may be printed to stderr
+        assert!(
+            matches!(answer("missing"), TestAnswer::FailWithMessage(message)
+            if message.starts_with("On standard error"))
+        );
+        // Only comparing stderr, it's compared against the .out file
+        let results = test_from_configuration(&config("stderr")).unwrap();
+        assert!(matches!(
+            results["a"]["negative"].as_deref(),
+            Ok(TestAnswer::FailWithMessage(_))
+        ));
+        fs::write(tests.path().join("negative.out"), "negative\n").unwrap();
+        let results = test_from_configuration(&config("stderr")).unwrap();
+        assert_eq!(
+            results["a"]["negative"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "archive-tests")]
+    fn test_archived_tests_dir() {
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"archived\"\ntests_dir = \"test-data/python-test-1-tests.tar.gz\"\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(
+            cases.keys().sorted().collect::<Vec<_>>(),
+            vec!["1", "2", "3"]
+        );
+        // The cases were read before the extracted files were deleted
+        assert_eq!(&*cases["3"].get_output().unwrap(), "123\n");
+        let results = test_from_configuration(&config).unwrap();
+        for case in ["1", "2", "3"].iter() {
+            assert_eq!(
+                results["correct"][*case].as_ref().unwrap(),
+                &TestAnswer::Success
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "archive-tests"))]
+    fn test_archived_tests_dir_without_feature() {
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"archived\"\ntests_dir = \"test-data/python-test-1-tests.tar.gz\"\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        match load_test_cases(&config) {
+            Err(e) => assert!(e.to_string().contains("\"archive-tests\" feature")),
+            Ok(_) => panic!("Expected an error without the archive-tests feature"),
+        }
+    }
+
+    #[test]
+    fn test_concatenated_inputs() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::create_dir(tests.path().join("fragments")).unwrap();
+        fs::write(tests.path().join("fragments/common.in"), "3\n").unwrap();
+        fs::write(tests.path().join("fragments/3.in"), "4\n").unwrap();
+        fs::write(
+            tests.path().join("3.inputs"),
+            "input = [\"fragments/common.in\", \"fragments/3.in\"]\n",
+        )
+        .unwrap();
+        fs::write(tests.path().join("3.out"), "7\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"fragments\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"d\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases.keys().collect::<Vec<_>>(), vec!["3"]);
+        assert_eq!(&*cases["3"].get_input().unwrap(), "3\n4\n");
+        fs::write(
+            tests.path().join("3.inputs"),
+            "input = [\"fragments/common.in\", \"fragments/missing.in\"]\n",
+        )
+        .unwrap();
+        assert!(load_test_cases(&config).is_err());
+    }
+
+    #[test]
+    fn test_concatenated_inline_inputs() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::create_dir(tests.path().join("fragments")).unwrap();
+        fs::write(tests.path().join("fragments/common.in"), "3\n").unwrap();
+        fs::write(tests.path().join("fragments/3.in"), "4\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"fragments\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n\n[[python.cases]]\nname = \"3\"\ninput = [\"fragments/common.in\", \"fragments/3.in\"]\noutput = \"7\\n\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases.keys().collect::<Vec<_>>(), vec!["3"]);
+        assert_eq!(&*cases["3"].get_input().unwrap(), "3\n4\n");
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["correct"]["3"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        // Without a tests directory, they're relative to the current
+        // directory
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"fragments\"\nfile = \"main.py\"\ntarget_dir = \"d\"\n\n[[python.cases]]\ninput = [{:?}, \"test-data/python-test-1/tests/2.in\"]\noutput = \"\"\n",
+                tests.path().join("fragments/common.in").to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            &*load_test_cases(&config).unwrap()["inline_1"]
+                .get_input()
+                .unwrap(),
+            "3\n10\n-2\n"
+        );
+        fs::remove_file(tests.path().join("fragments/common.in")).unwrap();
+        assert!(load_test_cases(&config).is_err());
+    }
+
+    #[test]
+    fn test_multiline_inline_cases() {
+        // Copies of the cases in the tests directory, written as
+        // multiline strings in a few different ways
+        let config_text = r#"[python]
+name = "python-test-1"
+tests_dir = "test-data/python-test-1/tests"
+file = "main.py"
+target_dir = "test-data/python-test-1/students"
+timeout = 1
+compare = "exact"
+
+[[python.cases]]
+name = "inline_1"
+input = """
+3
+4
+"""
+output = """
+7
+"""
+
+[[python.cases]]
+name = "inline_2"
+input = '''10
+-2
+'''
+output = "8\n"
+
+[[python.cases]]
+name = "no_final_newline"
+hidden = true
+input = """
+10
+-2
+"""
+output = """
+8"""
+"#;
+        let config = TestConfig::from_toml_values(config_text.parse().unwrap()).unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(cases.len(), 5);
+        assert!(cases["no_final_newline"].is_hidden());
+        assert!(!cases["inline_1"].is_hidden());
+        for (file_case, inline_case) in [("1", "inline_1"), ("2", "inline_2")].iter() {
+            assert_eq!(
+                cases[*file_case].get_input().unwrap(),
+                cases[*inline_case].get_input().unwrap()
+            );
+            assert_eq!(
+                cases[*file_case].get_output().unwrap(),
+                cases[*inline_case].get_output().unwrap()
+            );
+        }
+        assert_eq!(&*cases["no_final_newline"].get_output().unwrap(), "8");
+        let results = test_from_configuration(&config).unwrap();
+        for student in results.values() {
+            assert_eq!(
+                student["1"].as_deref().unwrap(),
+                student["inline_1"].as_deref().unwrap()
+            );
+            assert_eq!(
+                student["2"].as_deref().unwrap(),
+                student["inline_2"].as_deref().unwrap()
+            );
+        }
+        assert_eq!(
+            results["correct"]["no_final_newline"].as_deref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from(
+                "Expected no newline at the end of the output"
+            ))
+        );
+        // An inline case can't have the same name as one in the directory
+        let config = TestConfig::from_toml_values(
+            config_text
+                .replace("name = \"inline_1\"", "name = \"1\"")
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(load_test_cases(&config).is_err());
+    }
+
+    #[test]
+    fn test_only_inline_cases() {
+        let config = TestConfig::from_toml_values(
+            r#"[python]
+name = "inline only"
+file = "main.py"
+target_dir = "test-data/python-test-1/students"
+
+[[python.cases]]
+input = "3\n4\n"
+output = "7\n"
+
+[[python.cases]]
+name = "negative"
+input = "10\n-2\n"
+output = "8\n"
+"#
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.test_type(), TestType::Inline);
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(
+            cases.keys().sorted().collect::<Vec<_>>(),
+            vec!["inline_1", "negative"]
+        );
+        let results = test_from_configuration(&config).unwrap();
+        for case in ["inline_1", "negative"].iter() {
+            assert_eq!(
+                results["correct"][*case].as_ref().unwrap(),
+                &TestAnswer::Success
+            );
+        }
+        // Without any cases, the tests directory is still needed
+        assert!(TestConfig::from_toml_values(
+            "[python]\nname = \"none\"\nfile = \"main.py\"\ntarget_dir = \"d\"\n"
+                .parse()
+                .unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run_in_student_dir() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "").unwrap();
+        fs::write(tests.path().join("1.out"), "from the student's folder\n").unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let student = target.path().join("student");
+        fs::create_dir(&student).unwrap();
+        fs::write(
+            student.join("main.py"),
+            "with open('./local.txt') as f:\n    print(f.read().strip())\n",
+        )
+        .unwrap();
+        fs::write(student.join("local.txt"), "from the student's folder\n").unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"local\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                tests.path().to_str().unwrap(),
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        // Without it, the file is looked for where the tests are run from,
+        // so the student's code crashes
+        config.options_mut().set_run_in_student_dir(false);
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["1"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from("Expected exit code 0, got 1"))
+        );
+    }
+
+    #[test]
+    fn test_no_cases() {
+        let tests = tempfile::tempdir().unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"empty\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let error = test_from_configuration(&config).unwrap_err();
+        assert!(error.is::<NoCasesError<std::convert::Infallible>>());
+        assert!(error.to_string().contains("No test cases found in"));
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"empty\"\ncases = []\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        let error = test_from_configuration(&config).unwrap_err();
+        assert!(error.is::<NoCasesError<std::convert::Infallible>>());
+        // Without any students, there are just no results
+        let students = tempfile::tempdir().unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"nobody\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                students.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(test_from_configuration(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_directories() {
+        for (tests_dir, target_dir, message) in [
+            (
+                "test-data/nonexistent",
+                "test-data/python-test-1/students",
+                "Couldn't read the tests directory test-data/nonexistent",
+            ),
+            (
+                "test-data/python-test-1/tests",
+                "test-data/nonexistent",
+                "Couldn't read the target directory test-data/nonexistent",
+            ),
+        ]
+        .iter()
+        {
+            let config = TestConfig::from_toml_values(
+                format!(
+                    "[python]\nname = \"missing\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                    tests_dir, target_dir
+                )
+                .parse()
+                .unwrap(),
+            )
+            .unwrap();
+            let error = test_from_configuration(&config).unwrap_err();
+            assert!(error.is::<DirectoryError<std::convert::Infallible>>());
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_command_config() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "hello\n").unwrap();
+        fs::write(tests.path().join("1.out"), "hello\n").unwrap();
+        let target = tempfile::tempdir().unwrap();
+        for student in ["ready", "unready"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+        }
+        fs::write(target.path().join("ready").join("built"), "").unwrap();
+        // Setup only works for students who have the file it looks for
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[command]\nname = \"echo\"\ntests_dir = {:?}\ncommand = \"cat\"\nsetup = [\"sh\", \"-c\", \"test -f {{student_dir}}/built || {{ echo not built >&2; exit 1; }}\"]\ntarget_dir = {:?}\n",
+                tests.path().to_str().unwrap(),
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["ready"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(
+            results["unready"]["1"].as_ref().unwrap(),
+            &TestAnswer::CompileError(String::from("not built\n"))
+        );
+    }
+
+    #[test]
+    fn test_compile_error_message() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "21\n").unwrap();
+        fs::write(tests.path().join("1.out"), "42\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"helpers\"\ntests_dir = {:?}\nfiles = [\"main.py\", \"helpers.py\"]\ntarget_dir = \"test-data/python-multi-file\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["complete"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        // What the syntax check printed is kept, for the student to see
+        match results["broken_helper"]["1"].as_deref() {
+            Ok(TestAnswer::CompileError(message)) => {
+                assert!(message.contains("SyntaxError"), "{}", message)
+            }
+            result => panic!("Expected a compile error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_missing_entry_file() {
+        let target = tempfile::tempdir().unwrap();
+        for student in ["submitted", "misnamed"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+        }
+        fs::write(
+            target.path().join("submitted/main.py"),
+            "import sys\nprint(sum(int(line) for line in sys.stdin))\n",
+        )
+        .unwrap();
+        fs::write(target.path().join("misnamed/Main.py"), "print(7)\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"missing\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["submitted"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        for case in ["1", "2"].iter() {
+            assert_eq!(
+                results["misnamed"][*case].as_ref().unwrap(),
+                &TestAnswer::NoSubmission(String::from("Missing from the submission: main.py"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_include_input_in_message() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("public.in"), "public input\n").unwrap();
+        fs::write(tests.path().join("public.out"), "wrong\n").unwrap();
+        fs::write(tests.path().join("secret.in"), "secret input\n").unwrap();
+        fs::write(tests.path().join("secret.out"), "wrong\n").unwrap();
+        fs::write(tests.path().join("secret.hidden"), "").unwrap();
+        let target = tempfile::tempdir().unwrap();
+        fs::create_dir(target.path().join("student")).unwrap();
+        fs::write(target.path().join("student/main.py"), "print(input())\n").unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"inputs\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = {:?}\ninclude_input_in_message = true\n",
+                tests.path().to_str().unwrap(),
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["public"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from(
+                "Input:\npublic input\n\nLine 1: expected \"wrong\", got \"public input\""
+            ))
+        );
+        assert_eq!(
+            results["student"]["secret"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"wrong\", got \"secret input\""
+            ))
+        );
+        // Whether each case is hidden is carried in its result
+        assert!(results["student"]["secret"].as_ref().unwrap().is_hidden());
+        assert!(!results["student"]["public"].as_ref().unwrap().is_hidden());
+        // The message with the input in it is kept within the limit
+        config.options_mut().set_max_message_len(Some(20));
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["public"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(format!("Input:\npublic input\n{}", TRUNCATION_SUFFIX))
+        );
+        config.options_mut().set_max_message_len(None);
+        config.options_mut().set_include_input_in_message(false);
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["student"]["public"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"wrong\", got \"public input\""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_from_in_memory_cases() {
+        let config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        let mut cases = HashMap::new();
+        cases.insert(
+            String::from("sum"),
+            TestCase::new(String::from("3\n4\n"), String::from("7\n")).with_points(2.0),
+        );
+        cases.insert(
+            String::from("negative"),
+            TestCase::new(String::from("10\n-2\n"), String::from("8\n")),
+        );
+        cases.insert(
+            String::from("later"),
+            TestCase::new(String::new(), String::new()).with_skipped(true),
+        );
+        // The students don't have to be in the target directory, or be
+        // named after their folders
+        let students = vec![
+            (
+                String::from("Alice"),
+                PathBuf::from("test-data/python-test-1/students/correct"),
+            ),
+            (
+                String::from("Bob"),
+                PathBuf::from("test-data/python-test-1/students/wrong"),
+            ),
+        ];
+        let results = test_from_cases(&config, &cases, &students).unwrap();
+        assert_eq!(
+            results.keys().sorted().collect::<Vec<_>>(),
+            vec!["Alice", "Bob"]
+        );
+        assert_eq!(
+            results["Alice"]["sum"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(
+            results["Alice"]["negative"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(
+            results["Alice"]["later"].as_ref().unwrap(),
+            &TestAnswer::Skipped
+        );
+        assert!(!matches!(
+            results["Bob"]["sum"].as_deref(),
+            Ok(TestAnswer::Success)
+        ));
+        assert!(test_from_cases(&config, &HashMap::new(), &students).is_err());
+    }
+
+    #[test]
+    fn test_single_student() {
+        let config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        let results = test_student(
+            &config,
+            Path::new("test-data/python-test-1/students/correct"),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results.values() {
+            assert_eq!(result.as_ref().unwrap(), &TestAnswer::Success);
+        }
+        let config = TestConfig::from_file("test-data/java-test-1.toml").unwrap();
+        // A student who doesn't compile gets a compile error for every
+        // case, like in a whole run
+        let results = test_student(
+            &config,
+            Path::new("test-data/java-test-1/students/compile_fail"),
+        )
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        for result in results.values() {
+            assert!(matches!(result.as_deref(), Ok(TestAnswer::CompileError(_))));
+        }
+        assert!(test_student(&config, Path::new("test-data/java-test-1/students/nobody")).is_err());
+    }
+
+    #[test]
+    fn test_max_students() {
+        let mut config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        config.options_mut().set_max_students(Some(2));
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results.keys().sorted().collect::<Vec<_>>(),
+            vec!["correct", "panics"]
+        );
+        config.options_mut().set_max_students(Some(0));
+        assert!(test_from_configuration(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_student_filter() {
+        let target = tempfile::tempdir().unwrap();
+        for student in ["smith_a", "smith_b", "jones", "doe[2]"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+            fs::write(
+                target.path().join(student).join("main.py"),
+                "print(int(input()) + int(input()))\n",
+            )
+            .unwrap();
+        }
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"filtered\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\nstudents = [\"jones\"]\n",
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(results.keys().collect::<Vec<_>>(), vec!["jones"]);
+        assert_eq!(
+            results["jones"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        // Globs match several students, and an exact name is matched
+        // even if it has special characters
+        config.options_mut().set_student_filter(vec![
+            Pattern::new("smith*").unwrap(),
+            Pattern::new("doe[2]").unwrap(),
+            Pattern::new("nobody").unwrap(),
+        ]);
+        assert_eq!(
+            test_from_configuration(&config)
+                .unwrap()
+                .keys()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec!["doe[2]", "smith_a", "smith_b"]
+        );
+        // An empty filter tests everyone
+        config.options_mut().set_student_filter(Vec::new());
+        assert_eq!(test_from_configuration(&config).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_case_filter() {
+        let tests = tempfile::tempdir().unwrap();
+        for (case, input, output) in
+            [("small", "3\n4\n", "7\n"), ("negative", "10\n-2\n", "8\n")].iter()
+        {
+            fs::write(tests.path().join(format!("{}.in", case)), input).unwrap();
+            fs::write(tests.path().join(format!("{}.out", case)), output).unwrap();
+        }
+        // The filter is applied before the files are read, so this
+        // (unreadable) case doesn't cause an error when it isn't run
+        fs::write(tests.path().join("large.inputs"), "not toml").unwrap();
+        fs::write(tests.path().join("large.out"), "2\n").unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"filtered\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\nonly_cases = [\"neg*\"]\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["correct"].keys().collect::<Vec<_>>(),
+            vec!["negative"]
+        );
+        assert_eq!(
+            results["correct"]["negative"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        config
+            .options_mut()
+            .set_case_filter(vec![Pattern::new("nothing").unwrap()]);
+        assert!(test_from_configuration(&config)
+            .unwrap_err()
+            .to_string()
+            .contains("No test cases match the case filter"));
+        // An empty filter runs every case
+        config.options_mut().set_case_filter(Vec::new());
+        assert!(load_test_cases(&config).is_err());
+    }
+
+    #[test]
+    fn test_platform_expected_output() {
+        let tests = tempfile::tempdir().unwrap();
+        for case in ["both", "plain"].iter() {
+            fs::write(tests.path().join(format!("{}.in", case)), "").unwrap();
+            fs::write(tests.path().join(format!("{}.out", case)), "plain\n").unwrap();
+        }
+        fs::write(tests.path().join("both.out.windows"), "windows\n").unwrap();
+        fs::write(tests.path().join("both.out.unix"), "unix\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"platform\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"d\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(
+            cases.keys().sorted().collect::<Vec<_>>(),
+            vec!["both", "plain"]
+        );
+        let expected = match cfg!(windows) {
+            true => "windows\n",
+            false => "unix\n",
+        };
+        assert_eq!(&*cases["both"].get_output().unwrap(), expected);
+        assert_eq!(&*cases["plain"].get_output().unwrap(), "plain\n");
+    }
+
+    #[test]
+    fn test_regex_file() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "3\n4\n").unwrap();
+        fs::write(tests.path().join("1.regex"), "[0-9]\n").unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"regex\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let cases = load_test_cases(&config).unwrap();
+        assert_eq!(&*cases["1"].get_output().unwrap(), "#regex\n[0-9]\n");
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["correct"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(
+            results["wrong"]["1"].as_ref().unwrap(),
+            &TestAnswer::FailWithMessage(String::from("The output doesn't match /[0-9]/"))
+        );
+    }
+
+    #[test]
+    fn test_abort_if_all_fail() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "3\n4\n").unwrap();
+        fs::write(tests.path().join("1.out"), "7\n").unwrap();
+        // Nobody can pass this case, as if the reference were broken
+        fs::write(tests.path().join("2.in"), "10\n-2\n").unwrap();
+        fs::write(tests.path().join("2.out"), "-20\n").unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"abort\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\nabort_if_all_fail = 2\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let error = test_from_configuration(&config).unwrap_err();
+        assert!(error.is::<AllFailedError<std::convert::Infallible>>());
+        assert!(error.to_string().contains("case \"2\""));
+        config.options_mut().set_abort_if_all_fail(None);
+        let mut results = test_from_configuration(&config).unwrap();
+        assert_eq!(results.len(), 3);
+        // Only the students tested after resuming count towards the
+        // threshold
+        results.retain(|name, _| name == "correct");
+        config.options_mut().set_abort_if_all_fail(Some(2));
+        let error = resume_from_configuration(&config, results, &mut |_, _, _| {}).unwrap_err();
+        assert!(error.is::<AllFailedError<std::convert::Infallible>>());
+        assert!(error.to_string().contains("The first 2 students"));
+        // Students who didn't submit don't count, so it waits for two
+        // students whose code ran
+        let target = tempfile::tempdir().unwrap();
+        for student in ["a", "b", "c", "d"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+        }
+        fs::copy(
+            "test-data/python-test-1/students/correct/main.py",
+            target.path().join("c/main.py"),
+        )
+        .unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"abort\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = {:?}\nabort_if_all_fail = 2\n",
+                tests.path().to_str().unwrap(),
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(test_from_configuration(&config).unwrap().len(), 4);
+        fs::copy(
+            "test-data/python-test-1/students/correct/main.py",
+            target.path().join("d/main.py"),
+        )
+        .unwrap();
+        let error = test_from_configuration(&config).unwrap_err();
+        assert!(error.is::<AllFailedError<std::convert::Infallible>>());
+        // The working python-test-1 cases are fine
+        let mut config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        config.options_mut().set_abort_if_all_fail(Some(2));
+        assert_eq!(test_from_configuration(&config).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_unreadable_student() {
+        let target = tempfile::tempdir().unwrap();
+        for student in ["a", "b", "c"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+            fs::copy(
+                "test-data/python-test-1/students/correct/main.py",
+                target.path().join(student).join("main.py"),
+            )
+            .unwrap();
+        }
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"unreadable\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        // The second student's folder disappears after they're found, so
+        // it can't be read when they're tested
+        let mut removed = false;
+        let results = test_from_configuration_with_progress(&config, &mut |student, _, _| {
+            if student == "a" && !removed {
+                fs::remove_dir_all(target.path().join("b")).unwrap();
+                removed = true;
+            }
+        })
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        for student in ["a", "c"].iter() {
+            assert!(results[*student]
+                .values()
+                .all(|result| result.as_ref().unwrap() == &TestAnswer::Success));
+        }
+        for result in results["b"].values() {
+            let message = result.as_ref().unwrap_err().to_string();
+            assert!(
+                message.contains("Couldn't test student: No such file or directory"),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_unicode_case_file() {
+        use std::os::unix::ffi::OsStrExt;
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("1.in"), "3\n4\n").unwrap();
+        fs::write(tests.path().join("1.out"), "7\n").unwrap();
+        fs::write(
+            tests
+                .path()
+                .join(std::ffi::OsStr::from_bytes(b"bad\xff.in")),
+            "",
+        )
+        .unwrap();
+        let cases = load_directory_cases(
+            tests.path().to_str().unwrap(),
+            &TestConfig::from_file("test-data/python-test-1.toml").unwrap(),
+            &mut RemoteCache::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            cases
                 .iter()
-                .map(|case| {
-                    let mut out_data = String::new();
-                    File::open(format!("{}/{}.out", dir, case))?.read_to_string(&mut out_data)?;
-                    Ok(out_data)
-                })
-                .collect::<Result<Vec<_>, Box<dyn Error + 'static>>>()?;
-            let test_data: HashMap<String, TestCase> = cases
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["1"]
+        );
+    }
+
+    #[test]
+    fn test_case_order() {
+        let tests = tempfile::tempdir().unwrap();
+        for case in ["d", "a", "f", "c", "e", "b"].iter() {
+            fs::write(tests.path().join(format!("{}.in", case)), "3\n4\n").unwrap();
+            fs::write(tests.path().join(format!("{}.out", case)), "7\n").unwrap();
+        }
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"order\"\ntests_dir = {:?}\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n",
+                tests.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let mut reported: HashMap<String, Vec<String>> = HashMap::new();
+        test_from_configuration_with_progress(&config, &mut |student, case, _| {
+            reported
+                .entry(String::from(student))
+                .or_default()
+                .push(String::from(case));
+        })
+        .unwrap();
+        assert_eq!(reported.len(), 3);
+        for cases in reported.values() {
+            assert_eq!(cases, &["a", "b", "c", "d", "e", "f"]);
+        }
+    }
+
+    #[test]
+    fn test_panicking_student() {
+        let mut config = TestConfig::from_file("test-data/python-test-1.toml").unwrap();
+        config
+            .options_mut()
+            .set_comparator(|_: &str, expected: &str, actual: &str| {
+                if actual == "PANIC\n" {
+                    panic!("Deliberate panic");
+                }
+                match expected == actual {
+                    true => TestAnswer::Success,
+                    false => TestAnswer::Failure,
+                }
+            });
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results["correct"]["1"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        assert_eq!(
+            results["wrong"]["2"].as_ref().unwrap(),
+            &TestAnswer::Failure
+        );
+        for case in ["1", "2"].iter() {
+            let error = results["panics"][*case].as_ref().unwrap_err();
+            assert!(error.to_string().contains("Deliberate panic"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_students() {
+        use std::os::unix::fs::symlink;
+
+        let submissions = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        fs::create_dir(submissions.path().join("real")).unwrap();
+        fs::create_dir(target.path().join("plain")).unwrap();
+        symlink(
+            submissions.path().join("real"),
+            target.path().join("linked"),
+        )
+        .unwrap();
+        symlink(target.path(), target.path().join("parent")).unwrap();
+        symlink(target.path().join("cycle"), target.path().join("cycle")).unwrap();
+        symlink(target.path().join("missing"), target.path().join("broken")).unwrap();
+        let target_dir = target.path().to_str().unwrap();
+        let names = |options: &RunOptions| -> Vec<String> {
+            discover_students(target_dir, options)
+                .unwrap()
                 .into_iter()
-                .zip(
-                    inputs
-                        .into_iter()
-                        .zip(outputs.into_iter())
-                        .map(|(input, output)| TestCase { input, output }),
-                )
-                .collect();
-            // Get the students and test against the cases
-            fs::read_dir(config.target_dir())?
-                .filter_map(|entry| {
-                    // Remove directories and file i/o errors
-                    let entry = entry.ok()?;
-                    match entry.file_type() {
-                        Ok(filetype) => {
-                            if filetype.is_dir() {
-                                Some(entry)
-                            } else {
-                                None
-                            }
-                        }
-                        Err(_) => None,
-                    }
-                })
-                .map(|student_dir| {
-                    // Now, let's test the students
-                    let student_path = student_dir.path();
-                    let student_path = student_path.to_str().expect("Error loading student folder");
-                    let student_name = String::from(
-                        student_dir
-                            .file_name()
-                            .to_str()
-                            .expect("Error parsing student folder name as utf-8"),
-                    );
-                    if !config.do_setup(student_path) {
-                        return Ok((
-                            student_name,
-                            test_data
-                                .keys()
-                                .map(|k| (k.clone(), Ok(TestAnswer::CompileError)))
-                                .collect(),
-                        ));
-                    }
-                    let env_vars = config.env_vars(student_path);
-                    let test_results = test_student_against_test_case(
-                        config.command(student_path),
-                        config.args(student_path),
-                        &env_vars,
-                        &test_data,
-                        *config.case_timeout(),
-                    );
-                    Ok((student_name, test_results))
-                })
+                .map(|(name, _)| name)
                 .collect()
+        };
+        let mut options = RunOptions::default();
+        assert_eq!(names(&options), vec!["linked", "plain"]);
+        options.set_follow_symlinks(false);
+        assert_eq!(names(&options), vec!["plain"]);
+    }
+
+    #[test]
+    fn test_missing_command() {
+        let config = TestConfig::from_toml_values(
+            "[python]\nname = \"Missing\"\ntests_dir = \"test-data/python-test-1/tests\"\nversion = \"stipulate-missing-python\"\nfile = \"main.py\"\ntarget_dir = \"test-data/python-test-1/students\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        let error = test_from_configuration(&config).unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("Command not found: stipulate-missing-python"));
+    }
+
+    #[test]
+    fn test_missing_command_checked_first() {
+        let student = tempfile::tempdir().unwrap();
+        fs::create_dir(student.path().join("a")).unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[command]\nname = \"Missing\"\ntests_dir = \"test-data/python-test-1/tests\"\ncommand = \"stipulate-missing-interpreter\"\nsetup = [\"touch\", \"{{student_dir}}/setup-ran\"]\ntarget_dir = {:?}\n",
+                student.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let mut reported = 0;
+        let error = test_from_configuration_with_progress(&config, &mut |_, _, _| reported += 1)
+            .unwrap_err();
+        assert!(error.is::<CommandNotFoundError<std::convert::Infallible>>());
+        assert!(error
+            .to_string()
+            .ends_with("Command not found: stipulate-missing-interpreter"));
+        // Nobody was set up or tested
+        assert_eq!(reported, 0);
+        assert!(!student.path().join("a/setup-ran").exists());
+    }
+
+    #[test]
+    fn test_skipped_case_without_compiling() {
+        let tests = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("a.in"), "x\n").unwrap();
+        fs::write(tests.path().join("a.out"), "x\n").unwrap();
+        fs::write(tests.path().join("b.in"), "y\n").unwrap();
+        fs::write(tests.path().join("b.out"), "y\n").unwrap();
+        fs::write(tests.path().join("b.skip"), "").unwrap();
+        let students = tempfile::tempdir().unwrap();
+        fs::create_dir(students.path().join("alice")).unwrap();
+        fs::write(students.path().join("alice/compiles"), "").unwrap();
+        fs::create_dir(students.path().join("bob")).unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[command]\nname = \"Skips\"\ntests_dir = {:?}\ncommand = \"cat\"\nsetup = [\"test\", \"-e\", \"{{student_dir}}/compiles\"]\ntarget_dir = {:?}\n\n[grade_scale]\n50 = \"P\"\n0 = \"F\"\n",
+                tests.path().to_str().unwrap(),
+                students.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["bob"]["a"].as_ref().unwrap(),
+            &TestAnswer::CompileError(String::new())
+        );
+        // The skipped case doesn't count towards bob's total any more
+        // than alice's
+        assert_eq!(results["bob"]["b"].as_ref().unwrap(), &TestAnswer::Skipped);
+        let output = |mode: &str| {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            let path = file.path().to_str().unwrap();
+            let mut writer: Box<dyn crate::output::OutputMode> = match mode {
+                "grades" => Box::new(crate::output::GradeOutput::with_output(
+                    fs::File::create(path).unwrap(),
+                    config.grade_scale().unwrap().clone(),
+                )),
+                _ => crate::output::get_output_mode_for_file(mode, path, false)
+                    .unwrap()
+                    .unwrap(),
+            };
+            writer.output_class_results(&results).unwrap();
+            drop(writer);
+            fs::read_to_string(path).unwrap()
+        };
+        let csv = output("csv");
+        assert!(csv.contains("\nalice,1,1,1,1,"), "{}", csv);
+        assert!(csv.contains("\nbob,0,1,0,1,,C,s\n"), "{}", csv);
+        let grades = output("grades");
+        assert!(grades.contains("alice,1,1,100.0,P\n"), "{}", grades);
+        assert!(grades.contains("bob,0,1,0.0,F\n"), "{}", grades);
+        #[cfg(feature = "table-output")]
+        {
+            let table = output("print");
+            let bob = table.lines().find(|line| line.contains("bob")).unwrap();
+            assert!(bob.contains("| 0 ") && bob.contains("| 1 "), "{}", table);
+            assert!(!bob.contains("| 2 "), "{}", table);
+        }
+    }
+
+    #[test]
+    fn test_missing_compiler_checked_first() {
+        let student = tempfile::tempdir().unwrap();
+        fs::create_dir(student.path().join("a")).unwrap();
+        fs::write(
+            student.path().join("a/main.c"),
+            "int main() { return 0; }\n",
+        )
+        .unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[c]\nname = \"Missing\"\ntests_dir = \"test-data/python-test-1/tests\"\ncompiler = \"stipulate-missing-compiler\"\ntarget_dir = {:?}\n",
+                student.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let mut reported = 0;
+        let error = test_from_configuration_with_progress(&config, &mut |_, _, _| reported += 1)
+            .unwrap_err();
+        assert!(error.is::<CommandNotFoundError<std::convert::Infallible>>());
+        assert!(error
+            .to_string()
+            .ends_with("Command not found: stipulate-missing-compiler"));
+        assert_eq!(reported, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_missing_student_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tests = tempfile::tempdir().unwrap();
+        let students = tempfile::tempdir().unwrap();
+        fs::write(tests.path().join("a.in"), "").unwrap();
+        fs::write(tests.path().join("a.out"), "hi\n").unwrap();
+        fs::create_dir(students.path().join("alice")).unwrap();
+        fs::create_dir(students.path().join("bob")).unwrap();
+        let script = students.path().join("alice/run.sh");
+        fs::write(&script, "#!/bin/sh\necho hi\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let config = TestConfig::from_toml_values(
+            format!(
+                "[command]\nname = \"Binary\"\ntests_dir = {:?}\ncommand = \"{{student_dir}}/run.sh\"\ntarget_dir = {:?}\n",
+                tests.path().to_str().unwrap(),
+                students.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        // Only bob's results say that his binary is missing
+        let results = test_from_configuration(&config).unwrap();
+        assert_eq!(
+            results["alice"]["a"].as_ref().unwrap(),
+            &TestAnswer::Success
+        );
+        let error = results["bob"]["a"].as_ref().unwrap_err();
+        assert!(!error.is::<CommandNotFoundError<std::convert::Infallible>>());
+        assert!(error.to_string().contains("bob/run.sh"));
+    }
+
+    #[test]
+    fn test_ignored_folders() {
+        let target = tempfile::tempdir().unwrap();
+        for student in [".git", "__MACOSX", "solutions", ".keep", "jones", "smith"].iter() {
+            fs::create_dir(target.path().join(student)).unwrap();
+            fs::write(
+                target.path().join(student).join("main.py"),
+                "print(int(input()) + int(input()))\n",
+            )
+            .unwrap();
+        }
+        fs::write(
+            target.path().join(IGNORE_FILE),
+            "# Not students\n__MACOSX\n/solutions/\n\n!.keep\n",
+        )
+        .unwrap();
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[python]\nname = \"ignored\"\ntests_dir = \"test-data/python-test-1/tests\"\nfile = \"main.py\"\ntarget_dir = {:?}\n",
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            test_from_configuration(&config)
+                .unwrap()
+                .keys()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![".keep", "jones", "smith"]
+        );
+        // Without an ignore file, only dotfolders are ignored
+        fs::remove_file(target.path().join(IGNORE_FILE)).unwrap();
+        assert_eq!(
+            test_from_configuration(&config)
+                .unwrap()
+                .keys()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec!["__MACOSX", "jones", "smith", "solutions"]
+        );
+        // Everything is a student when ignoring is turned off
+        config.options_mut().set_ignore_folders(false);
+        assert_eq!(test_from_configuration(&config).unwrap().len(), 6);
+        // An invalid pattern is an error
+        config.options_mut().set_ignore_folders(true);
+        fs::write(target.path().join(IGNORE_FILE), "[unclosed\n").unwrap();
+        assert!(test_from_configuration(&config)
+            .unwrap_err()
+            .is::<IgnoreFileError<std::convert::Infallible>>());
+    }
+
+    #[test]
+    fn test_retry_timeouts() {
+        // Times out on the first `slow_runs` runs, then passes
+        let mock = |slow_runs: usize| {
+            let mut runs = 0;
+            move || {
+                runs += 1;
+                Ok(match runs <= slow_runs {
+                    true => TestAnswer::Timeout.into(),
+                    false => TestAnswer::Success.into(),
+                })
+            }
+        };
+        let mut retries = Vec::new();
+        let result = retry_timeouts(3, mock(2), |attempt| retries.push(attempt));
+        assert_eq!(result.unwrap(), TestAnswer::Success);
+        assert_eq!(retries, vec![1, 2]);
+        // It gives up once it's out of retries
+        let mut retries = Vec::new();
+        let result = retry_timeouts(2, mock(5), |attempt| retries.push(attempt));
+        assert_eq!(result.unwrap(), TestAnswer::Timeout);
+        assert_eq!(retries, vec![1, 2]);
+        // Without retries, it only runs once
+        assert_eq!(
+            retry_timeouts(0, mock(1), |_| panic!("Retried")).unwrap(),
+            TestAnswer::Timeout
+        );
+        // Other results aren't retried
+        assert_eq!(
+            retry_timeouts(3, || Ok(TestAnswer::Failure.into()), |_| panic!("Retried")).unwrap(),
+            TestAnswer::Failure
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_retries() {
+        let target = tempfile::tempdir().unwrap();
+        fs::create_dir(target.path().join("a")).unwrap();
+        // Slow the first time each case is run, and fast afterwards
+        let mut config = TestConfig::from_toml_values(
+            format!(
+                "[command]\nname = \"Retries\"\ntests_dir = \"test-data/python-test-1/tests\"\ncommand = \"sh\"\nargs = [\"-c\", \"read a; read b; if [ ! -e ran-$a-$b ]; then touch ran-$a-$b; sleep 5; fi; echo $((a + b))\"]\ntarget_dir = {:?}\ntimeout = 0.5\n",
+                target.path().to_str().unwrap()
+            )
+            .parse()
+            .unwrap(),
+        )
+        .unwrap();
+        let results = test_from_configuration(&config).unwrap();
+        assert!(results["a"]
+            .values()
+            .all(|result| matches!(result.as_deref(), Ok(TestAnswer::Timeout))));
+        for entry in fs::read_dir(target.path().join("a")).unwrap() {
+            fs::remove_file(entry.unwrap().path()).unwrap();
         }
+        config.options_mut().set_timeout_retries(1);
+        let results = test_from_configuration(&config).unwrap();
+        assert!(results["a"]
+            .values()
+            .all(|result| matches!(result.as_deref(), Ok(TestAnswer::Success))));
     }
 }
@@ -1,20 +1,22 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::io::{Read, Write};
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use errormake::errormake;
+use log::{debug, warn};
 
-use wait_timeout::ChildExt;
+use super::super::conf::{CompareStream, InputVia, RunOptions};
+use super::compare::judge_output;
 
-/// Reads from an input stream until the input stream ends, and returns
-/// the results in a `String`.
-fn read_from_stream<T: Read>(stream: &mut T) -> Result<String, Box<dyn Error + 'static>> {
-    let mut data = Vec::new();
-    stream.read_to_end(&mut data)?;
-    Ok(String::from_utf8(data)?)
-}
+use wait_timeout::ChildExt;
 
 /// An enum which contains the possible results of running a Test on a
 /// student's code. Note that this only has options for if the test
@@ -32,83 +34,861 @@ pub enum TestAnswer {
     /// information, which can be given to the student.
     FailWithMessage(String),
     /// The setup commands, when run, exitted with nonzero status
-    /// (likely indicating a compile error).
-    CompileError,
+    /// (likely indicating a compile error). This contains what went
+    /// wrong (such as the compiler's error messages), which can be given
+    /// to the student.
+    CompileError(String),
+    /// The program gave different output when it was run twice on the
+    /// same input (only checked if that was asked for).
+    Nondeterministic,
+    /// The test wasn't run, because the case is marked to be skipped.
+    /// Skipped cases don't count towards the number of cases passed or
+    /// the total number of cases.
+    Skipped,
+    /// The submission was missing a file which is needed to run it
+    /// (see `Config::required_files`). This contains a `String` saying
+    /// which.
+    NoSubmission(String),
+}
+
+/// The result of running a student's code on a single test case.
+///
+/// This dereferences to the `TestAnswer` for the case, and it can also
+/// carry the output that the student's code produced, if capturing was
+/// requested when running the test, the description of the case, if
+/// it has one, the number of points the case is worth, if that's known,
+/// whether the case's details are hidden from students, and how much
+/// memory and time the student's code used, if they're known.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CaseResult {
+    answer: TestAnswer,
+    output: Option<String>,
+    description: Option<String>,
+    points: Option<f64>,
+    hidden: bool,
+    peak_memory_kb: Option<u64>,
+    duration: Option<Duration>,
+}
+impl CaseResult {
+    /// Attaches the captured output of the student's code to this result
+    pub fn with_output(mut self, output: String) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Attaches the description of the case to this result
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Attaches the number of points the case is worth to this result
+    pub fn with_points(mut self, points: f64) -> Self {
+        self.points = Some(points);
+        self
+    }
+
+    /// Sets whether the case's details are hidden from students
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Attaches the peak memory use (resident set size, in kilobytes) of
+    /// the student's code to this result
+    pub fn with_peak_memory_kb(mut self, peak_memory_kb: u64) -> Self {
+        self.peak_memory_kb = Some(peak_memory_kb);
+        self
+    }
+
+    /// Attaches how long the student's code took to run (in wall-clock
+    /// time) to this result
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Replaces the answer in this result with the result of applying
+    /// `f` to it, keeping any captured output and description
+    pub fn map_answer<F: FnOnce(TestAnswer) -> TestAnswer>(self, f: F) -> Self {
+        CaseResult {
+            answer: f(self.answer),
+            ..self
+        }
+    }
+
+    /// Returns the answer for this case
+    pub fn answer(&self) -> &TestAnswer {
+        &self.answer
+    }
+
+    /// Returns the output captured from the student's code, if it was
+    /// captured
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    /// Returns the description of the case, if it has one
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns the number of points the case is worth, if it's known
+    /// (see `output::case_weight` for the points to use otherwise)
+    pub fn points(&self) -> Option<f64> {
+        self.points
+    }
+
+    /// Returns whether the case's details are hidden from students (see
+    /// `TestCase::is_hidden`)
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns the peak memory use (resident set size, in kilobytes) of
+    /// the student's code, if it was measured. It's only measured on
+    /// unix, and not for cases which timed out.
+    pub fn peak_memory_kb(&self) -> Option<u64> {
+        self.peak_memory_kb
+    }
+
+    /// Returns how long the student's code took to run (in wall-clock
+    /// time), if it was measured. For cases which timed out, this is how
+    /// long it ran before it was stopped.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+impl From<TestAnswer> for CaseResult {
+    fn from(answer: TestAnswer) -> Self {
+        CaseResult {
+            answer,
+            output: None,
+            description: None,
+            points: None,
+            hidden: false,
+            peak_memory_kb: None,
+            duration: None,
+        }
+    }
+}
+impl Deref for CaseResult {
+    type Target = TestAnswer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.answer
+    }
+}
+impl PartialEq<TestAnswer> for CaseResult {
+    fn eq(&self, other: &TestAnswer) -> bool {
+        self.answer == *other
+    }
+}
+
+/// The PATH given to student code when the environment is cleared
+#[cfg(target_family = "windows")]
+const CLEAN_PATH: &str = r"C:\Windows\system32;C:\Windows";
+#[cfg(not(target_family = "windows"))]
+const CLEAN_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// The placeholder which is replaced with the path to the file holding
+/// a case's input, when input is given through a file
+const INPUT_FILE_PLACEHOLDER: &str = "{input_file}";
+
+/// Replaces each `{input_file}` in the arguments with the path to the
+/// input file, or adds the path as the last argument if none of them
+/// have the placeholder
+fn input_file_args(args: &[String], path: &str) -> Vec<String> {
+    match args.iter().any(|arg| arg.contains(INPUT_FILE_PLACEHOLDER)) {
+        true => args
+            .iter()
+            .map(|arg| arg.replace(INPUT_FILE_PLACEHOLDER, path))
+            .collect(),
+        false => args
+            .iter()
+            .cloned()
+            .chain(std::iter::once(String::from(path)))
+            .collect(),
+    }
+}
+
+/// Returns the program to launch and the arguments to give it, putting
+/// the run wrapper (if there is one) in front of the command.
+fn wrap_command<'a>(
+    cmd: &'a str,
+    args: &'a [String],
+    wrapper: &'a [String],
+) -> (&'a str, Vec<&'a str>) {
+    let args = args.iter().map(String::as_str);
+    match wrapper.split_first() {
+        Some((program, wrapper_args)) => (
+            program,
+            wrapper_args
+                .iter()
+                .map(String::as_str)
+                .chain(std::iter::once(cmd))
+                .chain(args)
+                .collect(),
+        ),
+        None => (cmd, args.collect()),
+    }
 }
 
-/// Runs the given command with the given args, and passes the given
-/// argument as input through standard input. It returns true iff the
-/// command's output matches `expected_output`.
+/// Checks that the program which student code is launched with (the run
+/// wrapper in `options`, if there is one, and otherwise `cmd`) can be
+/// found on the PATH it will be run with, so that a missing interpreter
+/// can be reported once, before anyone is tested. If it can't be found,
+/// the error is a `CommandNotFoundError`, the same as spawning it gives.
 ///
-/// If timeout is None, then it will wait for the child to finish.
-/// Otherwise, it will only wait the specified amount of time.
+/// A program which is given as a path, rather than just a name (such as
+/// a binary compiled into the student's directory), isn't checked, since
+/// it may not exist until the setup has been done.
+pub fn check_command_exists(cmd: &str, options: &RunOptions) -> Result<(), Box<dyn Error>> {
+    let (program, _) = wrap_command(cmd, &[], options.run_wrapper());
+    let path = match options.clean_env() {
+        true => Some(CLEAN_PATH.into()),
+        false => std::env::var_os("PATH"),
+    };
+    check_program_on_path(program, path)
+}
+
+/// Checks that a program which setup runs (see `Config::setup_programs`)
+/// can be found on stipulate's own PATH, which setup is run with, the
+/// same way as `check_command_exists`.
+pub fn check_setup_program_exists(program: &str) -> Result<(), Box<dyn Error>> {
+    check_program_on_path(program, std::env::var_os("PATH"))
+}
+
+/// Checks that the program can be found on the given PATH, if it's just
+/// a name, and returns a `CommandNotFoundError` if it can't
+fn check_program_on_path(program: &str, path: Option<OsString>) -> Result<(), Box<dyn Error>> {
+    if !is_bare_name(program) {
+        return Ok(());
+    }
+    let found = path
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| is_program(dir.join(program))));
+    match found {
+        true => Ok(()),
+        false => Err(CommandNotFoundError::with_description(format!(
+            "Command not found: {}",
+            program
+        ))
+        .into()),
+    }
+}
+
+/// Whether the program is just a name, which is looked up on the PATH,
+/// rather than a path to it
+fn is_bare_name(program: &str) -> bool {
+    Path::new(program).components().count() == 1
+}
+
+/// Whether there's a program which can be run at the given path
+#[cfg(unix)]
+fn is_program(path: PathBuf) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// Whether there's a program which can be run at the given path (which
+/// may leave out the ".exe")
+#[cfg(not(unix))]
+fn is_program(path: PathBuf) -> bool {
+    let mut with_exe = path.clone().into_os_string();
+    with_exe.push(".exe");
+    path.is_file() || Path::new(&with_exe).is_file()
+}
+
+/// Runs the given command with the given args, and judges its output
+/// against `expected_output`.
 ///
-/// It returns true if it matches, false if it doesn't match, and Err
-/// if it encountered an error trying to evaluate it (with an &str
-/// explaining the error caused).
+/// The command is launched through the run wrapper in `options`, if
+/// there is one, in `working_dir` (or in stipulate's own working
+/// directory, if it's None), with `env_vars` set, and it's logged at the
+/// debug level before it's spawned. If the options ask for a clean
+/// environment, it only gets `env_vars` and a minimal PATH. The input
+/// is given through standard input, or through a temporary file if the
+/// options say to (see `input_file_args`). If timeout is None, then it
+/// will wait for the child to finish. Otherwise, it will only wait the
+/// specified amount of time.
 ///
-/// For now, it assumes that the child process sends valid UTF-8 out.
-/// If it doesn't, then this function will error.
+/// If the options ask to detect nondeterminism, the command is run a
+/// second time, and if its output is different, the result is
+/// `TestAnswer::Nondeterministic`. Then, if the options have an expected
+/// exit code and the command exited some other way, the result is a
+/// `TestAnswer::FailWithMessage` saying so. Otherwise, the stream the
+/// options compare (standard output by default, or standard error) is
+/// judged against `expected_output` by the comparator in `options`,
+/// after the masks are applied (see `judge_output`). When both are
+/// compared, standard error is then judged against `expected_error`.
+///
+/// How long the command ran (even if it timed out), its peak memory use
+/// (on unix), and, if the options ask to capture output, what it printed
+/// to the compared streams are attached to the result, all from the
+/// first run.
+///
+/// It returns Err if it encountered an error trying to evaluate the
+/// command, which is a `CommandNotFoundError` if the command is just a
+/// name which can't be found on the PATH. For now, it assumes that the
+/// child process sends valid UTF-8 out. If it doesn't, then this
+/// function will error.
+#[allow(clippy::too_many_arguments)]
 pub fn test_output_against_strings(
     cmd: &str,
     args: &[String],
     env_vars: &HashMap<String, String>,
+    working_dir: Option<&Path>,
     input: &str,
     expected_output: &str,
+    expected_error: &str,
     timeout: Option<Duration>,
-) -> Result<TestAnswer, Box<dyn Error + 'static>> {
-    let mut child = Command::new(cmd)
-        .args(args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .envs(env_vars)
-        .spawn()?;
-    child
-        .stdin
-        .as_mut()
-        .ok_or_else(|| {
-            ChildProcessIOError::with_description(String::from("Error grabbing child stdin"))
-        })?
-        .write_all(input.as_bytes())?;
-    match timeout {
-        Some(delay) => match child.wait_timeout(delay) {
-            Ok(Some(code)) => Ok(code),
-            Ok(None) => {
-                let _ = child.kill();
-                if let Err(e) = child.wait() {
-                    println!("Error reaping child process: {}", e);
-                };
-                return Ok(TestAnswer::Timeout);
+    options: &RunOptions,
+) -> Result<CaseResult, Box<dyn Error + 'static>> {
+    let (child_output, child_error, exit_status, peak_memory_kb, duration) =
+        match run_command(cmd, args, env_vars, working_dir, input, timeout, options)? {
+            Ok(output) => output,
+            Err(duration) => {
+                return Ok(CaseResult::from(TestAnswer::Timeout).with_duration(duration))
             }
-            Err(e) => Err(e),
+        };
+    let finish = |result: CaseResult| {
+        let result = result.with_duration(duration);
+        let result = match peak_memory_kb {
+            Some(peak_memory_kb) => result.with_peak_memory_kb(peak_memory_kb),
+            None => result,
+        };
+        match (options.capture_output(), options.compare_stream()) {
+            (false, _) => result,
+            (true, CompareStream::Stdout) => result.with_output(child_output.clone()),
+            (true, CompareStream::Stderr) => result.with_output(child_error.clone()),
+            (true, CompareStream::Both) => result.with_output(child_output.clone() + &child_error),
+        }
+    };
+    if options.detect_nondeterminism() {
+        match run_command(cmd, args, env_vars, working_dir, input, timeout, options)? {
+            Ok((second_output, second_error, ..))
+                if second_output == child_output && second_error == child_error => {}
+            Ok(_) => return Ok(finish(TestAnswer::Nondeterministic.into())),
+            Err(_) => return Ok(finish(TestAnswer::Timeout.into())),
+        }
+    }
+    if let Some(expected) = options.expected_exit_code() {
+        if exit_status.code() != Some(expected) {
+            let message = match exit_status.code() {
+                Some(code) => format!("Expected exit code {}, got {}", expected, code),
+                None => format!(
+                    "Expected exit code {}, but it ended with {}",
+                    expected, exit_status
+                ),
+            };
+            return Ok(finish(TestAnswer::FailWithMessage(message).into()));
+        }
+    }
+    let judge = |expected: &str, actual: &str| {
+        judge_output(
+            options.comparator(),
+            options.masks(),
+            input,
+            expected,
+            actual,
+        )
+    };
+    let answer = match options.compare_stream() {
+        CompareStream::Stdout => judge(expected_output, &child_output),
+        CompareStream::Stderr => judge(expected_output, &child_error),
+        CompareStream::Both => match judge(expected_output, &child_output) {
+            TestAnswer::Success => match judge(expected_error, &child_error) {
+                TestAnswer::Failure => TestAnswer::FailWithMessage(String::from(
+                    "Standard error didn't match the expected output",
+                )),
+                TestAnswer::FailWithMessage(message) => {
+                    TestAnswer::FailWithMessage(format!("On standard error: {}", message))
+                }
+                answer => answer,
+            },
+            answer => answer,
         },
-        None => child.wait(),
-    }?;
-    let child_output = read_from_stream(child.stdout.as_mut().ok_or_else(|| {
+    };
+    Ok(finish(answer.into()))
+}
+
+/// What a command printed to standard output and standard error (which
+/// is empty unless the options compare it), how it exited, its peak
+/// memory use in kilobytes (if that's known), and how long it ran for
+type CommandOutput = (String, String, ExitStatus, Option<u64>, Duration);
+
+/// Runs the given command (through the run wrapper in `options`, if
+/// there is one) with the given input, and returns its output, its exit
+/// status, its peak memory use, and how long it ran for (from when it
+/// was spawned until it exited). If it timed out, the inner result is
+/// an error holding how long it ran for before it was stopped.
+///
+/// Standard error is only read if the options compare it. Otherwise,
+/// it's inherited, so it shows up in the terminal.
+///
+/// If the options say to give input through a file, the input is
+/// written to a temporary file, whose path is put in the arguments, and
+/// standard input is left empty. The file is deleted when this returns.
+fn run_command(
+    cmd: &str,
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+    working_dir: Option<&Path>,
+    input: &str,
+    timeout: Option<Duration>,
+    options: &RunOptions,
+) -> Result<Result<CommandOutput, Duration>, Box<dyn Error + 'static>> {
+    let input_file = match options.input_via() {
+        InputVia::Stdin => None,
+        InputVia::File => {
+            let mut file = tempfile::NamedTempFile::new()?;
+            file.write_all(input.as_bytes())?;
+            Some(file)
+        }
+    };
+    let file_args;
+    let (args, input) = match &input_file {
+        Some(file) => {
+            let path = file.path().to_str().ok_or_else(|| {
+                ChildProcessIOError::with_description(String::from(
+                    "The input file's path isn't valid UTF-8",
+                ))
+            })?;
+            file_args = input_file_args(args, path);
+            (file_args.as_slice(), "")
+        }
+        None => (args, input),
+    };
+    let (cmd, args) = wrap_command(cmd, args, options.run_wrapper());
+    debug!(
+        "Spawning {:?} with args {:?} in {:?} with env overrides {:?}",
+        cmd,
+        args,
+        working_dir
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+        env_vars
+    );
+    let mut command = Command::new(cmd);
+    if options.clean_env() {
+        command.env_clear().env("PATH", CLEAN_PATH);
+    }
+    if let Some(working_dir) = working_dir {
+        command.current_dir(working_dir);
+    }
+    command
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .envs(env_vars);
+    let read_stderr = options.compare_stream() != CompareStream::Stdout;
+    if read_stderr {
+        command.stderr(Stdio::piped());
+    }
+    #[cfg(unix)]
+    if let Some(nice) = options.nice() {
+        set_nice_on_exec(&mut command, nice);
+    }
+    let start = Instant::now();
+    let mut child = command.spawn().map_err(|e| -> Box<dyn Error + 'static> {
+        // Only a program looked up on the PATH is missing for everyone.
+        // A missing path (such as a binary in the student's directory)
+        // or working directory is only a problem for this student.
+        let missing_dir = working_dir.is_some_and(|dir| !dir.is_dir());
+        match e.kind() {
+            io::ErrorKind::NotFound if is_bare_name(cmd) && !missing_dir => Box::new(
+                CommandNotFoundError::with_description(format!("Command not found: {}", cmd)),
+            ),
+            io::ErrorKind::NotFound => Box::new(ChildProcessIOError::with_description(format!(
+                "Couldn't run {}: {}",
+                cmd, e
+            ))),
+            _ => Box::new(e),
+        }
+    })?;
+    let mut stdin = child.stdin.take().ok_or_else(|| {
+        ChildProcessIOError::with_description(String::from("Error grabbing child stdin"))
+    })?;
+    let mut stdout = child.stdout.take().ok_or_else(|| {
         ChildProcessIOError::with_description(String::from("Error grabbing child stdout"))
-    })?)?;
-    Ok(match child_output == expected_output {
-        true => TestAnswer::Success,
-        false => TestAnswer::Failure,
-    })
+    })?;
+    let stderr = match read_stderr {
+        true => Some(child.stderr.take().ok_or_else(|| {
+            ChildProcessIOError::with_description(String::from("Error grabbing child stderr"))
+        })?),
+        false => None,
+    };
+    // Write the input and read the output on their own threads, so that
+    // code which prints a lot before it's read all of its input can't
+    // block on a full pipe while we're blocked on another
+    let input = String::from(input);
+    let writer = thread::spawn(move || match stdin.write_all(input.as_bytes()) {
+        // Code which exits without reading all of its input is fine
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    });
+    let reader = thread::spawn(move || {
+        let mut data = Vec::new();
+        stdout.read_to_end(&mut data).map(|_| data)
+    });
+    let error_reader = stderr.map(|mut stderr| {
+        thread::spawn(move || {
+            let mut data = Vec::new();
+            stderr.read_to_end(&mut data).map(|_| data)
+        })
+    });
+    let (exit_status, peak_memory_kb) = match wait_for_child(&mut child, timeout)? {
+        Some(finished) => finished,
+        None => {
+            // The threads aren't waited for, since anything the code
+            // started which still has the pipes open could keep them
+            // going, and they finish on their own when the pipes close
+            let duration = start.elapsed();
+            stop_child(&mut child, options.kill_grace_period());
+            return Ok(Err(duration));
+        }
+    };
+    let duration = start.elapsed();
+    let join_error =
+        || ChildProcessIOError::with_description(String::from("Panicked during child process I/O"));
+    writer.join().map_err(|_| join_error())??;
+    let child_output = String::from_utf8(reader.join().map_err(|_| join_error())??)?;
+    let child_error = match error_reader {
+        Some(error_reader) => String::from_utf8(error_reader.join().map_err(|_| join_error())??)?,
+        None => String::new(),
+    };
+    Ok(Ok((
+        child_output,
+        child_error,
+        exit_status,
+        peak_memory_kb,
+        duration,
+    )))
+}
+
+/// Waits for the child to exit (for at most `timeout`, if it's given),
+/// and reaps it.
+///
+/// It returns None if the child timed out (in which case it's still
+/// running). Otherwise, it returns the child's exit status and its peak
+/// memory use, in kilobytes, which is read from `wait4`.
+#[cfg(unix)]
+fn wait_for_child(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> io::Result<Option<(ExitStatus, Option<u64>)>> {
+    // Close stdin, as `Child::wait` does, so the child sees the end of
+    // its input
+    drop(child.stdin.take());
+    let pid = child.id() as libc::pid_t;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // There's no way to wait with a timeout and still get the child's
+    // resource usage, so this polls (more slowly as time goes on)
+    let mut poll_interval = Duration::from_micros(100);
+    loop {
+        let mut status = 0;
+        // Safe because rusage is plain data, for which zero is valid
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let flags = match deadline {
+            Some(_) => libc::WNOHANG,
+            None => 0,
+        };
+        // Safe because the child hasn't been reaped yet, so its pid
+        // can't have been reused
+        match unsafe { libc::wait4(pid, &mut status, flags, &mut usage) } {
+            -1 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+            0 => {}
+            _ => {
+                return Ok(Some((
+                    ExitStatus::from_raw(status),
+                    Some(max_rss_kb(&usage)),
+                )))
+            }
+        }
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(poll_interval.min(deadline - now));
+            poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Waits for the child to exit (for at most `timeout`, if it's given),
+/// and reaps it.
+///
+/// It returns None if the child timed out (in which case it's still
+/// running). Otherwise, it returns the child's exit status. Peak memory
+/// use isn't measured on this platform.
+#[cfg(not(unix))]
+fn wait_for_child(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> io::Result<Option<(ExitStatus, Option<u64>)>> {
+    match timeout {
+        Some(delay) => Ok(child.wait_timeout(delay)?.map(|status| (status, None))),
+        None => child.wait().map(|status| Some((status, None))),
+    }
+}
+
+/// The longest to wait between checks of whether a child has exited
+#[cfg(unix)]
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Returns the peak resident set size in the given usage, in kilobytes
+#[cfg(unix)]
+fn max_rss_kb(usage: &libc::rusage) -> u64 {
+    // macOS gives this in bytes, and everywhere else gives kilobytes
+    match cfg!(target_os = "macos") {
+        true => usage.ru_maxrss as u64 / 1024,
+        false => usage.ru_maxrss as u64,
+    }
+}
+
+/// Stops a child process which timed out, and reaps it.
+///
+/// If there's a grace period (and this is unix), the child is first
+/// sent SIGTERM, so it can clean up, and it's only killed if it hasn't
+/// exited by the end of the grace period. Otherwise, it's killed right
+/// away.
+fn stop_child(child: &mut Child, grace_period: Option<Duration>) {
+    #[cfg(unix)]
+    if let Some(grace_period) = grace_period {
+        // Safe because the child hasn't been reaped yet, so its pid
+        // can't have been reused
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+        }
+        if let Ok(Some(_)) = child.wait_timeout(grace_period) {
+            return;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace_period;
+    let _ = child.kill();
+    if let Err(e) = child.wait() {
+        warn!("Error reaping child process: {}", e);
+    };
+}
+
+/// Makes the command set its niceness to the given value after it's
+/// forked, before it starts running.
+#[cfg(unix)]
+fn set_nice_on_exec(command: &mut Command, nice: i32) {
+    // Safe because `setpriority` is async-signal-safe and nothing is
+    // allocated in the closure.
+    unsafe {
+        command.pre_exec(
+            move || match libc::setpriority(libc::PRIO_PROCESS, 0, nice) {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            },
+        );
+    }
 }
 
 errormake!(#[doc="An error occured in child process I/O"] pub ChildProcessIOError);
+errormake!(#[doc="The command to run could not be found"] pub CommandNotFoundError);
 
 #[cfg(test)]
 mod tests {
+    use super::super::Mask;
     use super::*;
 
+    use std::sync::{Mutex, Once};
+
+    /// A logger which keeps every message logged, for inspection
+    struct TestLogger {
+        messages: Mutex<Vec<String>>,
+    }
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.messages
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", record.level(), record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    lazy_static::lazy_static! {
+        static ref LOGGER: TestLogger = TestLogger {
+            messages: Mutex::new(Vec::new()),
+        };
+    }
+
+    /// Installs the test logger (if it wasn't already), and returns it
+    fn test_logger() -> &'static TestLogger {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&*LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+        &LOGGER
+    }
+
+    #[test]
+    fn test_spawn_is_logged() {
+        let logger = test_logger();
+        let mut env_vars = HashMap::new();
+        env_vars.insert(String::from("STIPULATE_LOG_TEST"), String::from("1"));
+        test_output_against_strings(
+            "echo",
+            &["logged-spawn-marker".to_string()],
+            &env_vars,
+            None,
+            "",
+            "",
+            "",
+            None,
+            &RunOptions::default(),
+        )
+        .unwrap();
+        let messages = logger.messages.lock().unwrap();
+        let message = messages
+            .iter()
+            .find(|m| m.contains("logged-spawn-marker"))
+            .expect("Spawn wasn't logged");
+        assert!(message.starts_with("DEBUG Spawning \"echo\""));
+        assert!(message.contains("STIPULATE_LOG_TEST"));
+    }
+
+    #[test]
+    fn test_run_wrapper() {
+        let wrapper = vec![String::from("env"), String::from("--")];
+        let args = vec![String::from("a b"), String::from("c")];
+        assert_eq!(
+            wrap_command("prog", &args, &wrapper),
+            ("env", vec!["--", "prog", "a b", "c"])
+        );
+        assert_eq!(wrap_command("prog", &args, &[]), ("prog", vec!["a b", "c"]));
+        let mut options = RunOptions::default();
+        options.set_run_wrapper(vec![String::from("echo"), String::from("--")]);
+        assert_eq!(
+            test_output_against_strings(
+                "prog",
+                &args,
+                &HashMap::new(),
+                None,
+                "",
+                "-- prog a b c\n",
+                "",
+                None,
+                &options
+            )
+            .unwrap(),
+            TestAnswer::Success
+        );
+    }
+
+    #[test]
+    fn test_detect_nondeterminism() {
+        let mut options = RunOptions::default();
+        options.set_detect_nondeterminism(true);
+        let varies = vec![String::from("-c"), String::from("date +%N")];
+        assert_eq!(
+            test_output_against_strings(
+                "sh",
+                &varies,
+                &HashMap::new(),
+                None,
+                "",
+                "",
+                "",
+                None,
+                &options
+            )
+            .unwrap(),
+            TestAnswer::Nondeterministic
+        );
+        assert_eq!(
+            test_output_against_strings(
+                "echo",
+                &["Hello, world".to_string()],
+                &HashMap::new(),
+                None,
+                "",
+                "Hello, world\n",
+                "",
+                None,
+                &options
+            )
+            .unwrap(),
+            TestAnswer::Success
+        );
+        // Without the option, it's just judged on the first run
+        let result = test_output_against_strings(
+            "sh",
+            &varies,
+            &HashMap::new(),
+            None,
+            "",
+            "",
+            "",
+            None,
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert!(matches!(*result, TestAnswer::FailWithMessage(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nondeterminism_second_run_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = RunOptions::default();
+        options.set_detect_nondeterminism(true);
+        options.set_capture_output(true);
+        // Only the second run is slow
+        let script = format!(
+            "if [ -e {0} ]; then sleep 5; else touch {0}; echo first; fi",
+            dir.path().join("ran").to_str().unwrap()
+        );
+        let result = test_output_against_strings(
+            "sh",
+            &[String::from("-c"), script],
+            &HashMap::new(),
+            None,
+            "",
+            "first\n",
+            "",
+            Some(Duration::from_millis(500)),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(result, TestAnswer::Timeout);
+        // The first run's details are kept
+        assert_eq!(result.output(), Some("first\n"));
+        assert!(result.duration().is_some());
+    }
+
     #[test]
     fn test_without_timeout() {
         assert_eq!(
             test_output_against_strings(
                 "echo",
-                &vec!["Hello, world".to_string()],
+                &["Hello, world".to_string()],
                 &HashMap::new(),
+                None,
                 "",
                 "Hello, world\n",
-                None
+                "",
+                None,
+                &RunOptions::default()
             )
             .unwrap(),
             TestAnswer::Success
@@ -116,14 +896,19 @@ mod tests {
         assert_eq!(
             test_output_against_strings(
                 "echo",
-                &vec!["Goodbye, world".to_string()],
+                &["Goodbye, world".to_string()],
                 &HashMap::new(),
+                None,
                 "",
                 "Hello, world\n",
-                None
+                "",
+                None,
+                &RunOptions::default()
             )
             .unwrap(),
-            TestAnswer::Failure
+            TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"Hello, world\", got \"Goodbye, world\""
+            ))
         );
     }
 
@@ -132,11 +917,14 @@ mod tests {
         assert_eq!(
             test_output_against_strings(
                 "echo",
-                &vec!["Hello, world".to_string()],
+                &["Hello, world".to_string()],
                 &HashMap::new(),
+                None,
                 "",
                 "Hello, world\n",
-                Some(Duration::new(1, 0))
+                "",
+                Some(Duration::new(1, 0)),
+                &RunOptions::default()
             )
             .unwrap(),
             TestAnswer::Success
@@ -144,26 +932,493 @@ mod tests {
         assert_eq!(
             test_output_against_strings(
                 "echo",
-                &vec!["Goodbye, world".to_string()],
+                &["Goodbye, world".to_string()],
                 &HashMap::new(),
+                None,
                 "",
                 "Hello, world\n",
-                Some(Duration::new(1, 0))
+                "",
+                Some(Duration::new(1, 0)),
+                &RunOptions::default()
             )
             .unwrap(),
-            TestAnswer::Failure
+            TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"Hello, world\", got \"Goodbye, world\""
+            ))
         );
+        let result = test_output_against_strings(
+            "sleep",
+            &["10".to_string()],
+            &HashMap::new(),
+            None,
+            "",
+            "Hello, world\n",
+            "",
+            Some(Duration::new(0, 100)),
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, TestAnswer::Timeout);
+    }
+
+    #[test]
+    fn test_capture_output() {
+        let mut options = RunOptions::default();
+        options.set_capture_output(true);
+        let result = test_output_against_strings(
+            "echo",
+            &["Goodbye, world".to_string()],
+            &HashMap::new(),
+            None,
+            "",
+            "Hello, world\n",
+            "",
+            None,
+            &options,
+        )
+        .unwrap();
         assert_eq!(
+            result,
+            TestAnswer::FailWithMessage(String::from(
+                "Line 1: expected \"Hello, world\", got \"Goodbye, world\""
+            ))
+        );
+        assert_eq!(result.output(), Some("Goodbye, world\n"));
+        let result = test_output_against_strings(
+            "echo",
+            &["Goodbye, world".to_string()],
+            &HashMap::new(),
+            None,
+            "",
+            "Hello, world\n",
+            "",
+            None,
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.output(), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        let result = test_output_against_strings(
+            "sleep",
+            &[String::from("0.2")],
+            &HashMap::new(),
+            None,
+            "",
+            "",
+            "",
+            Some(Duration::from_secs(10)),
+            &RunOptions::default(),
+        )
+        .unwrap();
+        let duration = result.duration().unwrap();
+        assert!(duration >= Duration::from_millis(200), "{:?}", duration);
+        assert!(duration < Duration::from_secs(10), "{:?}", duration);
+        // Cases which timed out are timed until they were stopped
+        let result = test_output_against_strings(
+            "sleep",
+            &[String::from("10")],
+            &HashMap::new(),
+            None,
+            "",
+            "",
+            "",
+            Some(Duration::from_millis(100)),
+            &RunOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result, TestAnswer::Timeout);
+        let duration = result.duration().unwrap();
+        assert!(duration >= Duration::from_millis(100), "{:?}", duration);
+        assert!(duration < Duration::from_secs(10), "{:?}", duration);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_code() {
+        let run = |args: &[&str], expected: &str, options: &RunOptions| {
+            let args: Vec<String> = args.iter().map(|arg| String::from(*arg)).collect();
             test_output_against_strings(
-                "sleep",
-                &vec!["10".to_string()],
+                "sh",
+                &args,
                 &HashMap::new(),
+                None,
                 "",
-                "Hello, world\n",
-                Some(Duration::new(0, 100))
+                expected,
+                "",
+                None,
+                options,
+            )
+            .unwrap()
+        };
+        let mut options = RunOptions::default();
+        assert_eq!(
+            run(&["-c", "echo done"], "done\n", &options),
+            TestAnswer::Success
+        );
+        // The right output doesn't help if it then crashes
+        assert_eq!(
+            run(&["-c", "echo done; exit 3"], "done\n", &options),
+            TestAnswer::FailWithMessage(String::from("Expected exit code 0, got 3"))
+        );
+        assert_eq!(
+            run(&["-c", "kill -9 $$"], "", &options),
+            TestAnswer::FailWithMessage(String::from(
+                "Expected exit code 0, but it ended with signal: 9 (SIGKILL)"
+            ))
+        );
+        options.set_expected_exit_code(Some(3));
+        assert_eq!(
+            run(&["-c", "echo done; exit 3"], "done\n", &options),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            run(&["-c", "echo done"], "done\n", &options),
+            TestAnswer::FailWithMessage(String::from("Expected exit code 3, got 0"))
+        );
+        options.set_expected_exit_code(None);
+        assert_eq!(
+            run(&["-c", "echo done; exit 3"], "done\n", &options),
+            TestAnswer::Success
+        );
+        assert_eq!(
+            run(&["-c", "echo oops; exit 3"], "done\n", &options),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"done\", got \"oops\""))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compare_stream() {
+        let args = vec![String::from("-c"), String::from("echo out; echo err >&2")];
+        let run = |expected: &str, expected_error: &str, options: &RunOptions| {
+            test_output_against_strings(
+                "sh",
+                &args,
+                &HashMap::new(),
+                None,
+                "",
+                expected,
+                expected_error,
+                None,
+                options,
+            )
+            .unwrap()
+        };
+        let mut options = RunOptions::default();
+        options.set_capture_output(true);
+        // Only stdout is compared by default, and stderr isn't captured
+        let result = run("out\n", "wrong\n", &options);
+        assert_eq!(result, TestAnswer::Success);
+        assert_eq!(result.output(), Some("out\n"));
+        options.set_compare_stream(CompareStream::Stderr);
+        let result = run("err\n", "", &options);
+        assert_eq!(result, TestAnswer::Success);
+        assert_eq!(result.output(), Some("err\n"));
+        assert_eq!(
+            run("out\n", "", &options),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"out\", got \"err\""))
+        );
+        options.set_compare_stream(CompareStream::Both);
+        let result = run("out\n", "err\n", &options);
+        assert_eq!(result, TestAnswer::Success);
+        assert_eq!(result.output(), Some("out\nerr\n"));
+        assert_eq!(
+            run("out\n", "", &options),
+            TestAnswer::FailWithMessage(String::from(
+                "On standard error: Line 1: expected \"\", got \"err\""
+            ))
+        );
+        assert_eq!(
+            run("wrong\n", "err\n", &options),
+            TestAnswer::FailWithMessage(String::from("Line 1: expected \"wrong\", got \"out\""))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_input_via_file() {
+        let run = |cmd: &str, args: &[&str], expected: &str, options: &RunOptions| {
+            test_output_against_strings(
+                cmd,
+                &args
+                    .iter()
+                    .map(|arg| String::from(*arg))
+                    .collect::<Vec<_>>(),
+                &HashMap::new(),
+                None,
+                "3\n4\n",
+                expected,
+                "",
+                None,
+                options,
+            )
+            .unwrap()
+        };
+        let mut options = RunOptions::default();
+        assert_eq!(run("cat", &[], "3\n4\n", &options), TestAnswer::Success);
+        options.set_input_via(InputVia::File);
+        // The path is the last argument, and standard input is empty
+        assert_eq!(run("cat", &[], "3\n4\n", &options), TestAnswer::Success);
+        assert_eq!(
+            run("sh", &["-c", "cat; cat \"$0\""], "3\n4\n", &options),
+            TestAnswer::Success
+        );
+        // Or it replaces each placeholder, instead of being added
+        assert_eq!(
+            run(
+                "sh",
+                &["-c", "cat {input_file} {input_file}"],
+                "3\n4\n3\n4\n",
+                &options
+            ),
+            TestAnswer::Success
+        );
+        // The file is gone afterwards
+        options.set_capture_output(true);
+        let result = run("sh", &["-c", "echo \"$0\""], "", &options);
+        let path = result.output().unwrap().trim_end();
+        assert!(!path.is_empty());
+        assert!(!Path::new(path).exists());
+    }
+
+    #[test]
+    fn test_check_command_exists() {
+        let mut options = RunOptions::default();
+        assert!(check_command_exists("sh", &options).is_ok());
+        let error = check_command_exists("stipulate-missing-command", &options).unwrap_err();
+        assert!(error.is::<CommandNotFoundError<std::convert::Infallible>>());
+        // Paths (such as a binary which setup makes) aren't checked
+        assert!(check_command_exists("./stipulate-missing-command", &options).is_ok());
+        // With a run wrapper, it's the wrapper which has to exist
+        options.set_run_wrapper(vec![String::from("stipulate-missing-wrapper")]);
+        let error = check_command_exists("sh", &options).unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("Command not found: stipulate-missing-wrapper"));
+    }
+
+    #[test]
+    fn test_command_not_found() {
+        let error = test_output_against_strings(
+            "stipulate-nonexistent-command",
+            &[],
+            &HashMap::new(),
+            None,
+            "",
+            "",
+            "",
+            None,
+            &RunOptions::default(),
+        )
+        .unwrap_err();
+        assert!(error.is::<CommandNotFoundError<std::convert::Infallible>>());
+        assert!(error.to_string().contains("stipulate-nonexistent-command"));
+    }
+
+    #[test]
+    fn test_masked_output() {
+        let mut options = RunOptions::default();
+        options.set_masks(vec![Mask::new(
+            regex::Regex::new(r"/tmp/\S+").unwrap(),
+            String::from("<DIR>"),
+        )]);
+        assert_eq!(
+            test_output_against_strings(
+                "echo",
+                &["Saved to /tmp/stipulate-5678/result.txt".to_string()],
+                &HashMap::new(),
+                None,
+                "",
+                "Saved to /tmp/reference/result.txt\n",
+                "",
+                None,
+                &options
+            )
+            .unwrap(),
+            TestAnswer::Success
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nice() {
+        // Raising the niceness to the maximum is always permitted. This
+        // is best-effort, since `nice` (which prints the niceness it was
+        // started with) might not be installed.
+        let mut options = RunOptions::default();
+        options.set_nice(Some(19));
+        match test_output_against_strings(
+            "nice",
+            &[],
+            &HashMap::new(),
+            None,
+            "",
+            "19\n",
+            "",
+            None,
+            &options,
+        ) {
+            Ok(result) => assert_eq!(result, TestAnswer::Success),
+            Err(e) => assert!(e.is::<CommandNotFoundError<std::convert::Infallible>>()),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_peak_memory() {
+        let peak_memory = |program: &str| {
+            let args = vec![String::from("-c"), String::from(program)];
+            test_output_against_strings(
+                "python3",
+                &args,
+                &HashMap::new(),
+                None,
+                "",
+                "",
+                "",
+                Some(Duration::from_secs(10)),
+                &RunOptions::default(),
+            )
+            .unwrap()
+            .peak_memory_kb()
+            .unwrap()
+        };
+        let trivial = peak_memory("pass");
+        // This fills 100 MB, so it's all resident
+        let large = peak_memory("data = b'x' * (100 * 1024 * 1024)");
+        assert!(large > trivial + 50 * 1024, "{} vs {}", large, trivial);
+    }
+
+    #[test]
+    fn test_large_input_echoed() {
+        // Much more than fits in a pipe, so it's only read back out in
+        // time if it's read while it's being written
+        let input: String = (0..200_000).map(|i| format!("line {}\n", i)).collect();
+        assert!(input.len() > 2 * 1024 * 1024);
+        assert_eq!(
+            test_output_against_strings(
+                "cat",
+                &[],
+                &HashMap::new(),
+                None,
+                &input,
+                &input,
+                "",
+                Some(Duration::from_secs(10)),
+                &RunOptions::default()
+            )
+            .unwrap(),
+            TestAnswer::Success
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_vars() {
+        let mut env_vars = HashMap::new();
+        env_vars.insert(String::from("FOO"), String::from("bar"));
+        let script = vec![String::from("-c"), String::from("echo $FOO")];
+        assert_eq!(
+            test_output_against_strings(
+                "sh",
+                &script,
+                &env_vars,
+                None,
+                "",
+                "bar\n",
+                "",
+                None,
+                &RunOptions::default()
+            )
+            .unwrap(),
+            TestAnswer::Success
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clean_env() {
+        let mut env_vars = HashMap::new();
+        env_vars.insert(
+            String::from("STIPULATE_CONFIGURED_VAR"),
+            String::from("set"),
+        );
+        let script = vec![
+            String::from("-c"),
+            String::from("echo \"${HOME:-unset} $PATH $STIPULATE_CONFIGURED_VAR\""),
+        ];
+        let run = |expected: &str, options: &RunOptions| {
+            test_output_against_strings(
+                "sh", &script, &env_vars, None, "", expected, "", None, options,
+            )
+            .unwrap()
+        };
+        // The host's HOME and PATH are inherited by default
+        let mut options = RunOptions::default();
+        let host_env = format!(
+            "{} {} set\n",
+            std::env::var("HOME").unwrap_or_else(|_| String::from("unset")),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        assert_eq!(run(&host_env, &options), TestAnswer::Success);
+        // Only the configured variables and a minimal PATH are given
+        options.set_clean_env(true);
+        assert_eq!(
+            run(&format!("unset {} set\n", CLEAN_PATH), &options),
+            TestAnswer::Success
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_grace_period() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("cleaned-up");
+        // This cleans up when it's sent SIGTERM, but runs forever otherwise
+        let script = vec![
+            String::from("-c"),
+            format!(
+                "trap 'echo done > {:?}; exit 1' TERM; while true; do sleep 0.05; done",
+                marker
+            ),
+        ];
+        let timeout = Some(Duration::from_millis(300));
+        let mut options = RunOptions::default();
+        assert_eq!(
+            test_output_against_strings(
+                "sh",
+                &script,
+                &HashMap::new(),
+                None,
+                "",
+                "",
+                "",
+                timeout,
+                &options
+            )
+            .unwrap(),
+            TestAnswer::Timeout
+        );
+        assert!(!marker.exists());
+        options.set_kill_grace_period(Some(Duration::from_secs(5)));
+        assert_eq!(
+            test_output_against_strings(
+                "sh",
+                &script,
+                &HashMap::new(),
+                None,
+                "",
+                "",
+                "",
+                timeout,
+                &options
             )
             .unwrap(),
             TestAnswer::Timeout
         );
+        assert!(marker.exists());
     }
 }
@@ -0,0 +1,109 @@
+//! Fetching of test data which is stored on a remote server
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use errormake::errormake;
+
+/// Fetches the contents at each URL at most once per run, remembering
+/// what was already downloaded.
+#[derive(Default)]
+pub struct RemoteCache {
+    fetched: HashMap<String, String>,
+}
+impl RemoteCache {
+    /// Returns the body found at the given URL, downloading it if it
+    /// hasn't been fetched already.
+    pub fn fetch(&mut self, url: &str) -> Result<String, Box<dyn Error + 'static>> {
+        if let Some(body) = self.fetched.get(url) {
+            return Ok(body.clone());
+        }
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(RemoteTestError::with_description(format!(
+                "Expected output location isn't an http(s) URL: {}",
+                url
+            ))
+            .into());
+        }
+        let body = fetch_url(url)?;
+        self.fetched.insert(String::from(url), body.clone());
+        Ok(body)
+    }
+}
+
+/// Downloads the body at the given URL as a string.
+#[cfg(feature = "remote-tests")]
+fn fetch_url(url: &str) -> Result<String, Box<dyn Error + 'static>> {
+    let fetch_error = |e: &dyn std::fmt::Display| {
+        RemoteTestError::with_description(format!(
+            "Failed to fetch expected output from {}: {}",
+            url, e
+        ))
+    };
+    ureq::get(url)
+        .call()
+        .map_err(|e| fetch_error(&e))?
+        .into_string()
+        .map_err(|e| fetch_error(&e).into())
+}
+
+/// Without the "remote-tests" feature, nothing can be downloaded.
+#[cfg(not(feature = "remote-tests"))]
+fn fetch_url(url: &str) -> Result<String, Box<dyn Error + 'static>> {
+    Err(RemoteTestError::with_description(format!(
+        "Cannot fetch expected output from {}: stipulate was built without the \"remote-tests\" feature",
+        url
+    ))
+    .into())
+}
+
+errormake!(#[doc="An error fetching remote test data"] pub RemoteTestError);
+
+#[cfg(all(test, feature = "remote-tests"))]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves `body` over HTTP to the given number of requests, and
+    /// returns the URL to request it from.
+    fn serve(body: &'static str, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/case.out", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_fetch_from_server() {
+        let url = serve("Hello, world\n", 1);
+        let mut cache = RemoteCache::default();
+        assert_eq!(cache.fetch(&url).unwrap(), "Hello, world\n");
+        // The server only answers once, so this must come from the cache
+        assert_eq!(cache.fetch(&url).unwrap(), "Hello, world\n");
+    }
+
+    #[test]
+    fn test_fetch_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/case.out", listener.local_addr().unwrap());
+        drop(listener);
+        let error = RemoteCache::default().fetch(&url).unwrap_err();
+        assert!(error.to_string().contains(&url));
+    }
+}
@@ -0,0 +1,3 @@
+from helpers import double
+
+print(double(int(input())))